@@ -0,0 +1,76 @@
+use core::fmt;
+use core::ops::Deref;
+
+use crate::error::ConfigError;
+use crate::{MAX_ADDR_BYTES, MIN_ADDR_BYTES};
+
+/// An RX or TX address of 3 to 5 bytes, LSByte first as on the wire
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Address {
+    bytes: [u8; MAX_ADDR_BYTES],
+    len: u8,
+}
+
+impl Address {
+    /// Copy a slice of 3 to 5 bytes
+    pub fn new(bytes: &[u8]) -> Result<Self, ConfigError> {
+        if !(MIN_ADDR_BYTES..=MAX_ADDR_BYTES).contains(&bytes.len()) {
+            return Err(ConfigError::AddressLength(bytes.len()));
+        }
+        let mut address = Address {
+            bytes: [0; MAX_ADDR_BYTES],
+            len: bytes.len() as u8,
+        };
+        address.bytes[0..bytes.len()].copy_from_slice(bytes);
+        Ok(address)
+    }
+
+    pub(crate) fn from_array(bytes: [u8; MAX_ADDR_BYTES]) -> Self {
+        Address {
+            bytes,
+            len: MAX_ADDR_BYTES as u8,
+        }
+    }
+
+    /// The same address with another LSByte, as pipes 2 to 5 have
+    pub(crate) fn with_lsbyte(mut self, lsbyte: u8) -> Self {
+        self.bytes[0] = lsbyte;
+        self
+    }
+
+    /// Number of bytes
+    pub fn width(&self) -> u8 {
+        self.len
+    }
+
+    /// Content, LSByte first
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[0..self.len.into()]
+    }
+}
+
+impl AsRef<[u8]> for Address {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Deref for Address {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl fmt::Debug for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Address(")?;
+        for (i, byte) in self.as_bytes().iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{:02X}", byte)?;
+        }
+        write!(f, ")")
+    }
+}
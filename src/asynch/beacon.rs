@@ -0,0 +1,109 @@
+use core::fmt;
+
+use crate::asynch::config::Configuration;
+use crate::asynch::device::{Device, UsingDevice};
+use crate::asynch::standby::StandbyMode;
+use crate::beacon::MAX_TRANSMISSION_US;
+use crate::command::{FlushTx, Nop};
+use crate::registers::Status;
+use crate::timing::CE_PULSE_US;
+
+/// Retransmits one payload with `REUSE_TX_PL`
+///
+/// Async counterpart of [`BeaconMode`](../struct.BeaconMode.html).
+pub struct BeaconMode<D: Device> {
+    device: D,
+    /// CE has been raised since the last `TX_DS`
+    sending: bool,
+}
+
+impl<D: Device> fmt::Debug for BeaconMode<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BeaconMode")
+    }
+}
+
+impl<D: Device> UsingDevice<D> for BeaconMode<D> {
+    fn device(&mut self) -> &mut D {
+        &mut self.device
+    }
+}
+
+impl<D: Device> Configuration<D> for BeaconMode<D> {}
+
+impl<D: Device> BeaconMode<D> {
+    /// Relies on the payload being loaded by `StandbyMode::beacon()`,
+    /// from which it is called
+    pub(crate) fn new(device: D) -> Self {
+        BeaconMode {
+            device,
+            sending: false,
+        }
+    }
+
+    /// Wait for the last transmission, and drop the payload
+    pub async fn standby(mut self) -> Result<StandbyMode<D>, (D, D::Error)> {
+        match self.stop().await {
+            Ok(()) => StandbyMode::from_rx_tx(self.device),
+            Err(e) => Err((self.device, e)),
+        }
+    }
+
+    async fn stop(&mut self) -> Result<(), D::Error> {
+        if self.sending {
+            self.device.ce_disable()?;
+            self.device.delay_us(MAX_TRANSMISSION_US).await;
+            self.sending = false;
+        }
+        self.device.send_command(&FlushTx).await?;
+        let mut clear = Status(0);
+        clear.set_tx_ds(true);
+        self.device.write_register(clear).await?;
+        Ok(())
+    }
+
+    /// Send the payload once more
+    pub async fn pulse(&mut self) -> Result<(), D::Error> {
+        self.device.ce_enable()?;
+        self.device.delay_us(CE_PULSE_US).await;
+        self.device.ce_disable()?;
+        self.sending = true;
+        Ok(())
+    }
+
+    /// Wait for a `pulse()` to complete, sleeping on the IRQ pin
+    pub async fn wait_sent(&mut self) -> Result<(), D::Error> {
+        loop {
+            let (status, ()) = self.device.send_command(&Nop).await?;
+            if status.tx_ds() {
+                break;
+            }
+            self.device.wait_irq().await?;
+        }
+        let mut clear = Status(0);
+        clear.set_tx_ds(true);
+        self.device.write_register(clear).await?;
+        self.sending = false;
+        Ok(())
+    }
+
+    /// Send the payload `count` times, waiting `interval_us` between
+    /// the end of one transmission and the start of the next
+    pub async fn repeat(&mut self, count: u32, interval_us: u32) -> Result<(), D::Error> {
+        for i in 0..count {
+            if i > 0 {
+                self.device.delay_us(interval_us).await;
+            }
+            self.pulse().await?;
+            self.wait_sent().await?;
+        }
+        Ok(())
+    }
+
+    /// Hold CE high to send the payload back to back, until `standby()`
+    pub fn hold(&mut self) -> Result<(), D::Error> {
+        self.device.ce_enable()?;
+        self.sending = true;
+        Ok(())
+    }
+}
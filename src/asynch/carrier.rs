@@ -0,0 +1,64 @@
+use core::fmt;
+
+use crate::asynch::config::Configuration;
+use crate::asynch::device::{Device, UsingDevice};
+use crate::asynch::standby::StandbyMode;
+use crate::config::Channel;
+use crate::registers::{RfCh, RfSetup};
+use crate::timing::SETTLING_US;
+
+/// Emits an unmodulated carrier
+///
+/// Async counterpart of [`CarrierTestMode`](../struct.CarrierTestMode.html).
+pub struct CarrierTestMode<D: Device> {
+    device: D,
+    /// `RF_SETUP` before entering this mode
+    rf_setup: RfSetup,
+}
+
+impl<D: Device> fmt::Debug for CarrierTestMode<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CarrierTestMode")
+    }
+}
+
+impl<D: Device> UsingDevice<D> for CarrierTestMode<D> {
+    fn device(&mut self) -> &mut D {
+        &mut self.device
+    }
+}
+
+impl<D: Device> Configuration<D> for CarrierTestMode<D> {}
+
+impl<D: Device> CarrierTestMode<D> {
+    /// Relies on the carrier being started by
+    /// `StandbyMode::carrier_test()`, from which it is called
+    pub(crate) fn new(device: D, rf_setup: RfSetup) -> Self {
+        CarrierTestMode { device, rf_setup }
+    }
+
+    /// Stop the carrier, and restore `RF_SETUP`
+    pub async fn standby(mut self) -> Result<StandbyMode<D>, (D, D::Error)> {
+        let rf_setup = RfSetup(self.rf_setup.0);
+        let stop = async {
+            self.device.ce_disable()?;
+            self.device.write_register(rf_setup).await?;
+            Ok(())
+        };
+        match stop.await {
+            Ok(()) => StandbyMode::from_rx_tx(self.device),
+            Err(e) => Err((self.device, e)),
+        }
+    }
+
+    /// Move the carrier to another channel
+    pub async fn hop(&mut self, channel: Channel) -> Result<(), D::Error> {
+        let mut register = RfCh(0);
+        register.set_rf_ch(channel.get());
+        self.device.ce_disable()?;
+        self.device.write_register(register).await?;
+        self.device.ce_enable()?;
+        self.device.delay_us(SETTLING_US).await;
+        Ok(())
+    }
+}
@@ -0,0 +1,499 @@
+use crate::asynch::device::{Device, UsingDevice};
+use crate::asynch::variant;
+use crate::command::{FlushRx, FlushTx, Nop, ReadRawRegister, WriteRawRegister};
+use crate::address::Address;
+use crate::config::{
+    address, auto_retransmit, Channel, CrcMode, DataRate, Features, Interrupts, Pipe, PowerLevel,
+};
+use crate::radio_config::{PipeConfig, RadioConfig};
+use crate::registers::{
+    Config, Dynpd, EnAa, EnRxaddr, Feature, RfCh, RfSetup, RxAddrP0, RxAddrP1, SetupAw, SetupRetr,
+    Status, TxAddr,
+};
+use crate::variant::{check_bank1_register, ChipVariant};
+use crate::error::ConfigError;
+use crate::event::Events;
+use crate::{MAX_ADDR_BYTES, MIN_ADDR_BYTES, PIPES_COUNT};
+
+/// Async configuration methods
+///
+/// Counterpart of [`Configuration`](../trait.Configuration.html).
+#[allow(async_fn_in_trait)]
+pub trait Configuration<D>: UsingDevice<D>
+where
+    D: Device,
+{
+    /// Flush the RX queue, discarding any unread packets
+    async fn flush_rx(&mut self) -> Result<(), D::Error> {
+        self.device().send_command(&FlushRx).await?;
+        Ok(())
+    }
+
+    /// Flush TX queue, discarding any unsent packets
+    async fn flush_tx(&mut self) -> Result<(), D::Error> {
+        self.device().send_command(&FlushTx).await?;
+        Ok(())
+    }
+
+    /// Chip variant detected at construction, or set with
+    /// `set_variant()`
+    fn get_variant(&mut self) -> ChipVariant {
+        self.device().variant()
+    }
+
+    /// Override the detected chip variant, see
+    /// [`Configuration::set_variant()`](../trait.Configuration.html#method.set_variant)
+    async fn set_variant(&mut self, variant: ChipVariant) -> Result<(), D::Error> {
+        self.device().set_variant(variant);
+        if variant.has_bank1() {
+            variant::init_bank1(self.device()).await?;
+        }
+        if variant.needs_activate() {
+            variant::activate(self.device()).await?;
+        }
+        Ok(())
+    }
+
+    /// Get frequency offset (channel)
+    async fn get_frequency(&mut self) -> Result<u8, D::Error> {
+        let (_, register) = self.device().read_register::<RfCh>().await?;
+        Ok(register.rf_ch())
+    }
+
+    /// Set frequency offset (channel)
+    async fn set_frequency(&mut self, freq_offset: Channel) -> Result<(), D::Error> {
+        let mut register = RfCh(0);
+        register.set_rf_ch(freq_offset.get());
+        self.device().write_register(register).await?;
+        Ok(())
+    }
+
+    /// Set data rate and power level
+    async fn set_rf(&mut self, rate: &DataRate, power: PowerLevel) -> Result<(), D::Error> {
+        if *rate == DataRate::R250Kbps && !self.device().variant().has_250kbps() {
+            return Err(self.device().unsupported());
+        }
+        let mut register = RfSetup(0);
+        register.set_rf_pwr(power.get());
+
+        let (dr_low, dr_high) = match *rate {
+            DataRate::R250Kbps => (true, false),
+            DataRate::R1Mbps => (false, false),
+            DataRate::R2Mbps => (false, true),
+        };
+        register.set_rf_dr_low(dr_low);
+        register.set_rf_dr_high(dr_high);
+
+        self.device().write_register(register).await?;
+        Ok(())
+    }
+
+    /// Output power in dBm, see `ChipVariant::power_levels()`
+    async fn get_power_dbm(&mut self) -> Result<i8, D::Error> {
+        let variant = self.device().variant();
+        let (_, register) = self.device().read_register::<RfSetup>().await?;
+        let level = variant.power_level(&register);
+        Ok(variant.power_levels()[usize::from(level)])
+    }
+
+    /// Set the highest output power that does not exceed `dbm`, or
+    /// the lowest one, and return it
+    async fn set_power_dbm(&mut self, dbm: i8) -> Result<i8, D::Error> {
+        let variant = self.device().variant();
+        let level = variant.power_level_at_most(dbm);
+        self.device()
+            .update_register::<RfSetup, _, _>(|register| {
+                variant.set_power_level(register, level);
+            })
+            .await?;
+        Ok(variant.power_levels()[usize::from(level)])
+    }
+
+    /// Read register `addr` of the second register bank of Beken
+    /// parts into `buf`
+    ///
+    /// Fails with `Error::Unsupported` on the other variants, and
+    /// with `ConfigError::Register` or `ConfigError::RegisterLength`
+    /// for an `addr` above `0x1F` or a `buf` above 32 bytes.
+    async fn read_bank1_register(&mut self, addr: u8, buf: &mut [u8]) -> Result<(), D::Error> {
+        if !self.device().variant().has_bank1() {
+            return Err(self.device().unsupported());
+        }
+        if let Err(e) = check_bank1_register(addr, buf.len()) {
+            return Err(self.device().invalid_config(e));
+        }
+        variant::select_bank(self.device(), true).await?;
+        let result = self.device().send_command(&ReadRawRegister::new(addr, buf.len())).await;
+        variant::select_bank(self.device(), false).await?;
+        let (_, payload) = result?;
+        buf.copy_from_slice(&payload);
+        Ok(())
+    }
+
+    /// Write register `addr` of the second register bank of Beken
+    /// parts
+    ///
+    /// Fails like `read_bank1_register()`.
+    async fn write_bank1_register(&mut self, addr: u8, data: &[u8]) -> Result<(), D::Error> {
+        if !self.device().variant().has_bank1() {
+            return Err(self.device().unsupported());
+        }
+        if let Err(e) = check_bank1_register(addr, data.len()) {
+            return Err(self.device().invalid_config(e));
+        }
+        variant::select_bank(self.device(), true).await?;
+        let result = self.device().send_command(&WriteRawRegister::new(addr, data)).await;
+        variant::select_bank(self.device(), false).await?;
+        result?;
+        Ok(())
+    }
+
+    /// Set CRC mode
+    async fn set_crc(&mut self, mode: CrcMode) -> Result<(), D::Error> {
+        self.device().update_config(|config| mode.set_config(config)).await
+    }
+
+    /// Sets the interrupt mask
+    ///
+    /// The async modes sleep on the IRQ pin, so masking an interrupt
+    /// makes them miss the corresponding event.
+    async fn set_interrupt_mask(
+        &mut self,
+        data_ready_rx: bool,
+        data_sent_tx: bool,
+        max_retransmits_tx: bool,
+    ) -> Result<(), D::Error> {
+        self.device()
+            .update_config(|config| {
+                config.set_mask_rx_dr(data_ready_rx);
+                config.set_mask_tx_ds(data_sent_tx);
+                config.set_mask_max_rt(max_retransmits_tx);
+            })
+            .await
+    }
+
+    /// Configure which RX pipes to enable
+    async fn set_pipes_rx_enable(&mut self, bools: &[bool; PIPES_COUNT]) -> Result<(), D::Error> {
+        self.device().write_register(EnRxaddr::from_bools(bools)).await?;
+        Ok(())
+    }
+
+    /// Set address `addr` of pipe number `pipe_no`
+    ///
+    /// Pipes 2 to 5 only have the LSByte of their own, so the other
+    /// bytes of `addr` must be those of pipe 1.
+    async fn set_rx_addr(&mut self, pipe_no: Pipe, addr: &Address) -> Result<(), D::Error> {
+        let width = self.get_address_width().await?;
+        if addr.width() != width {
+            return Err(self.device().invalid_config(ConfigError::AddressLength(addr.len())));
+        }
+        match pipe_no.index() {
+            0 => {
+                self.device().write_register(RxAddrP0::new(addr)).await?;
+            }
+            1 => {
+                self.device().write_register(RxAddrP1::new(addr)).await?;
+            }
+            _ => {
+                let p1 = self.get_rx_addr(Pipe(1)).await?;
+                if p1[1..] != addr[1..] {
+                    let e = ConfigError::AddressPrefix(pipe_no.index());
+                    return Err(self.device().invalid_config(e));
+                }
+                self.set_rx_addr_suffix(pipe_no, addr[0]).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the LSByte of the address of pipe 2 to 5, which shares the
+    /// other bytes with pipe 1
+    async fn set_rx_addr_suffix(&mut self, pipe_no: Pipe, suffix: u8) -> Result<(), D::Error> {
+        macro_rules! w {
+            ( $($no: expr, $name: ident);+ ) => (
+                match pipe_no.index() {
+                    $(
+                        $no => {
+                            use crate::registers::$name;
+                            self.device().write_register($name(suffix)).await?;
+                        }
+                    )+
+                        _ => return Err(self.device().invalid_config(ConfigError::Pipe(pipe_no.index())))
+                }
+            )
+        }
+        w!(2, RxAddrP2;
+           3, RxAddrP3;
+           4, RxAddrP4;
+           5, RxAddrP5);
+        Ok(())
+    }
+
+    /// Set address of the TX pipe
+    async fn set_tx_addr(&mut self, addr: &Address) -> Result<(), D::Error> {
+        let width = self.get_address_width().await?;
+        if addr.width() != width {
+            return Err(self.device().invalid_config(ConfigError::AddressLength(addr.len())));
+        }
+        self.device().write_register(TxAddr::new(addr)).await?;
+        Ok(())
+    }
+
+    /// Configure auto-retransmit
+    ///
+    /// To disable, call as `set_auto_retransmit(0, 0)`.
+    async fn set_auto_retransmit(&mut self, delay: u8, count: u8) -> Result<(), D::Error> {
+        if delay > 0xF || count > 0xF {
+            return Err(self.device().invalid_config(ConfigError::AutoRetransmit(delay, count)));
+        }
+        self.device().write_register(auto_retransmit(delay, count)).await?;
+        Ok(())
+    }
+
+    /// Obtain auto-acknowledgment configuration for all pipes
+    async fn get_auto_ack(&mut self) -> Result<[bool; PIPES_COUNT], D::Error> {
+        let (_, register) = self.device().read_register::<EnAa>().await?;
+        Ok(register.to_bools())
+    }
+
+    /// Configure auto-acknowledgment for all RX pipes
+    async fn set_auto_ack(&mut self, bools: &[bool; PIPES_COUNT]) -> Result<(), D::Error> {
+        self.device().write_register(EnAa::from_bools(bools)).await?;
+        Ok(())
+    }
+
+    /// Set address width in bytes, `3..=5`
+    async fn set_address_width(&mut self, width: u8) -> Result<(), D::Error> {
+        if !(MIN_ADDR_BYTES..=MAX_ADDR_BYTES).contains(&usize::from(width)) {
+            return Err(self.device().invalid_config(ConfigError::AddressWidth(width)));
+        }
+        let mut register = SetupAw(0);
+        register.set_aw(width - 2);
+        self.device().write_register(register).await?;
+        Ok(())
+    }
+
+    /// Get address width configuration
+    async fn get_address_width(&mut self) -> Result<u8, D::Error> {
+        let (_, register) = self.device().read_register::<SetupAw>().await?;
+        Ok(2 + register.aw())
+    }
+
+    /// Get data rate and `RF_PWR`, see `set_rf()`
+    async fn get_rf(&mut self) -> Result<(DataRate, PowerLevel), D::Error> {
+        let (_, register) = self.device().read_register::<RfSetup>().await?;
+        Ok((DataRate::from_rf_setup(&register), PowerLevel::from_rf_setup(&register)))
+    }
+
+    /// Get CRC mode
+    async fn get_crc(&mut self) -> Result<CrcMode, D::Error> {
+        let (_, register) = self.device().read_register::<Config>().await?;
+        Ok(CrcMode::from_config(&register))
+    }
+
+    /// Get address of the TX pipe
+    async fn get_tx_addr(&mut self) -> Result<Address, D::Error> {
+        let width = self.get_address_width().await?;
+        let (_, register) = self.device().read_register::<TxAddr>().await?;
+        Ok(address(register.as_bytes(), width))
+    }
+
+    /// Get address of pipe number `pipe_no`
+    ///
+    /// Pipes 2 to 5 only have the LSByte of their own, and share the
+    /// other bytes with pipe 1.
+    async fn get_rx_addr(&mut self, pipe_no: Pipe) -> Result<Address, D::Error> {
+        let width = self.get_address_width().await?;
+        let (_, p1) = self.device().read_register::<RxAddrP1>().await?;
+        let mut bytes = [0; MAX_ADDR_BYTES];
+        bytes.copy_from_slice(&p1.as_bytes()[0..MAX_ADDR_BYTES]);
+        macro_rules! r {
+            ( $($no: expr, $name: ident);+ ) => (
+                match pipe_no.index() {
+                    0 => {
+                        let (_, register) = self.device().read_register::<RxAddrP0>().await?;
+                        bytes.copy_from_slice(&register.as_bytes()[0..MAX_ADDR_BYTES]);
+                    }
+                    $(
+                        $no => {
+                            use crate::registers::$name;
+                            let (_, register) = self.device().read_register::<$name>().await?;
+                            bytes[0] = register.0;
+                        }
+                    )+
+                    _ => {}
+                }
+            )
+        }
+        r!(2, RxAddrP2;
+           3, RxAddrP3;
+           4, RxAddrP4;
+           5, RxAddrP5);
+        Ok(address(&bytes, width))
+    }
+
+    /// Obtain which RX pipes are enabled
+    async fn get_pipes_rx_enable(&mut self) -> Result<[bool; PIPES_COUNT], D::Error> {
+        let (_, register) = self.device().read_register::<EnRxaddr>().await?;
+        Ok(register.to_bools())
+    }
+
+    /// Get auto-retransmit as `(delay, count)`
+    async fn get_auto_retransmit(&mut self) -> Result<(u8, u8), D::Error> {
+        let (_, register) = self.device().read_register::<SetupRetr>().await?;
+        Ok((register.ard(), register.arc()))
+    }
+
+    /// Get the flags of the `FEATURE` register
+    async fn get_features(&mut self) -> Result<Features, D::Error> {
+        let (_, register) = self.device().read_register::<Feature>().await?;
+        Ok(Features::from_register(&register))
+    }
+
+    /// Read the complete configuration, without validation
+    async fn get_config(&mut self) -> Result<RadioConfig, D::Error> {
+        let (data_rate, _) = self.get_rf().await?;
+        let (delay, count) = self.get_auto_retransmit().await?;
+        let enabled = self.get_pipes_rx_enable().await?;
+        let auto_ack = self.get_auto_ack().await?;
+        let lengths = self.get_pipes_rx_lengths().await?;
+        let features = self.get_features().await?;
+        let mut builder = RadioConfig::builder()
+            .rf_ch(self.get_frequency().await?)
+            .data_rate(data_rate)
+            .power_dbm(self.get_power_dbm().await?)
+            .crc(self.get_crc().await?)
+            .address_width(self.get_address_width().await?)
+            .auto_retransmit(delay, count)
+            .tx_addr(&self.get_tx_addr().await?)
+            .dynamic_payloads(features.dynamic_payloads)
+            .ack_payloads(features.ack_payloads)
+            .dynamic_ack(features.dynamic_ack);
+        for index in 0..PIPES_COUNT {
+            let pipe = Pipe(index as u8);
+            let addr = self.get_rx_addr(pipe).await?;
+            builder = match index {
+                0 | 1 => builder.rx_addr(pipe, &addr),
+                _ => builder.rx_addr_suffix(pipe, addr[0]),
+            };
+            builder = builder.pipe(pipe, PipeConfig {
+                enabled: enabled[index],
+                auto_ack: auto_ack[index],
+                length: lengths[index],
+            });
+        }
+        Ok(builder.unchecked())
+    }
+
+    /// Obtain interrupt pending status as `(RX_DR, TX_DR, MAX_RT)`
+    async fn get_interrupts(&mut self) -> Result<(bool, bool, bool), D::Error> {
+        let (status, ()) = self.device().send_command(&Nop).await?;
+        Ok((status.rx_dr(), status.tx_ds(), status.max_rt()))
+    }
+
+    /// Clear interrupts, and return the interrupts set before clearing
+    async fn clear_interrupts(&mut self) -> Result<Interrupts, D::Error> {
+        let mut clear = Status(0);
+        clear.set_rx_dr(true);
+        clear.set_tx_ds(true);
+        clear.set_max_rt(true);
+        let status = self.device().write_register(clear).await?;
+        Ok(Interrupts {
+            rx_dr: status.rx_dr(),
+            tx_ds: status.tx_ds(),
+            max_rt: status.max_rt(),
+        })
+    }
+
+    /// Clear the interrupt flags that are set, and return them as
+    /// events
+    ///
+    /// Only the flags that have been read are cleared.
+    async fn poll_events(&mut self) -> Result<Events, D::Error> {
+        let device = self.device();
+        let (status, ()) = device.send_command(&Nop).await?;
+        let mut clear = Status(0);
+        clear.set_rx_dr(status.rx_dr());
+        clear.set_tx_ds(status.tx_ds());
+        clear.set_max_rt(status.max_rt());
+        if clear.0 != 0 {
+            device.write_register(clear).await?;
+        }
+        Ok(Events::from_status(&status, device.config().prim_rx()))
+    }
+
+    /// Sleep until the **IRQ** pin is asserted, and then
+    /// [`poll_events()`](#method.poll_events)
+    async fn wait_events(&mut self) -> Result<Events, D::Error> {
+        self.device().wait_irq().await?;
+        self.poll_events().await
+    }
+
+    /// Obtain payload lengths of all RX pipes
+    ///
+    /// * `None`: Dynamic payload length
+    /// * `Some(len)`: Static payload length `len`
+    async fn get_pipes_rx_lengths(&mut self) -> Result<[Option<u8>; PIPES_COUNT], D::Error> {
+        let (_, dynpd) = self.device().read_register::<Dynpd>().await?;
+        let mut lengths = [None; PIPES_COUNT];
+        macro_rules! get_rx_pw {
+            ($name: ident, $index: expr) => {{
+                use crate::registers::$name;
+                let (_, register) = self.device().read_register::<$name>().await?;
+                if !dynpd.dpl_p($index) {
+                    lengths[$index] = Some(register.get());
+                }
+            }};
+        }
+        get_rx_pw!(RxPwP0, 0);
+        get_rx_pw!(RxPwP1, 1);
+        get_rx_pw!(RxPwP2, 2);
+        get_rx_pw!(RxPwP3, 3);
+        get_rx_pw!(RxPwP4, 4);
+        get_rx_pw!(RxPwP5, 5);
+
+        Ok(lengths)
+    }
+
+    /// ## `bools`
+    /// * `None`: Dynamic payload length
+    /// * `Some(len)`: Static payload length `len`
+    async fn set_pipes_rx_lengths(&mut self, lengths: &[Option<u8>; PIPES_COUNT]) -> Result<(), D::Error> {
+        if let Some(len) = lengths.iter().flatten().find(|len| **len > 32) {
+            return Err(self.device().invalid_config(ConfigError::PayloadLength(*len)));
+        }
+        // Enable dynamic payload lengths
+        let mut bools = [true; PIPES_COUNT];
+        for (i, length) in lengths.iter().enumerate() {
+            bools[i] = length.is_none();
+        }
+        let dynpd = Dynpd::from_bools(&bools);
+        if dynpd.0 != 0 {
+            self.device()
+                .update_register::<Feature, _, _>(|feature| {
+                    feature.set_en_dpl(true);
+                })
+                .await?;
+        }
+        self.device().write_register(dynpd).await?;
+
+        // Set static payload lengths
+        macro_rules! set_rx_pw {
+            ($name: ident, $index: expr) => {{
+                use crate::registers::$name;
+                let length = lengths[$index].unwrap_or(0);
+                let mut register = $name(0);
+                register.set(length);
+                self.device().write_register(register).await?;
+            }};
+        }
+        set_rx_pw!(RxPwP0, 0);
+        set_rx_pw!(RxPwP1, 1);
+        set_rx_pw!(RxPwP2, 2);
+        set_rx_pw!(RxPwP3, 3);
+        set_rx_pw!(RxPwP4, 4);
+        set_rx_pw!(RxPwP5, 5);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use crate::command::Command;
+use crate::error::ConfigError;
+use crate::registers::{Config, Register, Status};
+use crate::variant::ChipVariant;
+
+/// Async counterpart of [`Device`](../trait.Device.html)
+///
+/// Hides all the GPIO/SPI type parameters for use by the async
+/// operation modes.
+#[allow(async_fn_in_trait)]
+pub trait Device {
+    /// Error from the SPI or GPIO implementation
+    type Error;
+
+    /// Chip variant detected at construction
+    fn variant(&self) -> ChipVariant;
+    /// Override the detected variant
+    fn set_variant(&mut self, variant: ChipVariant);
+    /// Error for an operation that `variant()` does not support
+    fn unsupported(&self) -> Self::Error;
+    /// Error for an invalid configuration value
+    fn invalid_config(&self, e: ConfigError) -> Self::Error;
+    /// Error for a received payload width above 32 bytes
+    fn corrupt_payload(&self, width: u8) -> Self::Error;
+    /// Error for a transmission that did not complete in time
+    fn timeout(&self) -> Self::Error;
+    /// Error for mixing the send methods of `PtxMode`
+    fn busy(&self) -> Self::Error;
+
+    /// Set CE pin high
+    fn ce_enable(&mut self) -> Result<(), Self::Error>;
+    /// Set CE pin low
+    fn ce_disable(&mut self) -> Result<(), Self::Error>;
+    /// Wait with the delay provider given at construction
+    async fn delay_us(&mut self, us: u32);
+
+    /// Sleep until the IRQ pin is asserted (low)
+    async fn wait_irq(&mut self) -> Result<(), Self::Error>;
+
+    /// Send a command via SPI
+    async fn send_command<C: Command>(&mut self, command: &C) -> Result<(Status, C::Response), Self::Error>;
+    /// Send `W_REGISTER` command
+    async fn write_register<R: Register>(&mut self, register: R) -> Result<Status, Self::Error>;
+    /// Send `R_REGISTER` command
+    async fn read_register<R: Register>(&mut self) -> Result<(Status, R), Self::Error>;
+
+    /// Read, and modify a register, and write it back if it has been changed.
+    async fn update_register<Reg, F, R>(&mut self, f: F) -> Result<R, Self::Error>
+    where
+        Reg: Register + PartialEq + Clone,
+        F: FnOnce(&mut Reg) -> R,
+    {
+        // Use `update_config()` for `registers::Config`
+        assert!(Reg::addr() != 0x00);
+
+        let (_, old_register) = self.read_register::<Reg>().await?;
+        let mut register = old_register.clone();
+        let result = f(&mut register);
+
+        if register != old_register {
+            self.write_register(register).await?;
+        }
+        Ok(result)
+    }
+
+    /// The (cached) `CONFIG` register
+    fn config(&self) -> &Config;
+    /// Modify the (cached) `CONFIG` register and write if it has changed.
+    async fn update_config<F, R>(&mut self, f: F) -> Result<R, Self::Error>
+    where
+        F: FnOnce(&mut Config) -> R;
+}
+
+pub trait UsingDevice<D: Device> {
+    fn device(&mut self) -> &mut D;
+}
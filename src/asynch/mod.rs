@@ -0,0 +1,289 @@
+//! Async driver on [embedded-hal-async](https://crates.io/crates/embedded-hal-async)
+//!
+//! Mirrors the blocking API with the same typestate transitions, but
+//! sleeps on the **IRQ** pin instead of polling over SPI.
+
+use core::fmt;
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::digital::Wait;
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::command::{Command, ReadRegister, WriteRegister};
+use crate::registers::{Config, Register, SetupAw, Status};
+use crate::timing::NoDelay;
+use crate::{ChipVariant, ConfigError, Error};
+
+mod config;
+pub use self::config::Configuration;
+mod device;
+pub use self::device::Device;
+mod standby;
+pub use self::standby::StandbyMode;
+mod rx;
+pub use self::rx::RxMode;
+mod tx;
+pub use self::tx::TxMode;
+mod ptx;
+pub use self::ptx::PtxMode;
+mod prx;
+pub use self::prx::PrxMode;
+mod beacon;
+pub use self::beacon::BeaconMode;
+mod carrier;
+pub use self::carrier::CarrierTestMode;
+mod powerdown;
+pub use self::powerdown::PowerDownMode;
+mod radio_config;
+mod variant;
+
+/// Async driver for the nRF24L01+
+///
+/// Never deal with this directly. Instead, you store one of the following types:
+///
+/// * [`StandbyMode<D>`](struct.StandbyMode.html)
+/// * [`RxMode<D>`](struct.RxMode.html)
+/// * [`TxMode<D>`](struct.TxMode.html)
+/// * [`PtxMode<D>`](struct.PtxMode.html)
+/// * [`PrxMode<D>`](struct.PrxMode.html)
+/// * [`BeaconMode<D>`](struct.BeaconMode.html)
+/// * [`CarrierTestMode<D>`](struct.CarrierTestMode.html)
+/// * [`PowerDownMode<D>`](struct.PowerDownMode.html)
+///
+/// where `D: `[`Device`](trait.Device.html)
+pub struct NRF24L01<CE, IRQ, SPI, DELAY = NoDelay>
+where
+    CE: OutputPin,
+    IRQ: Wait,
+    SPI: SpiDevice<u8>,
+    DELAY: DelayNs,
+{
+    ce: CE,
+    irq: IRQ,
+    spi: SPI,
+    delay: DELAY,
+    config: Config,
+    variant: ChipVariant,
+}
+
+impl<CE, IRQ, SPI, DELAY> fmt::Debug for NRF24L01<CE, IRQ, SPI, DELAY>
+where
+    CE: OutputPin,
+    IRQ: Wait,
+    SPI: SpiDevice<u8>,
+    DELAY: DelayNs,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NRF24L01")
+    }
+}
+
+impl<CE, IRQ, SPI> NRF24L01<CE, IRQ, SPI>
+where
+    CE: OutputPin,
+    IRQ: Wait,
+    SPI: SpiDevice<u8>,
+{
+    /// Construct a new driver instance.
+    ///
+    /// `spi` is expected to drive the **CSN** pin itself. `irq` is the
+    /// active-low **IRQ** pin of the chip.
+    pub async fn new(
+        ce: CE,
+        irq: IRQ,
+        spi: SPI,
+    ) -> Result<StandbyMode<Self>, Error<SPI::Error, CE::Error, IRQ::Error>> {
+        Self::new_with_delay(ce, irq, spi, NoDelay).await
+    }
+
+    /// Construct a new driver instance, leaving the chip powered down
+    pub async fn new_powered_down(
+        ce: CE,
+        irq: IRQ,
+        spi: SPI,
+    ) -> Result<PowerDownMode<Self>, Error<SPI::Error, CE::Error, IRQ::Error>> {
+        Self::new_powered_down_with_delay(ce, irq, spi, NoDelay).await
+    }
+}
+
+impl<CE, IRQ, SPI, DELAY> NRF24L01<CE, IRQ, SPI, DELAY>
+where
+    CE: OutputPin,
+    IRQ: Wait,
+    SPI: SpiDevice<u8>,
+    DELAY: DelayNs,
+{
+    /// Construct a new driver instance that waits with `delay`
+    pub async fn new_with_delay(
+        ce: CE,
+        irq: IRQ,
+        spi: SPI,
+        delay: DELAY,
+    ) -> Result<StandbyMode<Self>, Error<SPI::Error, CE::Error, IRQ::Error>> {
+        let device = Self::init(ce, irq, spi, delay).await?;
+        StandbyMode::power_up(device).await.map_err(|(_, e)| e)
+    }
+
+    /// Construct a new driver instance that waits with `delay`,
+    /// leaving the chip powered down
+    pub async fn new_powered_down_with_delay(
+        ce: CE,
+        irq: IRQ,
+        spi: SPI,
+        delay: DELAY,
+    ) -> Result<PowerDownMode<Self>, Error<SPI::Error, CE::Error, IRQ::Error>> {
+        let mut device = Self::init(ce, irq, spi, delay).await?;
+        let config = device.config.clone();
+        device.write_register(config).await?;
+        Ok(PowerDownMode::new(device))
+    }
+
+    async fn init(
+        mut ce: CE,
+        irq: IRQ,
+        spi: SPI,
+        delay: DELAY,
+    ) -> Result<Self, Error<SPI::Error, CE::Error, IRQ::Error>> {
+        ce.set_low().map_err(Error::GpioError)?;
+
+        // Reset value
+        let mut config = Config(0b0000_1000);
+        config.set_mask_rx_dr(false);
+        config.set_mask_tx_ds(false);
+        config.set_mask_max_rt(false);
+        let mut device = NRF24L01 {
+            ce,
+            irq,
+            spi,
+            delay,
+            config,
+            variant: ChipVariant::Nrf24l01Plus,
+        };
+
+        // A Beken part may have been left in bank 1
+        variant::select_bank(&mut device, false).await?;
+
+        match device.is_connected().await {
+            Err(e) => return Err(e),
+            Ok(false) => return Err(Error::NotConnected),
+            _ => {}
+        }
+
+        device.variant = variant::detect(&mut device).await?;
+        if device.variant.has_bank1() {
+            variant::init_bank1(&mut device).await?;
+        }
+        if device.variant.needs_activate() {
+            variant::activate(&mut device).await?;
+        }
+
+        Ok(device)
+    }
+
+    /// Reads and validates content of the `SETUP_AW` register.
+    pub async fn is_connected(&mut self) -> Result<bool, Error<SPI::Error, CE::Error, IRQ::Error>> {
+        let (_, setup_aw) = self.read_register::<SetupAw>().await?;
+        let valid = setup_aw.aw() >= 1 && setup_aw.aw() <= 3 && setup_aw.0 & 0xFC == 0;
+        Ok(valid)
+    }
+}
+
+impl<CE, IRQ, SPI, DELAY> Device for NRF24L01<CE, IRQ, SPI, DELAY>
+where
+    CE: OutputPin,
+    IRQ: Wait,
+    SPI: SpiDevice<u8>,
+    DELAY: DelayNs,
+{
+    type Error = Error<SPI::Error, CE::Error, IRQ::Error>;
+
+    fn variant(&self) -> ChipVariant {
+        self.variant
+    }
+
+    fn set_variant(&mut self, variant: ChipVariant) {
+        self.variant = variant;
+    }
+
+    fn unsupported(&self) -> Self::Error {
+        Error::Unsupported
+    }
+
+    fn invalid_config(&self, e: ConfigError) -> Self::Error {
+        Error::Config(e)
+    }
+
+    fn corrupt_payload(&self, width: u8) -> Self::Error {
+        Error::CorruptPayload(width)
+    }
+
+    fn timeout(&self) -> Self::Error {
+        Error::Timeout
+    }
+
+    fn busy(&self) -> Self::Error {
+        Error::Busy
+    }
+
+    fn ce_enable(&mut self) -> Result<(), Self::Error> {
+        self.ce.set_high().map_err(Error::GpioError)
+    }
+
+    fn ce_disable(&mut self) -> Result<(), Self::Error> {
+        self.ce.set_low().map_err(Error::GpioError)
+    }
+
+    async fn delay_us(&mut self, us: u32) {
+        self.delay.delay_us(us).await;
+    }
+
+    async fn wait_irq(&mut self) -> Result<(), Self::Error> {
+        self.irq.wait_for_low().await.map_err(Error::IrqError)
+    }
+
+    async fn send_command<C: Command>(&mut self, command: &C) -> Result<(Status, C::Response), Self::Error> {
+        // Allocate storage
+        let mut buf_storage = [0; 33];
+        let len = command.len();
+        let buf = &mut buf_storage[0..len];
+        // Serialize the command
+        command.encode(buf);
+
+        // SPI transaction, CSN is asserted by the `SpiDevice`
+        self.spi.transfer_in_place(buf).await.map_err(Error::SpiError)?;
+
+        // Parse response
+        let status = Status(buf[0]);
+        let response = C::decode_response(buf);
+
+        Ok((status, response))
+    }
+
+    async fn write_register<R: Register>(&mut self, register: R) -> Result<Status, Self::Error> {
+        let (status, ()) = self.send_command(&WriteRegister::new(register)).await?;
+        Ok(status)
+    }
+
+    async fn read_register<R: Register>(&mut self) -> Result<(Status, R), Self::Error> {
+        self.send_command(&ReadRegister::new()).await
+    }
+
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    async fn update_config<F, R>(&mut self, f: F) -> Result<R, Self::Error>
+    where
+        F: FnOnce(&mut Config) -> R,
+    {
+        // Mutate
+        let old_config = self.config.clone();
+        let result = f(&mut self.config);
+
+        if self.config != old_config {
+            let config = self.config.clone();
+            self.write_register(config).await?;
+        }
+        Ok(result)
+    }
+}
@@ -0,0 +1,39 @@
+use core::fmt;
+
+use crate::asynch::config::Configuration;
+use crate::asynch::device::{Device, UsingDevice};
+use crate::asynch::standby::StandbyMode;
+
+/// Represents **Power Down** mode
+///
+/// Async counterpart of [`PowerDownMode`](../struct.PowerDownMode.html).
+pub struct PowerDownMode<D: Device> {
+    device: D,
+}
+
+impl<D: Device> fmt::Debug for PowerDownMode<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PowerDownMode")
+    }
+}
+
+impl<D: Device> UsingDevice<D> for PowerDownMode<D> {
+    fn device(&mut self) -> &mut D {
+        &mut self.device
+    }
+}
+
+impl<D: Device> Configuration<D> for PowerDownMode<D> {}
+
+impl<D: Device> PowerDownMode<D> {
+    /// Relies on `PWR_UP` being cleared by the caller
+    pub(crate) fn new(device: D) -> Self {
+        PowerDownMode { device }
+    }
+
+    /// Power up into Standby-I, and wait for the 1.5 ms start-up time
+    /// with the delay provider of the driver
+    pub async fn power_up(self) -> Result<StandbyMode<D>, (D, D::Error)> {
+        StandbyMode::power_up(self.device).await
+    }
+}
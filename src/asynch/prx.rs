@@ -0,0 +1,123 @@
+use core::fmt;
+
+use crate::asynch::config::Configuration;
+use crate::asynch::device::{Device, UsingDevice};
+use crate::asynch::rx::read_payload;
+use crate::asynch::standby::StandbyMode;
+use crate::config::Pipe;
+use crate::command::{FlushTx, WriteAckPayload};
+use crate::payload;
+use crate::registers::{FifoStatus, Status};
+use crate::rxtx::{AckQueue, Received};
+use crate::PIPES_COUNT;
+
+/// In PRX mode, the device receives packets and answers them with
+/// ACK payloads.
+///
+/// Async counterpart of [`PrxMode`](../struct.PrxMode.html).
+pub struct PrxMode<D: Device> {
+    device: D,
+    acks: AckQueue,
+}
+
+impl<D: Device> fmt::Debug for PrxMode<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PrxMode")
+    }
+}
+
+impl<D: Device> UsingDevice<D> for PrxMode<D> {
+    fn device(&mut self) -> &mut D {
+        &mut self.device
+    }
+}
+
+impl<D: Device> Configuration<D> for PrxMode<D> {}
+
+impl<D: Device> PrxMode<D> {
+    /// Relies on everything being set up by `StandbyMode::prx()`, from
+    /// which it is called
+    pub(crate) fn new(device: D) -> Self {
+        PrxMode {
+            device,
+            acks: AckQueue::new(),
+        }
+    }
+
+    /// Disable `CE` so that you can switch into another mode, flushing
+    /// ACK payloads that have not been delivered.
+    pub async fn standby(mut self) -> Result<StandbyMode<D>, (D, D::Error)> {
+        if let Err(e) = self.device.send_command(&FlushTx).await {
+            return Err((self.device, e));
+        }
+        StandbyMode::from_rx_tx(self.device)
+    }
+
+    /// Queue an ACK payload for the next packet on `pipe`
+    ///
+    /// Returns `false` if the TX FIFO already holds 3 ACK payloads.
+    /// Fails with `ConfigError::PayloadLength` for a `payload` above
+    /// 32 bytes.
+    pub async fn queue_ack_payload(&mut self, pipe: Pipe, payload: &[u8]) -> Result<bool, D::Error> {
+        if let Err(e) = payload::check_len(payload) {
+            return Err(self.device.invalid_config(e));
+        }
+        let pipe = pipe.index() as u8;
+
+        if self.acks.is_full() {
+            let (_, fifo_status) = self.device.read_register::<FifoStatus>().await?;
+            self.acks.update(false, fifo_status.tx_empty(), false);
+            if self.acks.is_full() {
+                return Ok(false);
+            }
+        }
+        self.device.send_command(&WriteAckPayload::new(pipe, payload)).await?;
+        self.acks.push(pipe);
+        Ok(true)
+    }
+
+    /// Number of queued ACK payloads that have not been reported as
+    /// delivered yet
+    pub fn pending_ack_payloads(&self) -> usize {
+        self.acks.len()
+    }
+
+    /// Number of ACK payloads per pipe that have been delivered since
+    /// the last call
+    pub fn take_delivered(&mut self) -> [u8; PIPES_COUNT] {
+        self.acks.take_delivered()
+    }
+
+    /// Sleep until a packet has been received
+    ///
+    /// Acknowledges all interrupts, like the blocking
+    /// [`PrxMode::read`](../struct.PrxMode.html#method.read).
+    pub async fn read(&mut self) -> Result<Received, D::Error> {
+        loop {
+            let (status, fifo_status) = self.device.read_register::<FifoStatus>().await?;
+            let pending = status.rx_dr() || status.tx_ds() || status.max_rt();
+            if pending {
+                let mut clear = Status(0);
+                clear.set_rx_dr(true);
+                clear.set_tx_ds(true);
+                clear.set_max_rt(true);
+                self.device.write_register(clear).await?;
+            }
+            self.acks
+                .update(status.tx_ds(), fifo_status.tx_empty(), fifo_status.rx_empty());
+            if fifo_status.rx_empty() {
+                // Check again after clearing, as a packet arriving in
+                // between does not assert the IRQ pin.
+                if !pending {
+                    self.device.wait_irq().await?;
+                }
+                continue;
+            }
+
+            let pipe = status.rx_p_no();
+            let payload = read_payload(&mut self.device, pipe).await?;
+            self.acks.received(pipe);
+            return Ok(Received { pipe, payload });
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use core::fmt;
+
+use crate::asynch::config::Configuration;
+use crate::asynch::device::{Device, UsingDevice};
+use crate::asynch::rx::read_payload;
+use crate::asynch::standby::StandbyMode;
+use crate::command::{FlushTx, Nop, WriteTxPayload, WriteTxPayloadNoAck};
+use crate::payload;
+use crate::asynch::tx::handle_max_rt;
+use crate::ptx::PtxRegisters;
+use crate::registers::{Feature, FifoStatus, ObserveTx, Status};
+use crate::rxtx::{MaxRtPolicy, Outcome, PacketId, PacketQueue, Received, SendReceiveResult, TxShadow};
+use crate::PIPES_COUNT;
+
+/// Async counterpart of `PtxRegisters::read()`
+pub(crate) async fn read_registers<D: Device>(device: &mut D) -> Result<PtxRegisters, D::Error> {
+    let (_, en_aa) = device.read_register().await?;
+    let (_, setup_retr) = device.read_register().await?;
+    let (_, feature) = device.read_register().await?;
+    let (_, dynpd) = device.read_register().await?;
+    Ok(PtxRegisters { en_aa, setup_retr, feature, dynpd })
+}
+
+/// Async counterpart of `PtxRegisters::write()`
+pub(crate) async fn write_registers<D: Device>(device: &mut D, saved: &PtxRegisters) -> Result<(), D::Error> {
+    device.write_register(saved.en_aa.clone()).await?;
+    device.write_register(saved.setup_retr.clone()).await?;
+    // FEATURE enables DYNPD
+    device.write_register(saved.feature.clone()).await?;
+    device.write_register(saved.dynpd.clone()).await?;
+    Ok(())
+}
+
+/// In PTX mode, the device transmits packets immediately, and receives packets
+/// only as acknowledge payloads.
+///
+/// Async counterpart of [`PtxMode`](../struct.PtxMode.html).
+pub struct PtxMode<D: Device> {
+    device: D,
+    /// `EN_DYN_ACK` has been set
+    dyn_ack: bool,
+    /// Registers before entering this mode
+    saved: PtxRegisters,
+    /// Packets from `send()`
+    queue: PacketQueue,
+    /// Packets from `send_receive()` and `send_no_ack()`, and the
+    /// `MAX_RT` policy
+    shadow: TxShadow,
+}
+
+impl<D: Device> fmt::Debug for PtxMode<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PtxMode")
+    }
+}
+
+impl<D: Device> UsingDevice<D> for PtxMode<D> {
+    fn device(&mut self) -> &mut D {
+        &mut self.device
+    }
+}
+
+impl<D: Device> Configuration<D> for PtxMode<D> {}
+
+impl<D: Device> PtxMode<D> {
+    /// Relies on everything being set up by `StandbyMode::ptx()`, from which it is called
+    pub(crate) fn new(device: D, saved: PtxRegisters) -> Self {
+        PtxMode {
+            device,
+            dyn_ack: false,
+            saved,
+            queue: PacketQueue::new(),
+            shadow: TxShadow::new(),
+        }
+    }
+
+    /// Select what happens when a packet from `send_receive()` is not
+    /// acknowledged after the maximum number of retransmits
+    pub fn set_max_rt_policy(&mut self, policy: MaxRtPolicy) {
+        self.shadow.set_policy(policy);
+    }
+
+    /// Wait until the TX FIFO is empty, sleeping on the IRQ pin, and
+    /// disable `CE`
+    ///
+    /// Behaves like the blocking
+    /// [`PtxMode::standby`](../struct.PtxMode.html#method.standby).
+    pub async fn standby(mut self) -> Result<StandbyMode<D>, (D, D::Error)> {
+        match self.wait_empty().await {
+            Ok(()) => StandbyMode::from_rx_tx(self.device),
+            Err(e) => Err((self.device, e)),
+        }
+    }
+
+    /// Like `standby()`, and restore the registers that
+    /// `StandbyMode::ptx()` changed
+    pub async fn standby_and_restore(mut self) -> Result<StandbyMode<D>, (D, D::Error)> {
+        let restore = async {
+            self.wait_empty().await?;
+            write_registers(&mut self.device, &self.saved).await
+        };
+        match restore.await {
+            Ok(()) => StandbyMode::from_rx_tx(self.device),
+            Err(e) => Err((self.device, e)),
+        }
+    }
+
+    async fn wait_empty(&mut self) -> Result<(), D::Error> {
+        while self.wait_outcome().await?.is_some() {}
+        loop {
+            let (status, ()) = self.device.send_command(&Nop).await?;
+            if status.max_rt() {
+                // The packet stays in the FIFO until the policy applies
+                handle_max_rt(&mut self.device, &status, &mut self.shadow).await?;
+                continue;
+            }
+            // Release the IRQ pin before sleeping on it
+            let mut clear = Status(0);
+            clear.set_rx_dr(true);
+            clear.set_tx_ds(true);
+            self.device.write_register(clear).await?;
+            self.shadow.observe(&status);
+            let (_, fifo_status) = self.device.read_register::<FifoStatus>().await?;
+            if fifo_status.tx_empty() {
+                return Ok(());
+            }
+            self.device.ce_enable()?;
+            self.device.wait_irq().await?;
+        }
+    }
+
+    /// Queue a packet, and obtain a token for its outcome
+    ///
+    /// Behaves like the blocking
+    /// [`PtxMode::send`](../struct.PtxMode.html#method.send).
+    pub async fn send(&mut self, payload: &[u8]) -> Result<Option<PacketId>, D::Error> {
+        if let Err(e) = payload::check_len(payload) {
+            return Err(self.device.invalid_config(e));
+        }
+        if self.queue.is_full() {
+            return Ok(None);
+        }
+        if self.queue.pending() == 0 {
+            self.check_idle().await?;
+        }
+        let id = self.queue.push(payload);
+        self.start_next().await?;
+        Ok(Some(id))
+    }
+
+    /// Number of packets from `send()` whose outcome has not been
+    /// reported yet
+    pub fn pending(&self) -> usize {
+        self.queue.pending()
+    }
+
+    /// Sleep on the IRQ pin until the packet in the chip has an
+    /// outcome, or return `None` if no packet is pending
+    pub async fn wait_outcome(&mut self) -> Result<Option<Outcome>, D::Error> {
+        while self.queue.in_flight().is_some() {
+            if let Some(outcome) = self.poll_outcome().await? {
+                return Ok(Some(outcome));
+            }
+            self.device.wait_irq().await?;
+        }
+        Ok(None)
+    }
+
+    /// Check the outcome of the packet in the chip, without sleeping
+    ///
+    /// After an ACK payload fails with `Error::CorruptPayload`, the
+    /// next call reports the packet as delivered without it.
+    pub async fn poll_outcome(&mut self) -> Result<Option<Outcome>, D::Error> {
+        let id = match self.queue.in_flight() {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        // OBSERVE_TX is reset when the next packet starts
+        let (status, observe_tx) = self.device.read_register::<ObserveTx>().await?;
+        let mut clear = Status(0);
+        let outcome = if status.tx_ds() {
+            // RX_P_NO is 0b111 when the RX FIFO has been flushed
+            let ack_payload = if status.rx_dr() && usize::from(status.rx_p_no()) < PIPES_COUNT {
+                Some(read_payload(&mut self.device, status.rx_p_no()).await?)
+            } else {
+                None
+            };
+            clear.set_tx_ds(true);
+            clear.set_rx_dr(true);
+            self.shadow.observe(&status);
+            Outcome::Delivered {
+                id,
+                retransmits: observe_tx.arc_cnt(),
+                ack_payload,
+            }
+        } else if status.max_rt() {
+            // The packet stays in the FIFO until it is flushed
+            self.device.send_command(&FlushTx).await?;
+            clear.set_max_rt(true);
+            Outcome::Dropped { id }
+        } else {
+            return Ok(None);
+        };
+        self.device.write_register(clear).await?;
+        self.queue.done();
+        self.start_next().await?;
+        Ok(Some(outcome))
+    }
+
+    /// Check that no packet from `send_receive()` or `send_no_ack()`,
+    /// and no ACK payload for them is left, and clear their `TX_DS`
+    /// and `RX_DR` for `poll_outcome()`
+    async fn check_idle(&mut self) -> Result<(), D::Error> {
+        let (status, fifo_status) = self.device.read_register::<FifoStatus>().await?;
+        if !fifo_status.tx_empty() || !fifo_status.rx_empty() {
+            return Err(self.device.busy());
+        }
+        if status.tx_ds() || status.rx_dr() {
+            let mut clear = Status(0);
+            clear.set_tx_ds(status.tx_ds());
+            clear.set_rx_dr(status.rx_dr());
+            self.device.write_register(clear).await?;
+            self.shadow.observe(&status);
+        }
+        Ok(())
+    }
+
+    async fn start_next(&mut self) -> Result<(), D::Error> {
+        if let Some(payload) = self.queue.start() {
+            self.device.send_command(&WriteTxPayload::new(payload)).await?;
+            self.device.ce_enable()?;
+        }
+        Ok(())
+    }
+
+    /// Send asynchronously
+    ///
+    /// Behaves like the blocking
+    /// [`PtxMode::send_receive`](../struct.PtxMode.html#method.send_receive);
+    /// use `wait_irq()` to sleep until there is something to do. All
+    /// interrupts are acknowledged, so repeat the call until nothing is
+    /// received before sleeping again. Fails with `Error::Busy` while
+    /// packets from `send()` are pending, and with
+    /// `ConfigError::PayloadLength` for a payload above 32 bytes.
+    pub async fn send_receive(&mut self, send: Option<&[u8]>) -> Result<SendReceiveResult, D::Error> {
+        if self.queue.pending() > 0 {
+            return Err(self.device.busy());
+        }
+        if let Err(e) = payload::check_len(send.unwrap_or_default()) {
+            return Err(self.device.invalid_config(e));
+        }
+        let (status, fifo_status) = self.device.read_register::<FifoStatus>().await?;
+        let discarded = if status.max_rt() {
+            handle_max_rt(&mut self.device, &status, &mut self.shadow).await?
+        } else {
+            0
+        };
+        let dropped = discarded > 0;
+
+        // Release the IRQ pin
+        let mut clear = Status(0);
+        clear.set_rx_dr(true);
+        clear.set_tx_ds(true);
+        clear.set_max_rt(true);
+        let released = self.device.write_register(clear).await?;
+        self.shadow.observe(&released);
+
+        let sent = match (fifo_status.tx_full() && !dropped, send) {
+            (true, _) | (false, None) => false,
+            (false, Some(payload)) => {
+                self.device.send_command(&WriteTxPayload::new(payload)).await?;
+                self.shadow.record(payload, false);
+                self.device.ce_enable()?;
+                true
+            }
+        };
+
+        let received = if fifo_status.rx_empty() {
+            None
+        } else {
+            let payload = read_payload(&mut self.device, status.rx_p_no()).await?;
+            Some(Received {
+                pipe: status.rx_p_no(),
+                payload,
+            })
+        };
+        Ok(SendReceiveResult { sent, received, dropped, discarded })
+    }
+
+    /// Send without requesting an acknowledgement
+    ///
+    /// Returns `false` if the TX FIFO is full. Enables `EN_DYN_ACK` on
+    /// first use. Fails with `Error::Busy` while packets from `send()`
+    /// are pending, and with `ConfigError::PayloadLength` for a
+    /// `payload` above 32 bytes.
+    pub async fn send_no_ack(&mut self, payload: &[u8]) -> Result<bool, D::Error> {
+        if self.queue.pending() > 0 {
+            return Err(self.device.busy());
+        }
+        if let Err(e) = payload::check_len(payload) {
+            return Err(self.device.invalid_config(e));
+        }
+        let (_, fifo_status) = self.device.read_register::<FifoStatus>().await?;
+        if fifo_status.tx_full() {
+            return Ok(false);
+        }
+        if !self.dyn_ack {
+            self.device
+                .update_register::<Feature, _, _>(|feature| {
+                    feature.set_en_dyn_ack(true);
+                })
+                .await?;
+            self.dyn_ack = true;
+        }
+        self.device.send_command(&WriteTxPayloadNoAck::new(payload)).await?;
+        self.shadow.record(payload, true);
+        self.device.ce_enable()?;
+        Ok(true)
+    }
+
+    /// Sleep until the IRQ pin signals a sent, dropped or received packet
+    pub async fn wait_irq(&mut self) -> Result<(), D::Error> {
+        self.device.wait_irq().await
+    }
+}
@@ -0,0 +1,29 @@
+use crate::asynch::device::{Device, UsingDevice};
+use crate::asynch::standby::StandbyMode;
+use crate::command::{ReadRawRegister, WriteRawRegister};
+use crate::config::DataRate;
+use crate::radio_config::RadioConfig;
+
+impl<D: Device> StandbyMode<D> {
+    /// Write a configuration, see
+    /// [`StandbyMode::apply_config()`](../struct.StandbyMode.html#method.apply_config)
+    pub async fn apply_config(&mut self, config: &RadioConfig) -> Result<(), D::Error> {
+        let device = self.device();
+        if config.data_rate() == DataRate::R250Kbps && !device.variant().has_250kbps() {
+            return Err(device.unsupported());
+        }
+
+        device.update_config(|register| config.crc().set_config(register)).await?;
+        for value in config.registers(device.variant()).iter() {
+            let (_, current) = device
+                .send_command(&ReadRawRegister::new(value.addr, value.len))
+                .await?;
+            if *current != *value.as_bytes() {
+                device
+                    .send_command(&WriteRawRegister::new(value.addr, value.as_bytes()))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+use core::fmt;
+
+use crate::asynch::config::Configuration;
+use crate::asynch::device::{Device, UsingDevice};
+use crate::asynch::standby::StandbyMode;
+use crate::command::{FlushRx, ReadRxPayload, ReadRxPayloadWidth};
+use crate::payload::{self, Payload};
+use crate::registers::{
+    Dynpd, Feature, FifoStatus, RxPwP0, RxPwP1, RxPwP2, RxPwP3, RxPwP4, RxPwP5, Status, CD,
+};
+use crate::rxtx::Received;
+use crate::PIPES_COUNT;
+
+/// Read the payload at the head of the RX FIFO, received on `pipe`
+///
+/// Async counterpart of `rx::read_payload()`.
+pub(crate) async fn read_payload<D: Device>(device: &mut D, pipe: u8) -> Result<Payload, D::Error> {
+    let width = match static_payload_width(device, pipe).await? {
+        Some(width) => width,
+        None => device.send_command(&ReadRxPayloadWidth).await?.1,
+    };
+    if usize::from(width) > payload::MAX_LEN {
+        device.send_command(&FlushRx).await?;
+        return Err(device.corrupt_payload(width));
+    }
+    let (_, payload) = device.send_command(&ReadRxPayload::new(width.into())).await?;
+    Ok(payload)
+}
+
+/// `RX_PW_Px` of `pipe`, or `None` for a dynamic payload length
+async fn static_payload_width<D: Device>(device: &mut D, pipe: u8) -> Result<Option<u8>, D::Error> {
+    if usize::from(pipe) >= PIPES_COUNT {
+        return Ok(None);
+    }
+    let (_, feature) = device.read_register::<Feature>().await?;
+    let (_, dynpd) = device.read_register::<Dynpd>().await?;
+    if feature.en_dpl() && dynpd.dpl_p(pipe.into()) {
+        return Ok(None);
+    }
+
+    macro_rules! rx_pw {
+        ($name: ident) => {
+            device.read_register::<$name>().await?.1.get()
+        };
+    }
+    let width = match pipe {
+        0 => rx_pw!(RxPwP0),
+        1 => rx_pw!(RxPwP1),
+        2 => rx_pw!(RxPwP2),
+        3 => rx_pw!(RxPwP3),
+        4 => rx_pw!(RxPwP4),
+        _ => rx_pw!(RxPwP5),
+    };
+    Ok(Some(width))
+}
+
+/// Represents **RX Mode**
+///
+/// Async counterpart of [`RxMode`](../struct.RxMode.html).
+pub struct RxMode<D: Device> {
+    device: D,
+}
+
+impl<D: Device> fmt::Debug for RxMode<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RxMode")
+    }
+}
+
+impl<D: Device> UsingDevice<D> for RxMode<D> {
+    fn device(&mut self) -> &mut D {
+        &mut self.device
+    }
+}
+
+impl<D: Device> Configuration<D> for RxMode<D> {}
+
+impl<D: Device> RxMode<D> {
+    /// Relies on everything being set up by `StandbyMode::rx()`, from
+    /// which it is called
+    pub(crate) fn new(device: D) -> Self {
+        RxMode { device }
+    }
+
+    /// Disable `CE` so that you can switch into TX mode.
+    pub fn standby(self) -> Result<StandbyMode<D>, (D, D::Error)> {
+        StandbyMode::from_rx_tx(self.device)
+    }
+
+    /// Is there any incoming data to read? Return the pipe number.
+    ///
+    /// Acknowledges all interrupts, like the blocking
+    /// [`RxMode::can_read`](../struct.RxMode.html#method.can_read).
+    pub async fn can_read(&mut self) -> Result<Option<u8>, D::Error> {
+        let mut clear = Status(0);
+        clear.set_rx_dr(true);
+        clear.set_tx_ds(true);
+        clear.set_max_rt(true);
+        self.device.write_register(clear).await?;
+
+        let (status, fifo_status) = self.device.read_register::<FifoStatus>().await?;
+        if !fifo_status.rx_empty() {
+            Ok(Some(status.rx_p_no()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Is an in-band RF signal detected?
+    pub async fn has_carrier(&mut self) -> Result<bool, D::Error> {
+        let (_, cd) = self.device.read_register::<CD>().await?;
+        Ok(cd.0 & 1 == 1)
+    }
+
+    /// Has a signal above -64 dBm been received?
+    ///
+    /// Fails with `Error::Unsupported` on the nRF24L01.
+    pub async fn received_power_detected(&mut self) -> Result<bool, D::Error> {
+        if !self.device.variant().has_rpd() {
+            return Err(self.device.unsupported());
+        }
+        self.has_carrier().await
+    }
+
+    /// Is the RX queue empty?
+    pub async fn is_empty(&mut self) -> Result<bool, D::Error> {
+        let (_, fifo_status) = self.device.read_register::<FifoStatus>().await?;
+        Ok(fifo_status.rx_empty())
+    }
+
+    /// Is the RX queue full?
+    pub async fn is_full(&mut self) -> Result<bool, D::Error> {
+        let (_, fifo_status) = self.device.read_register::<FifoStatus>().await?;
+        Ok(fifo_status.rx_full())
+    }
+
+    /// Wait for the next received packet and read it
+    ///
+    /// Sleeps on the IRQ pin while the RX FIFO is empty, so the
+    /// `RX_DR` interrupt must not be masked. Use `receive()` to get
+    /// the pipe number of the same packet. A width above 32 bytes
+    /// flushes the RX FIFO and fails with `Error::CorruptPayload`.
+    pub async fn read(&mut self) -> Result<Payload, D::Error> {
+        // Interrupts are cleared before the FIFO is checked, so a
+        // packet arriving in between still asserts the IRQ pin.
+        let pipe = loop {
+            if let Some(pipe) = self.can_read().await? {
+                break pipe;
+            }
+            self.device.wait_irq().await?;
+        };
+        read_payload(&mut self.device, pipe).await
+    }
+
+    /// Wait for the next received packet and read it together with
+    /// its pipe number
+    ///
+    /// Like the blocking
+    /// [`RxMode::receive`](../struct.RxMode.html#method.receive), the
+    /// pipe number is taken from the same `STATUS` that tells whether
+    /// the RX FIFO holds a packet. A width above 32 bytes flushes the
+    /// RX FIFO and fails with `Error::CorruptPayload`.
+    pub async fn receive(&mut self) -> Result<Received, D::Error> {
+        let pipe = loop {
+            let mut clear = Status(0);
+            clear.set_rx_dr(true);
+            clear.set_tx_ds(true);
+            clear.set_max_rt(true);
+            let status = self.device.write_register(clear).await?;
+            // RX_P_NO is 0b111 when the RX FIFO is empty
+            if usize::from(status.rx_p_no()) < PIPES_COUNT {
+                break status.rx_p_no();
+            }
+            self.device.wait_irq().await?;
+        };
+
+        let payload = read_payload(&mut self.device, pipe).await?;
+        Ok(Received { pipe, payload })
+    }
+}
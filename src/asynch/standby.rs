@@ -0,0 +1,236 @@
+use core::fmt;
+
+use crate::asynch::config::Configuration;
+use crate::asynch::device::{Device, UsingDevice};
+use crate::asynch::beacon::BeaconMode;
+use crate::asynch::carrier::CarrierTestMode;
+use crate::asynch::powerdown::PowerDownMode;
+use crate::asynch::prx::PrxMode;
+use crate::asynch::ptx::{self, PtxMode};
+use crate::asynch::rx::RxMode;
+use crate::asynch::tx::TxMode;
+use crate::command::{FlushTx, ReuseTxPayload, WriteTxPayloadNoAck};
+use crate::payload;
+use crate::config::{auto_ack, auto_retransmit, Channel};
+use crate::error::ConfigError;
+use crate::registers::{Config, Dynpd, Feature, RfCh, RfSetup};
+use crate::timing::{POWER_UP_US, SETTLING_US};
+use crate::PIPES_COUNT;
+
+/// Represents **Standby-I** mode
+///
+/// Async counterpart of [`StandbyMode`](../struct.StandbyMode.html).
+pub struct StandbyMode<D: Device> {
+    device: D,
+}
+
+impl<D: Device> fmt::Debug for StandbyMode<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "StandbyMode")
+    }
+}
+
+impl<D: Device> UsingDevice<D> for StandbyMode<D> {
+    fn device(&mut self) -> &mut D {
+        &mut self.device
+    }
+}
+
+impl<D: Device> Configuration<D> for StandbyMode<D> {}
+
+impl<D: Device> StandbyMode<D> {
+    /// Constructor
+    ///
+    /// Puts the `device` into standy mode, and waits for the start-up
+    /// time if it was powered down
+    pub async fn power_up(mut device: D) -> Result<Self, (D, D::Error)> {
+        let power_up = |config: &mut Config| {
+            let was_down = !config.pwr_up();
+            config.set_pwr_up(true);
+            was_down
+        };
+        match device.update_config(power_up).await {
+            Ok(was_down) => {
+                if was_down {
+                    device.delay_us(POWER_UP_US).await;
+                }
+                Ok(StandbyMode { device })
+            }
+            Err(e) => Err((device, e)),
+        }
+    }
+
+    /// Power down, keeping the register content
+    pub async fn power_down(mut self) -> Result<PowerDownMode<D>, (Self, D::Error)> {
+        match self.device.update_config(|config| config.set_pwr_up(false)).await {
+            Ok(()) => Ok(PowerDownMode::new(self.device)),
+            Err(e) => Err((self, e)),
+        }
+    }
+
+    pub(crate) fn from_rx_tx(mut device: D) -> Result<Self, (D, D::Error)> {
+        match device.ce_disable() {
+            Ok(()) => Ok(StandbyMode { device }),
+            Err(e) => Err((device, e)),
+        }
+    }
+
+    /// Go into RX mode, and wait for the 130 µs settling time
+    pub async fn rx(self) -> Result<RxMode<D>, (D, D::Error)> {
+        let mut device = self.device;
+
+        let result = match device.update_config(|config| config.set_prim_rx(true)).await {
+            Ok(()) => device.ce_enable(),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => {
+                device.delay_us(SETTLING_US).await;
+                Ok(RxMode::new(device))
+            }
+            Err(e) => Err((device, e)),
+        }
+    }
+
+    /// Go into TX mode
+    pub async fn tx(self) -> Result<TxMode<D>, (D, D::Error)> {
+        let mut device = self.device;
+
+        match device.update_config(|config| config.set_prim_rx(false)).await {
+            // No need to device.ce_enable(); yet
+            Ok(()) => Ok(TxMode::new(device)),
+            Err(e) => Err((device, e)),
+        }
+    }
+
+    /// Enter PTX mode, see
+    /// [`StandbyMode::ptx()`](../struct.StandbyMode.html#method.ptx)
+    pub async fn ptx(self, delay: u8, retries: u8) -> Result<PtxMode<D>, (D, D::Error)> {
+        let mut device = self.device;
+        if delay > 0xF || retries > 0xF {
+            let e = device.invalid_config(ConfigError::AutoRetransmit(delay, retries));
+            return Err((device, e));
+        }
+        let saved = match ptx::read_registers(&mut device).await {
+            Ok(saved) => saved,
+            Err(e) => return Err((device, e)),
+        };
+
+        let config_ptx = async {
+            device.write_register(auto_ack(&[true; PIPES_COUNT])).await?;
+            device.write_register(auto_retransmit(delay, retries)).await?;
+            // Enable ack payload and dynamic payload features
+            device
+                .update_register::<Feature, _, _>(|feature| {
+                    feature.set_en_ack_pay(true);
+                    feature.set_en_dpl(true);
+                })
+                .await?;
+            // Enable dynamic payload on all pipes
+            device.write_register(Dynpd::from_bools(&[true; PIPES_COUNT])).await?;
+            device.update_config(|config| config.set_prim_rx(false)).await
+        };
+
+        match config_ptx.await {
+            // No need to device.ce_enable(); yet
+            Ok(()) => Ok(PtxMode::new(device, saved)),
+            Err(e) => {
+                // The first error is the one to report
+                let _ = ptx::write_registers(&mut device, &saved).await;
+                Err((device, e))
+            }
+        }
+    }
+
+    /// Enter PRX mode
+    ///
+    /// Enables auto-ack, ACK payloads, and dynamic payload lengths on
+    /// all pipes, and flushes the TX FIFO that holds the ACK payloads.
+    pub async fn prx(self) -> Result<PrxMode<D>, (D, D::Error)> {
+        let mut device = self.device;
+
+        let config_prx = async {
+            device.write_register(auto_ack(&[true; PIPES_COUNT])).await?;
+            device
+                .update_register::<Feature, _, _>(|feature| {
+                    feature.set_en_ack_pay(true);
+                    feature.set_en_dpl(true);
+                })
+                .await?;
+            device.write_register(Dynpd::from_bools(&[true; PIPES_COUNT])).await?;
+            device.send_command(&FlushTx).await?;
+            device.update_config(|config| config.set_prim_rx(true)).await?;
+            device.ce_enable()?;
+            device.delay_us(SETTLING_US).await;
+            Ok(())
+        };
+
+        match config_prx.await {
+            Ok(()) => Ok(PrxMode::new(device)),
+            Err(e) => Err((device, e)),
+        }
+    }
+
+    /// Enter beacon mode, which sends `payload` repeatedly
+    ///
+    /// Fails with `ConfigError::PayloadLength` for a `payload` above
+    /// 32 bytes.
+    pub async fn beacon(self, payload: &[u8]) -> Result<BeaconMode<D>, (D, D::Error)> {
+        let mut device = self.device;
+        if let Err(e) = payload::check_len(payload) {
+            let e = device.invalid_config(e);
+            return Err((device, e));
+        }
+
+        let config_beacon = async {
+            device.update_config(|config| config.set_prim_rx(false)).await?;
+            device.send_command(&FlushTx).await?;
+            device
+                .update_register::<Feature, _, _>(|feature| {
+                    feature.set_en_dyn_ack(true);
+                })
+                .await?;
+            device.send_command(&WriteTxPayloadNoAck::new(payload)).await?;
+            device.send_command(&ReuseTxPayload).await?;
+            Ok(())
+        };
+
+        match config_beacon.await {
+            Ok(()) => Ok(BeaconMode::new(device)),
+            Err(e) => Err((device, e)),
+        }
+    }
+
+    /// Emit a constant carrier on `channel`, at the highest output
+    /// power that does not exceed `dbm`
+    pub async fn carrier_test(self, channel: Channel, dbm: i8) -> Result<CarrierTestMode<D>, (D, D::Error)> {
+        let mut device = self.device;
+        let variant = device.variant();
+        if !variant.has_cont_wave() {
+            let e = device.unsupported();
+            return Err((device, e));
+        }
+
+        let config_carrier = async {
+            let (_, rf_setup) = device.read_register::<RfSetup>().await?;
+            let mut carrier = RfSetup(rf_setup.0);
+            carrier.set_cont_wave(true);
+            carrier.set_pll_lock(true);
+            variant.set_power_level(&mut carrier, variant.power_level_at_most(dbm));
+
+            let mut rf_ch = RfCh(0);
+            rf_ch.set_rf_ch(channel.get());
+            device.update_config(|config| config.set_prim_rx(false)).await?;
+            device.write_register(carrier).await?;
+            device.write_register(rf_ch).await?;
+            device.ce_enable()?;
+            device.delay_us(SETTLING_US).await;
+            Ok(rf_setup)
+        };
+
+        match config_carrier.await {
+            Ok(rf_setup) => Ok(CarrierTestMode::new(device, rf_setup)),
+            Err(e) => Err((device, e)),
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use core::fmt;
+
+use crate::asynch::config::Configuration;
+use crate::asynch::device::{Device, UsingDevice};
+use crate::asynch::standby::StandbyMode;
+use crate::command::{FlushTx, Nop, WriteTxPayload, WriteTxPayloadNoAck};
+use crate::payload;
+use crate::registers::{Feature, FifoStatus, ObserveTx, Status};
+use crate::rxtx::{MaxRtPolicy, TxShadow};
+
+/// Apply the policy of `shadow` to `MAX_RT` in `status`, and clear
+/// `TX_DS` and `MAX_RT`. Returns the number of discarded packets.
+///
+/// Async counterpart of `tx::handle_max_rt()`.
+pub(crate) async fn handle_max_rt<D: Device>(
+    device: &mut D,
+    status: &Status,
+    shadow: &mut TxShadow,
+) -> Result<usize, D::Error> {
+    let discarded = match shadow.retry(status.tx_ds()) {
+        Some(backoff_us) => {
+            device.delay_us(backoff_us).await;
+            0
+        }
+        None => {
+            // Probe for a second packet, see `tx::queued_on_max_rt()`
+            let (_, fifo_status) = device.read_register::<FifoStatus>().await?;
+            let queued = if fifo_status.tx_full() {
+                3
+            } else {
+                device.send_command(&WriteTxPayload::new(&[0])).await?;
+                let (_, fifo_status) = device.read_register::<FifoStatus>().await?;
+                if fifo_status.tx_full() { 2 } else { 1 }
+            };
+            device.send_command(&FlushTx).await?;
+            if shadow.policy() == MaxRtPolicy::FlushAll {
+                queued
+            } else {
+                for (payload, no_ack) in shadow.last(queued).skip(1) {
+                    if *no_ack {
+                        device.send_command(&WriteTxPayloadNoAck::new(payload)).await?;
+                    } else {
+                        device.send_command(&WriteTxPayload::new(payload)).await?;
+                    }
+                }
+                1
+            }
+        }
+    };
+    let mut clear = Status(0);
+    clear.set_tx_ds(true);
+    clear.set_max_rt(true);
+    device.write_register(clear).await?;
+    Ok(discarded)
+}
+
+/// Represents **TX Mode** and the associated **TX Settling** and
+/// **Standby-II** states
+///
+/// Async counterpart of [`TxMode`](../struct.TxMode.html).
+pub struct TxMode<D: Device> {
+    device: D,
+    /// `EN_DYN_ACK` has been set
+    dyn_ack: bool,
+    /// Packets in the TX FIFO, and the `MAX_RT` policy
+    shadow: TxShadow,
+    /// Packets discarded since `wait_empty()` last returned
+    discarded: usize,
+}
+
+impl<D: Device> fmt::Debug for TxMode<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TxMode")
+    }
+}
+
+impl<D: Device> UsingDevice<D> for TxMode<D> {
+    fn device(&mut self) -> &mut D {
+        &mut self.device
+    }
+}
+
+impl<D: Device> Configuration<D> for TxMode<D> {}
+
+impl<D: Device> TxMode<D> {
+    /// Relies on everything being set up by `StandbyMode::tx()`, from
+    /// which it is called
+    pub(crate) fn new(device: D) -> Self {
+        TxMode {
+            device,
+            dyn_ack: false,
+            shadow: TxShadow::new(),
+            discarded: 0,
+        }
+    }
+
+    /// Select what happens when a packet is not acknowledged after the
+    /// maximum number of retransmits
+    pub fn set_max_rt_policy(&mut self, policy: MaxRtPolicy) {
+        self.shadow.set_policy(policy);
+    }
+
+    /// Disable `CE` so that you can switch into RX mode.
+    pub async fn standby(mut self) -> Result<StandbyMode<D>, (D, D::Error)> {
+        match self.wait_empty().await {
+            Ok(_) => StandbyMode::from_rx_tx(self.device),
+            Err(e) => Err((self.device, e)),
+        }
+    }
+
+    /// Is TX FIFO empty?
+    pub async fn is_empty(&mut self) -> Result<bool, D::Error> {
+        let (_, fifo_status) = self.device.read_register::<FifoStatus>().await?;
+        Ok(fifo_status.tx_empty())
+    }
+
+    /// Is TX FIFO full?
+    pub async fn is_full(&mut self) -> Result<bool, D::Error> {
+        let (_, fifo_status) = self.device.read_register::<FifoStatus>().await?;
+        Ok(fifo_status.tx_full())
+    }
+
+    /// Does the TX FIFO have space?
+    pub async fn can_send(&mut self) -> Result<bool, D::Error> {
+        let full = self.is_full().await?;
+        Ok(!full)
+    }
+
+    /// Enqueue a packet, sleeping on the IRQ pin while the TX FIFO
+    /// is full
+    ///
+    /// Like `wait_empty()`, this applies the `MaxRtPolicy` when the
+    /// maximum amount of retries is reached. Discarded packets are
+    /// counted by the next `wait_empty()`. Fails with
+    /// `ConfigError::PayloadLength` for a `packet` above 32 bytes.
+    pub async fn send(&mut self, packet: &[u8]) -> Result<(), D::Error> {
+        if let Err(e) = payload::check_len(packet) {
+            return Err(self.device.invalid_config(e));
+        }
+        self.wait_not_full().await?;
+        self.device.send_command(&WriteTxPayload::new(packet)).await?;
+        self.shadow.record(packet, false);
+        self.device.ce_enable()?;
+        Ok(())
+    }
+
+    /// Enqueue a packet without requesting an acknowledgement,
+    /// sleeping on the IRQ pin while the TX FIFO is full
+    ///
+    /// Enables `EN_DYN_ACK` on first use. Fails with
+    /// `ConfigError::PayloadLength` for a `packet` above 32 bytes.
+    pub async fn send_no_ack(&mut self, packet: &[u8]) -> Result<(), D::Error> {
+        if let Err(e) = payload::check_len(packet) {
+            return Err(self.device.invalid_config(e));
+        }
+        if !self.dyn_ack {
+            self.device
+                .update_register::<Feature, _, _>(|feature| {
+                    feature.set_en_dyn_ack(true);
+                })
+                .await?;
+            self.dyn_ack = true;
+        }
+        self.wait_not_full().await?;
+        self.device.send_command(&WriteTxPayloadNoAck::new(packet)).await?;
+        self.shadow.record(packet, true);
+        self.device.ce_enable()?;
+        Ok(())
+    }
+
+    async fn wait_not_full(&mut self) -> Result<(), D::Error> {
+        loop {
+            let fifo_status = self.clear_tx_interrupts().await?;
+            if !fifo_status.tx_full() {
+                return Ok(());
+            }
+            self.device.ce_enable()?;
+            self.device.wait_irq().await?;
+        }
+    }
+
+    /// Wait until TX FIFO is empty, sleeping on the IRQ pin, and
+    /// return the number of packets that have been discarded
+    ///
+    /// If any packet cannot be delivered and the maximum amount of
+    /// retries is reached, the [`MaxRtPolicy`](../enum.MaxRtPolicy.html)
+    /// applies.
+    pub async fn wait_empty(&mut self) -> Result<usize, D::Error> {
+        loop {
+            let fifo_status = self.clear_tx_interrupts().await?;
+            if fifo_status.tx_empty() {
+                break;
+            }
+            self.device.ce_enable()?;
+            self.device.wait_irq().await?;
+        }
+        // Can save power now
+        self.device.ce_disable()?;
+
+        Ok(core::mem::replace(&mut self.discarded, 0))
+    }
+
+    /// Clear `TX_DS`, and `MAX_RT` after applying the `MaxRtPolicy`,
+    /// so that the IRQ pin is released. Returns the FIFO status
+    /// afterwards.
+    async fn clear_tx_interrupts(&mut self) -> Result<FifoStatus, D::Error> {
+        let (status, ()) = self.device.send_command(&Nop).await?;
+        if status.max_rt() {
+            self.discarded += handle_max_rt(&mut self.device, &status, &mut self.shadow).await?;
+        } else {
+            let mut clear = Status(0);
+            clear.set_tx_ds(true);
+            self.device.write_register(clear).await?;
+            self.shadow.observe(&status);
+        }
+
+        let (_, fifo_status) = self.device.read_register::<FifoStatus>().await?;
+        Ok(fifo_status)
+    }
+
+    /// Read the `OBSERVE_TX` register
+    pub async fn observe(&mut self) -> Result<ObserveTx, D::Error> {
+        let (_, observe_tx) = self.device.read_register().await?;
+        Ok(observe_tx)
+    }
+}
@@ -0,0 +1,57 @@
+//! Async counterpart of `crate::variant`
+
+use crate::asynch::device::Device;
+use crate::command::{Activate, Nop, WriteRawRegister};
+use crate::registers::{Feature, RfSetup};
+use crate::variant::{bank1_init, classify, feature_probe, rf_setup_probe, ChipVariant};
+
+/// Tell the variants apart, see `crate::variant::classify()`
+pub(crate) async fn detect<D: Device>(device: &mut D) -> Result<ChipVariant, D::Error> {
+    device.send_command(&Activate::Bank).await?;
+    let (status, ()) = device.send_command(&Nop).await?;
+    let beken = status.rbank();
+    if beken {
+        device.send_command(&Activate::Bank).await?;
+    }
+
+    let (_, rf_setup) = device.read_register::<RfSetup>().await?;
+    device.write_register(rf_setup_probe(&rf_setup)).await?;
+    let (_, probed) = device.read_register::<RfSetup>().await?;
+    device.write_register(rf_setup).await?;
+
+    Ok(classify(beken, &probed))
+}
+
+/// Switch Beken parts to register bank 0 or 1
+pub(crate) async fn select_bank<D: Device>(device: &mut D, bank1: bool) -> Result<(), D::Error> {
+    let (status, ()) = device.send_command(&Nop).await?;
+    if status.rbank() != bank1 {
+        device.send_command(&Activate::Bank).await?;
+    }
+    Ok(())
+}
+
+/// Run the bank 1 init sequence of Beken parts
+pub(crate) async fn init_bank1<D: Device>(device: &mut D) -> Result<(), D::Error> {
+    select_bank(device, true).await?;
+    for (addr, value) in bank1_init() {
+        device.send_command(&WriteRawRegister::new(addr, value)).await?;
+    }
+    select_bank(device, false).await
+}
+
+/// Unlock `FEATURE` and `DYNPD`, only if a write to `FEATURE` does
+/// not stick, as `ACTIVATE` toggles.
+pub(crate) async fn activate<D: Device>(device: &mut D) -> Result<(), D::Error> {
+    let (_, feature) = device.read_register::<Feature>().await?;
+    let probe = feature_probe(&feature);
+    device.write_register(Feature(probe.0)).await?;
+    let (_, probed) = device.read_register::<Feature>().await?;
+
+    if probed == probe {
+        device.write_register(feature).await?;
+    } else {
+        device.send_command(&Activate::Features).await?;
+    }
+    Ok(())
+}
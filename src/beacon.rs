@@ -0,0 +1,144 @@
+use core::fmt;
+
+use crate::command::{FlushTx, Nop};
+use crate::config::Configuration;
+use crate::device::{Device, UsingDevice};
+use crate::registers::Status;
+use crate::standby::StandbyMode;
+use crate::timing::CE_PULSE_US;
+
+/// Longest transmission: TX settling, and 32 bytes at 250 kbps
+pub(crate) const MAX_TRANSMISSION_US: u32 = 1500;
+/// Interval of polling for `TX_DS` in `repeat()`
+const POLL_US: u32 = 10;
+
+/// Retransmits one payload with `REUSE_TX_PL`
+///
+/// Every pulse on CE sends the payload once more, without another
+/// SPI payload write, and without requesting an acknowledgement.
+/// Holding CE high sends it back to back.
+///
+/// The CE pulse and the waits use the delay provider of the driver,
+/// so construct it with `new_with_delay()`: after `new()`, the CE
+/// pulse is only as long as the GPIO writes.
+pub struct BeaconMode<D: Device> {
+    device: D,
+    /// CE has been raised since the last `TX_DS`
+    sending: bool,
+}
+
+impl<D: Device> fmt::Debug for BeaconMode<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BeaconMode")
+    }
+}
+
+impl<D: Device> UsingDevice<D> for BeaconMode<D> {
+    fn device(&mut self) -> &mut D {
+        &mut self.device
+    }
+}
+
+impl<D: Device> Configuration<D> for BeaconMode<D> {}
+
+impl<D: Device> BeaconMode<D> {
+    /// Relies on the payload being loaded by `StandbyMode::beacon()`,
+    /// from which it is called
+    pub(crate) fn new(device: D) -> Self {
+        BeaconMode {
+            device,
+            sending: false,
+        }
+    }
+
+    /// Wait for the last transmission, and drop the payload
+    ///
+    /// `FLUSH_TX` ends `REUSE_TX_PL`, which must not happen while a
+    /// packet is on the air.
+    pub fn standby(mut self) -> Result<StandbyMode<D>, (D, D::Error)> {
+        match self.stop() {
+            Ok(()) => StandbyMode::from_rx_tx(self.device),
+            Err(e) => Err((self.device, e)),
+        }
+    }
+
+    fn stop(&mut self) -> Result<(), D::Error> {
+        if self.sending {
+            self.device.ce_disable()?;
+            self.device.delay_us(MAX_TRANSMISSION_US);
+            self.sending = false;
+        }
+        self.device.send_command(&FlushTx)?;
+        let mut clear = Status(0);
+        clear.set_tx_ds(true);
+        self.device.write_register(clear)?;
+        Ok(())
+    }
+
+    /// Send the payload once more
+    ///
+    /// Use `poll_sent()` to learn when it has left.
+    pub fn pulse(&mut self) -> Result<(), D::Error> {
+        self.device.ce_enable()?;
+        self.device.delay_us(CE_PULSE_US);
+        self.device.ce_disable()?;
+        self.sending = true;
+        Ok(())
+    }
+
+    /// Poll completion of a `pulse()`
+    pub fn poll_sent(&mut self) -> nb::Result<(), D::Error> {
+        let (status, ()) = self.device.send_command(&Nop)?;
+        if !status.tx_ds() {
+            return Err(nb::Error::WouldBlock);
+        }
+        let mut clear = Status(0);
+        clear.set_tx_ds(true);
+        self.device.write_register(clear)?;
+        self.sending = false;
+        Ok(())
+    }
+
+    /// Send the payload `count` times, waiting `interval_us` between
+    /// the end of one transmission and the start of the next
+    ///
+    /// Fails with `Error::Timeout` if a transmission does not report
+    /// `TX_DS` within `MAX_TRANSMISSION_US`, as measured by the delay
+    /// provider of the driver. A driver from `new()` has none, so it
+    /// polls until `TX_DS` without a timeout.
+    pub fn repeat(&mut self, count: u32, interval_us: u32) -> Result<(), D::Error> {
+        for i in 0..count {
+            if i > 0 {
+                self.device.delay_us(interval_us);
+            }
+            self.pulse()?;
+            self.wait_sent()?;
+        }
+        Ok(())
+    }
+
+    fn wait_sent(&mut self) -> Result<(), D::Error> {
+        if !self.device.has_delay() {
+            return nb::block!(self.poll_sent());
+        }
+        for _ in 0..MAX_TRANSMISSION_US / POLL_US {
+            match self.poll_sent() {
+                Ok(()) => return Ok(()),
+                Err(nb::Error::Other(e)) => return Err(e),
+                Err(nb::Error::WouldBlock) => self.device.delay_us(POLL_US),
+            }
+        }
+        match self.poll_sent() {
+            Ok(()) => Ok(()),
+            Err(nb::Error::Other(e)) => Err(e),
+            Err(nb::Error::WouldBlock) => Err(self.device.timeout()),
+        }
+    }
+
+    /// Hold CE high to send the payload back to back, until `standby()`
+    pub fn hold(&mut self) -> Result<(), D::Error> {
+        self.device.ce_enable()?;
+        self.sending = true;
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use core::fmt;
+
+use crate::config::{Channel, Configuration};
+use crate::device::{Device, UsingDevice};
+use crate::registers::{RfCh, RfSetup};
+use crate::standby::StandbyMode;
+use crate::timing::SETTLING_US;
+
+/// Emits an unmodulated carrier, for regulatory pre-scans and antenna
+/// tuning
+///
+/// `CONT_WAVE` and `PLL_LOCK` are set in `RF_SETUP`, and **CE** is
+/// held high. Only the nRF24L01+ and the Si24R1 support this.
+pub struct CarrierTestMode<D: Device> {
+    device: D,
+    /// `RF_SETUP` before entering this mode
+    rf_setup: RfSetup,
+}
+
+impl<D: Device> fmt::Debug for CarrierTestMode<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CarrierTestMode")
+    }
+}
+
+impl<D: Device> UsingDevice<D> for CarrierTestMode<D> {
+    fn device(&mut self) -> &mut D {
+        &mut self.device
+    }
+}
+
+impl<D: Device> Configuration<D> for CarrierTestMode<D> {}
+
+impl<D: Device> CarrierTestMode<D> {
+    /// Relies on the carrier being started by
+    /// `StandbyMode::carrier_test()`, from which it is called
+    pub(crate) fn new(device: D, rf_setup: RfSetup) -> Self {
+        CarrierTestMode { device, rf_setup }
+    }
+
+    /// Stop the carrier, and restore `RF_SETUP`
+    ///
+    /// `RF_CH` keeps the last channel.
+    pub fn standby(mut self) -> Result<StandbyMode<D>, (D, D::Error)> {
+        let rf_setup = RfSetup(self.rf_setup.0);
+        match self
+            .device
+            .ce_disable()
+            .and_then(|()| self.device.write_register(rf_setup))
+        {
+            Ok(_) => StandbyMode::from_rx_tx(self.device),
+            Err(e) => Err((self.device, e)),
+        }
+    }
+
+    /// Move the carrier to another channel
+    ///
+    /// **CE** is lowered while `RF_CH` changes, and the PLL settles
+    /// for 130 µs afterwards.
+    pub fn hop(&mut self, channel: Channel) -> Result<(), D::Error> {
+        let mut register = RfCh(0);
+        register.set_rf_ch(channel.get());
+        self.device.ce_disable()?;
+        self.device.write_register(register)?;
+        self.device.ce_enable()?;
+        self.device.delay_us(SETTLING_US);
+        Ok(())
+    }
+}
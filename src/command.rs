@@ -171,3 +171,151 @@ impl Command for Nop {
     type Response = ();
     fn decode_response(_: &[u8]) -> Self::Response {}
 }
+
+/// `ACTIVATE`
+pub enum Activate {
+    /// `0x73`, toggles the `FEATURE` and `DYNPD` registers of the
+    /// nRF24L01 (non-plus) and Beken parts
+    Features,
+    /// `0x53`, toggles the register bank of Beken parts
+    Bank,
+}
+
+impl Command for Activate {
+    fn len(&self) -> usize {
+        2
+    }
+
+    fn encode(&self, buf: &mut [u8]) {
+        buf[0] = 0b0101_0000;
+        buf[1] = match *self {
+            Activate::Features => 0x73,
+            Activate::Bank => 0x53,
+        };
+    }
+
+    type Response = ();
+    fn decode_response(_: &[u8]) -> Self::Response {}
+}
+
+/// `R_REGISTER` for registers without a type, such as those in
+/// bank 1 of Beken parts
+pub struct ReadRawRegister {
+    addr: u8,
+    len: usize,
+}
+
+impl ReadRawRegister {
+    pub fn new(addr: u8, len: usize) -> Self {
+        ReadRawRegister { addr, len }
+    }
+}
+
+impl Command for ReadRawRegister {
+    fn len(&self) -> usize {
+        1 + self.len
+    }
+
+    fn encode(&self, buf: &mut [u8]) {
+        buf[0] = self.addr;
+    }
+
+    type Response = Payload;
+    fn decode_response(data: &[u8]) -> Self::Response {
+        Payload::new(&data[1..])
+    }
+}
+
+/// `W_REGISTER` for registers without a type
+pub struct WriteRawRegister<'a> {
+    addr: u8,
+    data: &'a [u8],
+}
+
+impl<'a> WriteRawRegister<'a> {
+    pub fn new(addr: u8, data: &'a [u8]) -> Self {
+        WriteRawRegister { addr, data }
+    }
+}
+
+impl<'a> Command for WriteRawRegister<'a> {
+    fn len(&self) -> usize {
+        1 + self.data.len()
+    }
+
+    fn encode(&self, buf: &mut [u8]) {
+        buf[0] = 0b10_0000 | self.addr;
+        buf[1..].copy_from_slice(self.data);
+    }
+
+    type Response = ();
+    fn decode_response(_: &[u8]) -> Self::Response {}
+}
+
+/// `W_ACK_PAYLOAD` for a pipe, in PRX mode
+pub struct WriteAckPayload<'a> {
+    pipe: u8,
+    data: &'a [u8],
+}
+
+impl<'a> WriteAckPayload<'a> {
+    pub fn new(pipe: u8, data: &'a [u8]) -> Self {
+        WriteAckPayload { pipe, data }
+    }
+}
+
+impl<'a> Command for WriteAckPayload<'a> {
+    fn len(&self) -> usize {
+        1 + self.data.len()
+    }
+
+    fn encode(&self, buf: &mut [u8]) {
+        buf[0] = 0b1010_1000 | self.pipe;
+        buf[1..].copy_from_slice(self.data);
+    }
+
+    type Response = ();
+    fn decode_response(_: &[u8]) -> Self::Response {}
+}
+
+/// `W_TX_PAYLOAD_NOACK`, enabled by `EN_DYN_ACK`
+pub struct WriteTxPayloadNoAck<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> WriteTxPayloadNoAck<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        WriteTxPayloadNoAck { data }
+    }
+}
+
+impl<'a> Command for WriteTxPayloadNoAck<'a> {
+    fn len(&self) -> usize {
+        1 + self.data.len()
+    }
+
+    fn encode(&self, buf: &mut [u8]) {
+        buf[0] = 0b1011_0000;
+        buf[1..].copy_from_slice(self.data);
+    }
+
+    type Response = ();
+    fn decode_response(_: &[u8]) -> Self::Response {}
+}
+
+/// `REUSE_TX_PL`, retransmit the last payload until `W_TX_PAYLOAD`
+/// or `FLUSH_TX`
+pub struct ReuseTxPayload;
+
+impl Command for ReuseTxPayload {
+    fn len(&self) -> usize {
+        1
+    }
+
+    fn encode(&self, buf: &mut [u8]) {
+        buf[0] = 0b1110_0011;
+    }
+
+    type Response = ();
+    fn decode_response(_: &[u8]) -> Self::Response {}
+}
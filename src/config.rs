@@ -1,27 +1,125 @@
-use crate::command::{FlushRx, FlushTx, Nop};
+use core::convert::TryFrom;
+
+use crate::command::{FlushRx, FlushTx, Nop, ReadRawRegister, WriteRawRegister};
 use crate::device::{ Device, UsingDevice };
 use crate::registers::{
-    Config, Dynpd, EnAa, EnRxaddr, Feature, RfCh, RfSetup, SetupAw, SetupRetr, Status, TxAddr,
+    Config, Dynpd, EnAa, EnRxaddr, Feature, RfCh, RfSetup, RxAddrP0, RxAddrP1, SetupAw,
+    SetupRetr, Status, TxAddr,
 };
-use crate::PIPES_COUNT;
+use crate::snapshot::RegisterSnapshot;
+use crate::variant::{self, ChipVariant};
+use crate::address::Address;
+use crate::error::ConfigError;
+use crate::event::Events;
+use crate::radio_config::{PipeConfig, RadioConfig};
+use crate::{MAX_ADDR_BYTES, MIN_ADDR_BYTES, PIPES_COUNT};
+
+/// Highest `RF_CH`
+pub(crate) const MAX_CHANNEL: u8 = 125;
+
+/// RF channel, `0..=125`
+///
+/// The frequency is `2400 + channel` MHz.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+pub struct Channel(u8);
+
+impl Channel {
+    /// Fails for channels above 125
+    pub fn new(channel: u8) -> Result<Self, ConfigError> {
+        if channel > MAX_CHANNEL {
+            return Err(ConfigError::Channel(channel));
+        }
+        Ok(Channel(channel))
+    }
+
+    /// Channel number
+    pub fn get(&self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<u8> for Channel {
+    type Error = ConfigError;
+    fn try_from(channel: u8) -> Result<Self, ConfigError> {
+        Channel::new(channel)
+    }
+}
+
+/// RX pipe number, `0..=5`
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+pub struct Pipe(pub(crate) u8);
+
+impl Pipe {
+    /// Fails for pipes above 5
+    pub fn new(pipe: usize) -> Result<Self, ConfigError> {
+        if pipe >= PIPES_COUNT {
+            return Err(ConfigError::Pipe(pipe));
+        }
+        Ok(Pipe(pipe as u8))
+    }
+
+    /// Pipe number
+    pub fn index(&self) -> usize {
+        self.0.into()
+    }
+}
+
+impl TryFrom<usize> for Pipe {
+    type Error = ConfigError;
+    fn try_from(pipe: usize) -> Result<Self, ConfigError> {
+        Pipe::new(pipe)
+    }
+}
+
+/// `RF_PWR` of the Nordic parts, `0..=3`
+///
+/// `0` is -18 dBm, and `3` is 0 dBm. Use `set_power_dbm()` for the
+/// levels of the clones.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+pub struct PowerLevel(u8);
+
+impl PowerLevel {
+    /// The highest level, 0 dBm
+    pub const MAX: PowerLevel = PowerLevel(3);
+
+    /// Fails for levels above 3
+    pub fn new(level: u8) -> Result<Self, ConfigError> {
+        if level > Self::MAX.0 {
+            return Err(ConfigError::Power(level));
+        }
+        Ok(PowerLevel(level))
+    }
+
+    /// Level number
+    pub fn get(&self) -> u8 {
+        self.0
+    }
+
+    /// The 2-bit `RF_PWR` field of the Nordic parts
+    pub(crate) fn from_rf_setup(rf_setup: &RfSetup) -> Self {
+        PowerLevel(rf_setup.rf_pwr())
+    }
+}
+
+impl TryFrom<u8> for PowerLevel {
+    type Error = ConfigError;
+    fn try_from(level: u8) -> Result<Self, ConfigError> {
+        PowerLevel::new(level)
+    }
+}
 
 /// Supported air data rates.
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
 pub enum DataRate {
     /// 250 Kbps
     R250Kbps,
     /// 1 Mbps
+    #[default]
     R1Mbps,
     /// 2 Mbps
     R2Mbps,
 }
 
-impl Default for DataRate {
-    fn default() -> DataRate {
-        DataRate::R1Mbps
-    }
-}
-
 /// Supported CRC modes
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum CrcMode {
@@ -33,8 +131,26 @@ pub enum CrcMode {
     TwoBytes,
 }
 
+impl DataRate {
+    pub(crate) fn from_rf_setup(rf_setup: &RfSetup) -> Self {
+        match (rf_setup.rf_dr_low(), rf_setup.rf_dr_high()) {
+            (true, _) => DataRate::R250Kbps,
+            (false, false) => DataRate::R1Mbps,
+            (false, true) => DataRate::R2Mbps,
+        }
+    }
+}
+
 impl CrcMode {
-    fn set_config(&self, config: &mut Config) {
+    pub(crate) fn from_config(config: &Config) -> Self {
+        match (config.en_crc(), config.crco()) {
+            (false, _) => CrcMode::Disabled,
+            (true, false) => CrcMode::OneByte,
+            (true, true) => CrcMode::TwoBytes,
+        }
+    }
+
+    pub(crate) fn set_config(&self, config: &mut Config) {
         let (en_crc, crco) = match *self {
             CrcMode::Disabled => (false, false),
             CrcMode::OneByte => (true, false),
@@ -67,6 +183,36 @@ pub struct Interrupts {
     pub max_rt: bool,
 }
 
+/// Flags of the `FEATURE` register
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Features {
+    /// `EN_DPL`, dynamic payload lengths
+    pub dynamic_payloads: bool,
+    /// `EN_ACK_PAY`, payloads with ACK
+    pub ack_payloads: bool,
+    /// `EN_DYN_ACK`, the `W_TX_PAYLOAD_NOACK` command
+    pub dynamic_ack: bool,
+}
+
+impl Features {
+    pub(crate) fn from_register(feature: &Feature) -> Self {
+        Features {
+            dynamic_payloads: feature.en_dpl(),
+            ack_payloads: feature.en_ack_pay(),
+            dynamic_ack: feature.en_dyn_ack(),
+        }
+    }
+}
+
+/// The first `width` bytes of an address register
+pub(crate) fn address(bytes: &[u8], width: u8) -> Address {
+    // `SETUP_AW` 0 is illegal
+    let width = usize::from(width).max(MIN_ADDR_BYTES);
+    let mut address = [0; MAX_ADDR_BYTES];
+    address.copy_from_slice(&bytes[0..MAX_ADDR_BYTES]);
+    Address::new(&address[0..width]).unwrap()
+}
+
 /// Configuration methods
 ///
 /// These seem to work in all modes
@@ -85,6 +231,34 @@ where D: Device {
         Ok(())
     }
 
+    /// Chip variant detected at construction, or set with
+    /// `set_variant()`
+    fn get_variant(&mut self) -> ChipVariant {
+        self.device().variant()
+    }
+
+    /// Override the detected chip variant
+    ///
+    /// Detection is a heuristic, see
+    /// [`ChipVariant`](enum.ChipVariant.html). This runs the bank 1
+    /// init sequence and `ACTIVATE` if `variant` needs them.
+    fn set_variant(&mut self, variant: ChipVariant) -> Result<(), D::Error> {
+        self.device().set_variant(variant);
+        if variant.has_bank1() {
+            variant::init_bank1(self.device())?;
+        }
+        if variant.needs_activate() {
+            variant::activate(self.device())?;
+        }
+        Ok(())
+    }
+
+    /// Read all registers at once, for a dump or a later
+    /// `StandbyMode::restore()`
+    fn snapshot(&mut self) -> Result<RegisterSnapshot, D::Error> {
+        RegisterSnapshot::read(self.device())
+    }
+
     /// Get frequency offset (channel)
     fn get_frequency(&mut self) -> Result<u8, D::Error> {
         let (_, register) = self.device().read_register::<RfCh>()?;
@@ -95,26 +269,30 @@ where D: Device {
     /// Set frequency offset (channel)
     fn set_frequency(
         &mut self,
-        freq_offset: u8,
+        freq_offset: Channel,
     ) -> Result<(), D::Error> {
-        assert!(freq_offset < 126);
-
         let mut register = RfCh(0);
-        register.set_rf_ch(freq_offset);
+        register.set_rf_ch(freq_offset.get());
         self.device().write_register(register)?;
 
         Ok(())
     }
 
-    /// power: `0`: -18 dBm, `3`: 0 dBm
+    /// Set data rate and power level, prefer `set_power_dbm()` on the
+    /// clones
+    ///
+    /// `DataRate::R250Kbps` fails with `Error::Unsupported` on the
+    /// nRF24L01.
     fn set_rf(
         &mut self,
         rate: &DataRate,
-        power: u8,
+        power: PowerLevel,
     ) -> Result<(), D::Error> {
-        assert!(power < 0b100);
+        if *rate == DataRate::R250Kbps && !self.device().variant().has_250kbps() {
+            return Err(self.device().unsupported());
+        }
         let mut register = RfSetup(0);
-        register.set_rf_pwr(power);
+        register.set_rf_pwr(power.get());
 
         let (dr_low, dr_high) = match *rate {
             DataRate::R250Kbps => (true, false),
@@ -128,6 +306,68 @@ where D: Device {
         Ok(())
     }
 
+    /// Output power in dBm, see `ChipVariant::power_levels()`
+    fn get_power_dbm(&mut self) -> Result<i8, D::Error> {
+        let variant = self.device().variant();
+        let (_, register) = self.device().read_register::<RfSetup>()?;
+        let level = variant.power_level(&register);
+        Ok(variant.power_levels()[usize::from(level)])
+    }
+
+    /// Set the highest output power that does not exceed `dbm`, or
+    /// the lowest one
+    ///
+    /// Unlike `set_rf()`, this reaches all levels of the clones, such
+    /// as +7 dBm on the Si24R1 once selected with `set_variant()`.
+    /// Returns the power that has been set.
+    fn set_power_dbm(&mut self, dbm: i8) -> Result<i8, D::Error> {
+        let variant = self.device().variant();
+        let level = variant.power_level_at_most(dbm);
+        self.device().update_register::<RfSetup, _, _>(|register| {
+            variant.set_power_level(register, level);
+        })?;
+        Ok(variant.power_levels()[usize::from(level)])
+    }
+
+    /// Read register `addr` of the second register bank of Beken
+    /// parts into `buf`
+    ///
+    /// Fails with `Error::Unsupported` on the other variants, and
+    /// with `ConfigError::Register` or `ConfigError::RegisterLength`
+    /// for an `addr` above `0x1F` or a `buf` above 32 bytes.
+    fn read_bank1_register(&mut self, addr: u8, buf: &mut [u8]) -> Result<(), D::Error> {
+        if !self.device().variant().has_bank1() {
+            return Err(self.device().unsupported());
+        }
+        if let Err(e) = variant::check_bank1_register(addr, buf.len()) {
+            return Err(self.device().invalid_config(e));
+        }
+        variant::select_bank(self.device(), true)?;
+        let result = self.device().send_command(&ReadRawRegister::new(addr, buf.len()));
+        variant::select_bank(self.device(), false)?;
+        let (_, payload) = result?;
+        buf.copy_from_slice(&payload);
+        Ok(())
+    }
+
+    /// Write register `addr` of the second register bank of Beken
+    /// parts
+    ///
+    /// Fails like `read_bank1_register()`.
+    fn write_bank1_register(&mut self, addr: u8, data: &[u8]) -> Result<(), D::Error> {
+        if !self.device().variant().has_bank1() {
+            return Err(self.device().unsupported());
+        }
+        if let Err(e) = variant::check_bank1_register(addr, data.len()) {
+            return Err(self.device().invalid_config(e));
+        }
+        variant::select_bank(self.device(), true)?;
+        let result = self.device().send_command(&WriteRawRegister::new(addr, data));
+        variant::select_bank(self.device(), false)?;
+        result?;
+        Ok(())
+    }
+
     /// Set CRC mode
     fn set_crc(
         &mut self,
@@ -163,28 +403,62 @@ where D: Device {
     }
 
     /// Set address `addr` of pipe number `pipe_no`
+    ///
+    /// `addr` must have the width set with `set_address_width()`.
+    /// Pipes 2 to 5 only have the LSByte of their own, so the other
+    /// bytes of `addr` must be those of pipe 1, which is to be set
+    /// first.
     fn set_rx_addr(
         &mut self,
-        pipe_no: usize,
-        addr: &[u8],
+        pipe_no: Pipe,
+        addr: &Address,
+    ) -> Result<(), D::Error> {
+        let width = self.get_address_width()?;
+        if addr.width() != width {
+            return Err(self.device().invalid_config(ConfigError::AddressLength(addr.len())));
+        }
+        match pipe_no.index() {
+            0 => {
+                self.device().write_register(RxAddrP0::new(addr))?;
+            }
+            1 => {
+                self.device().write_register(RxAddrP1::new(addr))?;
+            }
+            _ => {
+                let p1 = self.get_rx_addr(Pipe(1))?;
+                if p1[1..] != addr[1..] {
+                    let e = ConfigError::AddressPrefix(pipe_no.index());
+                    return Err(self.device().invalid_config(e));
+                }
+                self.set_rx_addr_suffix(pipe_no, addr[0])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the LSByte of the address of pipe 2 to 5, which shares the
+    /// other bytes with pipe 1
+    ///
+    /// Fails with `ConfigError::Pipe` for pipes 0 and 1.
+    fn set_rx_addr_suffix(
+        &mut self,
+        pipe_no: Pipe,
+        suffix: u8,
     ) -> Result<(), D::Error> {
         macro_rules! w {
             ( $($no: expr, $name: ident);+ ) => (
-                match pipe_no {
+                match pipe_no.index() {
                     $(
                         $no => {
                             use crate::registers::$name;
-                            let register = $name::new(addr);
-                            self.device().write_register(register)?;
+                            self.device().write_register($name(suffix))?;
                         }
                     )+
-                        _ => panic!("No such pipe {}", pipe_no)
+                        _ => return Err(self.device().invalid_config(ConfigError::Pipe(pipe_no.index())))
                 }
             )
         }
-        w!(0, RxAddrP0;
-           1, RxAddrP1;
-           2, RxAddrP2;
+        w!(2, RxAddrP2;
            3, RxAddrP3;
            4, RxAddrP4;
            5, RxAddrP5);
@@ -192,23 +466,32 @@ where D: Device {
     }
 
     /// Set address of the TX pipe
+    ///
+    /// `addr` must have the width set with `set_address_width()`.
     fn set_tx_addr(
         &mut self,
-        addr: &[u8],
+        addr: &Address,
     ) -> Result<(), D::Error> {
-        let register = TxAddr::new(addr);
-        self.device().write_register(register)?;
+        let width = self.get_address_width()?;
+        if addr.width() != width {
+            return Err(self.device().invalid_config(ConfigError::AddressLength(addr.len())));
+        }
+        self.device().write_register(TxAddr::new(addr))?;
         Ok(())
     }
 
     /// Configure auto-retransmit
     ///
-    /// To disable, call as `set_auto_retransmit(0, 0)`.
+    /// To disable, call as `set_auto_retransmit(0, 0)`. Both `delay`
+    /// and `count` are `0..=15`.
     fn set_auto_retransmit(
         &mut self,
         delay: u8,
         count: u8,
     ) -> Result<(), D::Error> {
+        if delay > 0xF || count > 0xF {
+            return Err(self.device().invalid_config(ConfigError::AutoRetransmit(delay, count)));
+        }
         self.device().write_register(auto_retransmit(delay, count))?;
         Ok(())
     }
@@ -236,6 +519,20 @@ where D: Device {
         Ok(())
     }
 
+    /// Set address width in bytes, `3..=5`
+    fn set_address_width(
+        &mut self,
+        width: u8,
+    ) -> Result<(), D::Error> {
+        if !(MIN_ADDR_BYTES..=MAX_ADDR_BYTES).contains(&usize::from(width)) {
+            return Err(self.device().invalid_config(ConfigError::AddressWidth(width)));
+        }
+        let mut register = SetupAw(0);
+        register.set_aw(width - 2);
+        self.device().write_register(register)?;
+        Ok(())
+    }
+
     /// Get address width configuration
     fn get_address_width(
         &mut self,
@@ -244,6 +541,132 @@ where D: Device {
         Ok(2 + register.aw())
     }
 
+    /// Get data rate and `RF_PWR`, see `set_rf()`
+    fn get_rf(
+        &mut self,
+    ) -> Result<(DataRate, PowerLevel), D::Error> {
+        let (_, register) = self.device().read_register::<RfSetup>()?;
+        Ok((DataRate::from_rf_setup(&register), PowerLevel::from_rf_setup(&register)))
+    }
+
+    /// Get CRC mode
+    fn get_crc(
+        &mut self,
+    ) -> Result<CrcMode, D::Error> {
+        let (_, register) = self.device().read_register::<Config>()?;
+        Ok(CrcMode::from_config(&register))
+    }
+
+    /// Get address of the TX pipe
+    fn get_tx_addr(
+        &mut self,
+    ) -> Result<Address, D::Error> {
+        let width = self.get_address_width()?;
+        let (_, register) = self.device().read_register::<TxAddr>()?;
+        Ok(address(register.as_bytes(), width))
+    }
+
+    /// Get address of pipe number `pipe_no`
+    ///
+    /// Pipes 2 to 5 only have the LSByte of their own, and share the
+    /// other bytes with pipe 1.
+    fn get_rx_addr(
+        &mut self,
+        pipe_no: Pipe,
+    ) -> Result<Address, D::Error> {
+        let width = self.get_address_width()?;
+        let (_, p1) = self.device().read_register::<RxAddrP1>()?;
+        let mut bytes = [0; MAX_ADDR_BYTES];
+        bytes.copy_from_slice(&p1.as_bytes()[0..MAX_ADDR_BYTES]);
+        macro_rules! r {
+            ( $($no: expr, $name: ident);+ ) => (
+                match pipe_no.index() {
+                    0 => {
+                        let (_, register) = self.device().read_register::<RxAddrP0>()?;
+                        bytes.copy_from_slice(&register.as_bytes()[0..MAX_ADDR_BYTES]);
+                    }
+                    $(
+                        $no => {
+                            use crate::registers::$name;
+                            let (_, register) = self.device().read_register::<$name>()?;
+                            bytes[0] = register.0;
+                        }
+                    )+
+                    _ => {}
+                }
+            )
+        }
+        r!(2, RxAddrP2;
+           3, RxAddrP3;
+           4, RxAddrP4;
+           5, RxAddrP5);
+        Ok(address(&bytes, width))
+    }
+
+    /// Obtain which RX pipes are enabled
+    fn get_pipes_rx_enable(
+        &mut self,
+    ) -> Result<[bool; PIPES_COUNT], D::Error> {
+        let (_, register) = self.device().read_register::<EnRxaddr>()?;
+        Ok(register.to_bools())
+    }
+
+    /// Get auto-retransmit as `(delay, count)`
+    fn get_auto_retransmit(
+        &mut self,
+    ) -> Result<(u8, u8), D::Error> {
+        let (_, register) = self.device().read_register::<SetupRetr>()?;
+        Ok((register.ard(), register.arc()))
+    }
+
+    /// Get the flags of the `FEATURE` register
+    fn get_features(
+        &mut self,
+    ) -> Result<Features, D::Error> {
+        let (_, register) = self.device().read_register::<Feature>()?;
+        Ok(Features::from_register(&register))
+    }
+
+    /// Read the complete configuration
+    ///
+    /// This is what the chip runs, so it is not validated like
+    /// `RadioConfigBuilder::build()` does.
+    fn get_config(
+        &mut self,
+    ) -> Result<RadioConfig, D::Error> {
+        let (data_rate, _) = self.get_rf()?;
+        let (delay, count) = self.get_auto_retransmit()?;
+        let enabled = self.get_pipes_rx_enable()?;
+        let auto_ack = self.get_auto_ack()?;
+        let lengths = self.get_pipes_rx_lengths()?;
+        let features = self.get_features()?;
+        let mut builder = RadioConfig::builder()
+            .rf_ch(self.get_frequency()?)
+            .data_rate(data_rate)
+            .power_dbm(self.get_power_dbm()?)
+            .crc(self.get_crc()?)
+            .address_width(self.get_address_width()?)
+            .auto_retransmit(delay, count)
+            .tx_addr(&self.get_tx_addr()?)
+            .dynamic_payloads(features.dynamic_payloads)
+            .ack_payloads(features.ack_payloads)
+            .dynamic_ack(features.dynamic_ack);
+        for index in 0..PIPES_COUNT {
+            let pipe = Pipe(index as u8);
+            let addr = self.get_rx_addr(pipe)?;
+            builder = match index {
+                0 | 1 => builder.rx_addr(pipe, &addr),
+                _ => builder.rx_addr_suffix(pipe, addr[0]),
+            };
+            builder = builder.pipe(pipe, PipeConfig {
+                enabled: enabled[index],
+                auto_ack: auto_ack[index],
+                length: lengths[index],
+            });
+        }
+        Ok(builder.unchecked())
+    }
+
     /// Obtain interrupt pending status as `(RX_DR, TX_DR, MAX_RT)`
     /// where `RX_DR` indicates new data in the RX FIFO, `TX_DR`
     /// indicates that a packet has been sent, and `MAX_RT` indicates
@@ -275,13 +698,69 @@ where D: Device {
         }
     }
 
-    /// ## `bools`
+    /// Clear the interrupt flags that are set, and return them as
+    /// events
+    ///
+    /// Only the flags that have been read are cleared, so an event
+    /// that occurs in between raises the **IRQ** pin again. With an
+    /// IRQ pin, nothing is sent over SPI while it is not asserted.
+    /// This never blocks, and can be called from an interrupt handler
+    /// that owns the driver.
+    fn poll_events(&mut self) -> Result<Events, D::Error> {
+        let device = self.device();
+        if !device.irq_asserted()? {
+            return Ok(Events::default());
+        }
+        let (status, ()) = device.send_command(&Nop)?;
+        let mut clear = Status(0);
+        clear.set_rx_dr(status.rx_dr());
+        clear.set_tx_ds(status.tx_ds());
+        clear.set_max_rt(status.max_rt());
+        if clear.0 != 0 {
+            device.write_register(clear)?;
+        }
+        Ok(Events::from_status(&status, device.config().prim_rx()))
+    }
+
+    /// Obtain payload lengths of all RX pipes
+    ///
     /// * `None`: Dynamic payload length
     /// * `Some(len)`: Static payload length `len`
+    fn get_pipes_rx_lengths(
+        &mut self,
+    ) -> Result<[Option<u8>; PIPES_COUNT], D::Error> {
+        let (_, dynpd) = self.device().read_register::<Dynpd>()?;
+        let mut lengths = [None; PIPES_COUNT];
+        macro_rules! get_rx_pw {
+            ($name: ident, $index: expr) => {{
+                use crate::registers::$name;
+                let (_, register) = self.device().read_register::<$name>()?;
+                if !dynpd.dpl_p($index) {
+                    lengths[$index] = Some(register.get());
+                }
+            }};
+        }
+        get_rx_pw!(RxPwP0, 0);
+        get_rx_pw!(RxPwP1, 1);
+        get_rx_pw!(RxPwP2, 2);
+        get_rx_pw!(RxPwP3, 3);
+        get_rx_pw!(RxPwP4, 4);
+        get_rx_pw!(RxPwP5, 5);
+
+        Ok(lengths)
+    }
+
+    /// ## `bools`
+    /// * `None`: Dynamic payload length
+    /// * `Some(len)`: Static payload length `len`, up to 32
     fn set_pipes_rx_lengths(
         &mut self,
         lengths: &[Option<u8>; PIPES_COUNT],
     ) -> Result<(), D::Error> {
+        if let Some(len) = lengths.iter().flatten().find(|len| **len > 32) {
+            return Err(self.device().invalid_config(ConfigError::PayloadLength(*len)));
+        }
+
         // Enable dynamic payload lengths
         let mut bools = [true; PIPES_COUNT];
         for (i, length) in lengths.iter().enumerate() {
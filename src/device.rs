@@ -1,26 +1,52 @@
 use crate::command::Command;
+use crate::error::ConfigError;
 use crate::registers::{Config, Register, Status};
+use crate::variant::ChipVariant;
 
 /// Trait that hides all the GPIO/SPI type parameters for use by the
 /// operation modes
 pub trait Device {
-    /// Error from the SPI implementation
+    /// Error from the SPI or GPIO implementation
     type Error;
 
+    /// Chip variant detected at construction
+    fn variant(&self) -> ChipVariant;
+    /// Override the detected variant
+    fn set_variant(&mut self, variant: ChipVariant);
+    /// Error for an operation that `variant()` does not support
+    fn unsupported(&self) -> Self::Error;
+    /// Error for an invalid configuration value
+    fn invalid_config(&self, e: ConfigError) -> Self::Error;
+    /// Error for a received payload width above 32 bytes
+    fn corrupt_payload(&self, width: u8) -> Self::Error;
+    /// Error for a transmission that did not complete in time
+    fn timeout(&self) -> Self::Error;
+    /// Error for mixing the send methods of `PtxMode`
+    fn busy(&self) -> Self::Error;
+
     /// Set CE pin high
-    fn ce_enable(&mut self);
+    fn ce_enable(&mut self) -> Result<(), Self::Error>;
     /// Set CE pin low
-    fn ce_disable(&mut self);
+    fn ce_disable(&mut self) -> Result<(), Self::Error>;
+    /// Wait with the delay provider given at construction, which
+    /// returns at once for [`NoDelay`](timing/struct.NoDelay.html)
+    fn delay_us(&mut self, us: u32);
+    /// Was a delay provider given at construction? `false` for
+    /// drivers from `new()`, whose `delay_us()` returns at once.
+    fn has_delay(&self) -> bool;
+    /// Is the **IRQ** pin asserted? Always `true` for
+    /// [`NoIrq`](struct.NoIrq.html).
+    fn irq_asserted(&mut self) -> Result<bool, Self::Error>;
     /// Helper; the receiving during RX and sending during TX require `CE`
     /// to be low.
-    fn with_ce_disabled<F, R>(&mut self, f: F) -> R
+    fn with_ce_disabled<F, R>(&mut self, f: F) -> Result<R, Self::Error>
     where
         F: FnOnce(&mut Self) -> R,
     {
-        self.ce_disable();
+        self.ce_disable()?;
         let r = f(self);
-        self.ce_enable();
-        r
+        self.ce_enable()?;
+        Ok(r)
     }
 
     /// Send a command via SPI
@@ -49,6 +75,8 @@ pub trait Device {
         Ok(result)
     }
 
+    /// The (cached) `CONFIG` register
+    fn config(&self) -> &Config;
     /// Modify the (cached) `CONFIG` register and write if it has changed.
     fn update_config<F, R>(&mut self, f: F) -> Result<R, Self::Error>
     where
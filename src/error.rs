@@ -1,18 +1,100 @@
-use core::fmt::Debug;
+use core::convert::Infallible;
+use core::fmt::{self, Debug};
 
-/// Wraps an SPI error
+/// Combined error of the SPI and GPIO implementations
 ///
-/// TODO: eliminate this?
+/// `IRQE` is the error of the **IRQ** pin, which cannot fail without
+/// one.
 #[derive(Debug)]
-pub enum Error<SPIE: Debug> {
+pub enum Error<SPIE: Debug, GPIOE: Debug, IRQE: Debug = Infallible> {
     /// Wrap an SPI error
     SpiError(SPIE),
+    /// Wrap an error of the **CE** pin
+    GpioError(GPIOE),
+    /// Wrap an error of the **IRQ** pin
+    IrqError(IRQE),
     /// Module not connected
     NotConnected,
+    /// Operation not supported by the detected chip variant
+    Unsupported,
+    /// Invalid configuration value
+    Config(ConfigError),
+    /// `R_RX_PL_WID` above 32 bytes, after which the RX FIFO has been
+    /// flushed
+    CorruptPayload(u8),
+    /// The chip did not report a transmission in time
+    Timeout,
+    /// Packets of another send method of `PtxMode` are still in the
+    /// TX FIFO
+    Busy,
 }
 
-impl<SPIE: Debug> From<SPIE> for Error<SPIE> {
+impl<SPIE: Debug, GPIOE: Debug, IRQE: Debug> From<SPIE> for Error<SPIE, GPIOE, IRQE> {
     fn from(e: SPIE) -> Self {
         Error::SpiError(e)
     }
 }
+
+/// Why a configuration is invalid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    /// Channel above 125
+    Channel(u8),
+    /// Address width other than 3, 4 or 5 bytes
+    AddressWidth(u8),
+    /// Address whose length differs from the address width, or a
+    /// suffix of pipes 2 to 5 that is not one byte
+    AddressLength(usize),
+    /// Pipe number above 5, or a pipe that does not support the
+    /// operation
+    Pipe(usize),
+    /// Power level above 3
+    Power(u8),
+    /// Auto-retransmit delay or count above 15
+    AutoRetransmit(u8, u8),
+    /// Payload, or static payload length, above 32 bytes
+    PayloadLength(u8),
+    /// Auto-ack enabled with CRC disabled, which the chip overrides
+    AutoAckWithoutCrc,
+    /// Dynamic payload length on a pipe without `EN_DPL`
+    DynamicWithoutEnDpl(usize),
+    /// Dynamic payload length on a pipe without auto-ack
+    DynamicWithoutAutoAck(usize),
+    /// ACK payloads without `EN_DPL`
+    AckPayloadWithoutEnDpl,
+    /// Address of pipe 2 to 5 whose upper bytes differ from pipe 1
+    AddressPrefix(usize),
+    /// Register address above `0x1F`
+    Register(u8),
+    /// Register content above 32 bytes
+    RegisterLength(usize),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Channel(channel) => write!(f, "no such channel {}", channel),
+            ConfigError::AddressWidth(width) => write!(f, "invalid address width {}", width),
+            ConfigError::AddressLength(len) => write!(f, "address length {} does not match the width", len),
+            ConfigError::Pipe(pipe) => write!(f, "invalid pipe {}", pipe),
+            ConfigError::Power(level) => write!(f, "no such power level {}", level),
+            ConfigError::AutoRetransmit(delay, count) => {
+                write!(f, "invalid auto-retransmit delay {} or count {}", delay, count)
+            }
+            ConfigError::PayloadLength(len) => write!(f, "payload length {} above 32", len),
+            ConfigError::AutoAckWithoutCrc => write!(f, "auto-ack requires CRC"),
+            ConfigError::DynamicWithoutEnDpl(pipe) => {
+                write!(f, "dynamic payload length on pipe {} requires EN_DPL", pipe)
+            }
+            ConfigError::DynamicWithoutAutoAck(pipe) => {
+                write!(f, "dynamic payload length on pipe {} requires auto-ack", pipe)
+            }
+            ConfigError::AckPayloadWithoutEnDpl => write!(f, "ACK payloads require EN_DPL"),
+            ConfigError::AddressPrefix(pipe) => {
+                write!(f, "address of pipe {} must share the upper bytes of pipe 1", pipe)
+            }
+            ConfigError::Register(addr) => write!(f, "no such register 0x{:02X}", addr),
+            ConfigError::RegisterLength(len) => write!(f, "register length {} above 32", len),
+        }
+    }
+}
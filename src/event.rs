@@ -0,0 +1,135 @@
+use core::convert::Infallible;
+use core::fmt::Debug;
+use embedded_hal::digital::InputPin;
+
+use crate::config::Pipe;
+use crate::registers::Status;
+
+/// The active-low **IRQ** pin, or [`NoIrq`](struct.NoIrq.html)
+///
+/// Implemented for every `InputPin`. Its errors are reported as
+/// [`Error::IrqError`](enum.Error.html#variant.IrqError).
+pub trait IrqPin {
+    /// Error of reading the pin
+    type Error: Debug;
+
+    /// Is the pin low?
+    fn is_asserted(&mut self) -> Result<bool, Self::Error>;
+}
+
+impl<P: InputPin> IrqPin for P {
+    type Error = P::Error;
+
+    fn is_asserted(&mut self) -> Result<bool, P::Error> {
+        self.is_low()
+    }
+}
+
+/// No **IRQ** pin connected
+///
+/// Reads as always asserted, so that the `STATUS` register is checked
+/// over SPI every time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoIrq;
+
+impl IrqPin for NoIrq {
+    type Error = Infallible;
+
+    fn is_asserted(&mut self) -> Result<bool, Infallible> {
+        Ok(true)
+    }
+}
+
+/// One cause of the **IRQ** pin being asserted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// `RX_DR` in PRX, with the pipe of the packet at the head of the
+    /// RX FIFO
+    RxReady(Pipe),
+    /// `TX_DS`: a packet has been sent, and acknowledged if auto-ack
+    /// is enabled. In PRX an ACK payload has been sent.
+    TxSent,
+    /// `MAX_RT`: the packet at the head of the TX FIFO has not been
+    /// acknowledged, and stays there until flushed
+    MaxRetries,
+    /// `RX_DR` in PTX: an ACK payload is in the RX FIFO
+    AckPayloadReceived,
+    /// `RX_DR` in PRX, but `RX_P_NO` names no pipe because the RX
+    /// FIFO has been read empty since
+    RxDrained,
+}
+
+/// Set of events returned by
+/// [`Configuration::poll_events()`](trait.Configuration.html#method.poll_events)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Events {
+    rx_ready: Option<Pipe>,
+    rx_drained: bool,
+    tx_sent: bool,
+    max_retries: bool,
+    ack_payload_received: bool,
+}
+
+impl Events {
+    /// Map the flags of `status`, where `prim_rx` tells whether
+    /// `RX_DR` is a packet or an ACK payload
+    pub(crate) fn from_status(status: &Status, prim_rx: bool) -> Self {
+        let rx_dr = status.rx_dr();
+        // RX_P_NO is 0b111 when the RX FIFO has been read since
+        let pipe = Pipe::new(status.rx_p_no().into()).ok();
+        Events {
+            rx_ready: if rx_dr && prim_rx { pipe } else { None },
+            rx_drained: rx_dr && prim_rx && pipe.is_none(),
+            tx_sent: status.tx_ds(),
+            max_retries: status.max_rt(),
+            ack_payload_received: rx_dr && !prim_rx,
+        }
+    }
+
+    /// No event has occurred
+    pub fn is_empty(&self) -> bool {
+        *self == Events::default()
+    }
+
+    /// Pipe of a received packet
+    pub fn rx_ready(&self) -> Option<Pipe> {
+        self.rx_ready
+    }
+
+    /// Has a packet been received that has been read since?
+    pub fn rx_drained(&self) -> bool {
+        self.rx_drained
+    }
+
+    /// Has a packet been sent?
+    pub fn tx_sent(&self) -> bool {
+        self.tx_sent
+    }
+
+    /// Has the maximum number of retransmits been reached?
+    pub fn max_retries(&self) -> bool {
+        self.max_retries
+    }
+
+    /// Has an ACK payload been received?
+    pub fn ack_payload_received(&self) -> bool {
+        self.ack_payload_received
+    }
+
+    /// Does the set contain `event`?
+    pub fn contains(&self, event: Event) -> bool {
+        self.iter().any(|e| e == event)
+    }
+
+    /// All events in the set
+    pub fn iter(&self) -> impl Iterator<Item = Event> {
+        let flag = |set: bool, event: Event| if set { Some(event) } else { None };
+        self.rx_ready
+            .map(Event::RxReady)
+            .into_iter()
+            .chain(flag(self.rx_drained, Event::RxDrained))
+            .chain(flag(self.tx_sent, Event::TxSent))
+            .chain(flag(self.max_retries, Event::MaxRetries))
+            .chain(flag(self.ack_payload_received, Event::AckPayloadReceived))
+    }
+}
@@ -12,15 +12,22 @@
 #![no_std]
 #[macro_use]
 extern crate bitfield;
+#[cfg(feature = "sim")]
+#[macro_use]
+extern crate std;
 
 use core::fmt;
-use core::fmt::Debug;
-use embedded_hal::blocking::spi::Transfer as SpiTransfer;
-use embedded_hal::digital::v2::OutputPin;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::OutputPin;
+use embedded_hal::spi::SpiDevice;
 
 mod config;
-pub use crate::config::{Configuration, CrcMode, DataRate};
+pub use crate::config::{Channel, Configuration, CrcMode, DataRate, Features, Pipe, PowerLevel};
+mod address;
+pub use crate::address::Address;
 pub mod setup;
+pub mod timing;
+use crate::timing::NoDelay;
 
 mod registers;
 use crate::registers::{Config, Register, SetupAw, Status};
@@ -29,7 +36,9 @@ use crate::command::{Command, ReadRegister, WriteRegister};
 mod payload;
 pub use crate::payload::Payload;
 mod error;
-pub use crate::error::Error;
+pub use crate::error::{ConfigError, Error};
+mod event;
+pub use crate::event::{Event, Events, IrqPin, NoIrq};
 
 mod device;
 pub use crate::device::Device;
@@ -40,8 +49,29 @@ pub use crate::rx::RxMode;
 mod tx;
 pub use crate::tx::TxMode;
 mod rxtx;
+pub use crate::rxtx::{MaxRtPolicy, Outcome, PacketId, Received, SendReceiveResult};
 mod ptx;
 pub use crate::ptx::PtxMode;
+mod prx;
+pub use crate::prx::PrxMode;
+mod beacon;
+pub use crate::beacon::BeaconMode;
+mod carrier;
+pub use crate::carrier::CarrierTestMode;
+mod powerdown;
+pub use crate::powerdown::PowerDownMode;
+mod selftest;
+pub use crate::selftest::{Diagnosis, SelfTestReport};
+mod radio_config;
+pub use crate::radio_config::{PipeConfig, RadioConfig, RadioConfigBuilder};
+mod snapshot;
+pub use crate::snapshot::{RegisterChange, RegisterSnapshot};
+mod variant;
+pub use crate::variant::ChipVariant;
+#[cfg(feature = "async")]
+pub mod asynch;
+#[cfg(feature = "sim")]
+pub mod sim;
 
 /// Number of RX pipes with configurable addresses
 pub const PIPES_COUNT: usize = 6;
@@ -57,30 +87,137 @@ pub const MAX_ADDR_BYTES: usize = 5;
 /// * [`StandbyMode<D>`](struct.StandbyMode.html)
 /// * [`RxMode<D>`](struct.RxMode.html)
 /// * [`TxMode<D>`](struct.TxMode.html)
+/// * [`PtxMode<D>`](struct.PtxMode.html)
+/// * [`PrxMode<D>`](struct.PrxMode.html)
+/// * [`BeaconMode<D>`](struct.BeaconMode.html)
+/// * [`CarrierTestMode<D>`](struct.CarrierTestMode.html)
+/// * [`PowerDownMode<D>`](struct.PowerDownMode.html)
 ///
 /// where `D: `[`Device`](trait.Device.html)
-pub struct NRF24L01<E: Debug, CE: OutputPin<Error = E>, CSN: OutputPin<Error = E>, SPI: SpiTransfer<u8>> {
+pub struct NRF24L01<CE, SPI, DELAY = NoDelay, IRQ = NoIrq>
+where
+    CE: OutputPin,
+    SPI: SpiDevice<u8>,
+    DELAY: DelayNs,
+    IRQ: IrqPin,
+{
     ce: CE,
-    csn: CSN,
     spi: SPI,
+    delay: DELAY,
+    irq: IRQ,
+    /// Constructed with a delay provider, not with `new()`
+    has_delay: bool,
     config: Config,
+    variant: ChipVariant,
 }
 
-impl<E: Debug, CE: OutputPin<Error = E>, CSN: OutputPin<Error = E>, SPI: SpiTransfer<u8, Error = SPIE>, SPIE: Debug> fmt::Debug
-    for NRF24L01<E, CE, CSN, SPI>
+impl<CE, SPI, DELAY, IRQ> fmt::Debug for NRF24L01<CE, SPI, DELAY, IRQ>
+where
+    CE: OutputPin,
+    SPI: SpiDevice<u8>,
+    DELAY: DelayNs,
+    IRQ: IrqPin,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "NRF24L01")
     }
 }
 
-impl<E: Debug, CE: OutputPin<Error = E>, CSN: OutputPin<Error = E>, SPI: SpiTransfer<u8, Error = SPIE>, SPIE: Debug>
-    NRF24L01<E, CE, CSN, SPI>
-{
+impl<CE: OutputPin, SPI: SpiDevice<u8>> NRF24L01<CE, SPI> {
     /// Construct a new driver instance.
-    pub fn new(mut ce: CE, mut csn: CSN, spi: SPI) -> Result<StandbyMode<Self>, Error<SPIE>> {
-        ce.set_low().unwrap();
-        csn.set_high().unwrap();
+    ///
+    /// `spi` is expected to drive the **CSN** pin itself, so that the
+    /// bus can be shared with other chips.
+    ///
+    /// Transitions do not wait for the chip to settle, see
+    /// [`new_with_delay()`](#method.new_with_delay).
+    pub fn new(ce: CE, spi: SPI) -> Result<StandbyMode<Self>, Error<SPI::Error, CE::Error>> {
+        let device = Self::init(ce, NoIrq, spi, NoDelay, false)?;
+        StandbyMode::power_up(device).map_err(|(_, e)| e)
+    }
+
+    /// Construct a new driver instance, leaving the chip powered down
+    pub fn new_powered_down(ce: CE, spi: SPI) -> Result<PowerDownMode<Self>, Error<SPI::Error, CE::Error>> {
+        let device = Self::init(ce, NoIrq, spi, NoDelay, false)?;
+        Self::power_down(device)
+    }
+}
+
+impl<CE: OutputPin, SPI: SpiDevice<u8>, DELAY: DelayNs> NRF24L01<CE, SPI, DELAY> {
+    /// Construct a new driver instance that waits with `delay`
+    ///
+    /// Powering up waits for the 1.5 ms start-up time, and entering
+    /// RX, PRX, or carrier test mode for the 130 µs settling time, so
+    /// that the returned mode is ready.
+    pub fn new_with_delay(ce: CE, spi: SPI, delay: DELAY) -> Result<StandbyMode<Self>, Error<SPI::Error, CE::Error>> {
+        Self::new_with_irq(ce, NoIrq, spi, delay)
+    }
+
+    /// Construct a new driver instance that waits with `delay`,
+    /// leaving the chip powered down
+    pub fn new_powered_down_with_delay(
+        ce: CE,
+        spi: SPI,
+        delay: DELAY,
+    ) -> Result<PowerDownMode<Self>, Error<SPI::Error, CE::Error>> {
+        Self::new_powered_down_with_irq(ce, NoIrq, spi, delay)
+    }
+}
+
+impl<CE, SPI, DELAY, IRQ> NRF24L01<CE, SPI, DELAY, IRQ>
+where
+    CE: OutputPin,
+    SPI: SpiDevice<u8>,
+    DELAY: DelayNs,
+    IRQ: IrqPin,
+{
+    /// Construct a new driver instance with the active-low **IRQ**
+    /// pin, that waits with `delay`
+    ///
+    /// [`poll_events()`](trait.Configuration.html#method.poll_events)
+    /// checks `irq` before using SPI. Passing
+    /// [`NoDelay`](timing/struct.NoDelay.html) skips the waits, but
+    /// [`BeaconMode::repeat()`](struct.BeaconMode.html#method.repeat)
+    /// then times out too early, as it measures time with `delay`.
+    pub fn new_with_irq(
+        ce: CE,
+        irq: IRQ,
+        spi: SPI,
+        delay: DELAY,
+    ) -> Result<StandbyMode<Self>, <Self as Device>::Error> {
+        let device = Self::init(ce, irq, spi, delay, true)?;
+        StandbyMode::power_up(device).map_err(|(_, e)| e)
+    }
+
+    /// Construct a new driver instance with the **IRQ** pin, leaving
+    /// the chip powered down
+    ///
+    /// `CONFIG` is written even if `PWR_UP` is already clear, in case
+    /// the chip was left powered up by an earlier run.
+    pub fn new_powered_down_with_irq(
+        ce: CE,
+        irq: IRQ,
+        spi: SPI,
+        delay: DELAY,
+    ) -> Result<PowerDownMode<Self>, <Self as Device>::Error> {
+        let device = Self::init(ce, irq, spi, delay, true)?;
+        Self::power_down(device)
+    }
+
+    fn power_down(mut device: Self) -> Result<PowerDownMode<Self>, <Self as Device>::Error> {
+        let config = device.config.clone();
+        device.write_register(config)?;
+        Ok(PowerDownMode::new(device))
+    }
+
+    fn init(
+        mut ce: CE,
+        irq: IRQ,
+        spi: SPI,
+        delay: DELAY,
+        has_delay: bool,
+    ) -> Result<Self, <Self as Device>::Error> {
+        ce.set_low().map_err(Error::GpioError)?;
 
         // Reset value
         let mut config = Config(0b0000_1000);
@@ -89,41 +226,103 @@ impl<E: Debug, CE: OutputPin<Error = E>, CSN: OutputPin<Error = E>, SPI: SpiTran
         config.set_mask_max_rt(false);
         let mut device = NRF24L01 {
             ce,
-            csn,
             spi,
+            delay,
+            irq,
+            has_delay,
             config,
+            variant: ChipVariant::Nrf24l01Plus,
         };
 
+        // A Beken part may have been left in bank 1
+        variant::select_bank(&mut device, false)?;
+
         match device.is_connected() {
             Err(e) => return Err(e),
             Ok(false) => return Err(Error::NotConnected),
             _ => {}
         }
 
-        // TODO: activate features?
+        device.variant = variant::detect(&mut device)?;
+        if device.variant.has_bank1() {
+            variant::init_bank1(&mut device)?;
+        }
+        if device.variant.needs_activate() {
+            variant::activate(&mut device)?;
+        }
 
-        StandbyMode::power_up(device).map_err(|(_, e)| e)
+        Ok(device)
     }
 
     /// Reads and validates content of the `SETUP_AW` register.
-    pub fn is_connected(&mut self) -> Result<bool, Error<SPIE>> {
+    ///
+    /// The `AW` field encodes 3 to 5 bytes as `0b01..=0b11`, and the
+    /// upper bits are reserved. A floating or shorted MISO fails
+    /// this check. See
+    /// [`StandbyMode::self_test()`](struct.StandbyMode.html#method.self_test)
+    /// for a thorough test.
+    pub fn is_connected(&mut self) -> Result<bool, <Self as Device>::Error> {
         let (_, setup_aw) = self.read_register::<SetupAw>()?;
-        let valid = setup_aw.aw() >= 3 && setup_aw.aw() <= 5;
+        let valid = setup_aw.aw() >= 1 && setup_aw.aw() <= 3 && setup_aw.0 & 0xFC == 0;
         Ok(valid)
     }
 }
 
-impl<E: Debug, CE: OutputPin<Error = E>, CSN: OutputPin<Error = E>, SPI: SpiTransfer<u8, Error = SPIE>, SPIE: Debug> Device
-    for NRF24L01<E, CE, CSN, SPI>
+impl<CE, SPI, DELAY, IRQ> Device for NRF24L01<CE, SPI, DELAY, IRQ>
+where
+    CE: OutputPin,
+    SPI: SpiDevice<u8>,
+    DELAY: DelayNs,
+    IRQ: IrqPin,
 {
-    type Error = Error<SPIE>;
+    type Error = Error<SPI::Error, CE::Error, IRQ::Error>;
+
+    fn variant(&self) -> ChipVariant {
+        self.variant
+    }
+
+    fn set_variant(&mut self, variant: ChipVariant) {
+        self.variant = variant;
+    }
+
+    fn unsupported(&self) -> Self::Error {
+        Error::Unsupported
+    }
+
+    fn invalid_config(&self, e: ConfigError) -> Self::Error {
+        Error::Config(e)
+    }
+
+    fn corrupt_payload(&self, width: u8) -> Self::Error {
+        Error::CorruptPayload(width)
+    }
 
-    fn ce_enable(&mut self) {
-        self.ce.set_high().unwrap();
+    fn timeout(&self) -> Self::Error {
+        Error::Timeout
     }
 
-    fn ce_disable(&mut self) {
-        self.ce.set_low().unwrap();
+    fn busy(&self) -> Self::Error {
+        Error::Busy
+    }
+
+    fn ce_enable(&mut self) -> Result<(), Self::Error> {
+        self.ce.set_high().map_err(Error::GpioError)
+    }
+
+    fn ce_disable(&mut self) -> Result<(), Self::Error> {
+        self.ce.set_low().map_err(Error::GpioError)
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        self.delay.delay_us(us);
+    }
+
+    fn has_delay(&self) -> bool {
+        self.has_delay
+    }
+
+    fn irq_asserted(&mut self) -> Result<bool, Self::Error> {
+        self.irq.is_asserted().map_err(Error::IrqError)
     }
 
     fn send_command<C: Command>(
@@ -137,12 +336,8 @@ impl<E: Debug, CE: OutputPin<Error = E>, CSN: OutputPin<Error = E>, SPI: SpiTran
         // Serialize the command
         command.encode(buf);
 
-        // SPI transaction
-        self.csn.set_low().unwrap();
-        let transfer_result = self.spi.transfer(buf).map(|_| {});
-        self.csn.set_high().unwrap();
-        // Propagate Err only after csn.set_high():
-        transfer_result?;
+        // SPI transaction, CSN is asserted by the `SpiDevice`
+        self.spi.transfer_in_place(buf).map_err(Error::SpiError)?;
 
         // Parse response
         let status = Status(buf[0]);
@@ -160,6 +355,10 @@ impl<E: Debug, CE: OutputPin<Error = E>, CSN: OutputPin<Error = E>, SPI: SpiTran
         self.send_command(&ReadRegister::new())
     }
 
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
     fn update_config<F, R>(&mut self, f: F) -> Result<R, Self::Error>
     where
         F: FnOnce(&mut Config) -> R,
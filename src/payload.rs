@@ -1,18 +1,34 @@
+use core::convert::TryFrom;
+use core::fmt;
 use core::ops::Deref;
 
+use crate::error::ConfigError;
+
+/// Longest payload of a packet
+pub(crate) const MAX_LEN: usize = 32;
+
+/// Fail for a payload that does not fit into a packet
+pub(crate) fn check_len(payload: &[u8]) -> Result<(), ConfigError> {
+    if payload.len() > MAX_LEN {
+        let len = u8::try_from(payload.len()).unwrap_or(u8::MAX);
+        return Err(ConfigError::PayloadLength(len));
+    }
+    Ok(())
+}
+
 /// Represents a received packet. Stores 32 bytes and the actual length.
 ///
 /// Use [`as_ref()`](#method.as_ref) or [`Deref`](#impl-Deref) to
 /// obtain a slice of the content.
 pub struct Payload {
-    data: [u8; 32],
+    data: [u8; MAX_LEN],
     len: usize,
 }
 
 impl Payload {
     /// Copy a slice
     pub fn new(source: &[u8]) -> Self {
-        let mut data = [0; 32];
+        let mut data = [0; MAX_LEN];
         let len = source.len().min(data.len());
         data[0..len].copy_from_slice(&source[0..len]);
         Payload { data, len }
@@ -22,6 +38,11 @@ impl Payload {
     pub fn len(&self) -> usize {
         self.len
     }
+
+    /// Is the payload empty?
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
 }
 
 impl AsRef<[u8]> for Payload {
@@ -33,6 +54,12 @@ impl AsRef<[u8]> for Payload {
 impl Deref for Payload {
     type Target = [u8];
     fn deref(&self) -> &[u8] {
-        &self.as_ref()
+        self.as_ref()
+    }
+}
+
+impl fmt::Debug for Payload {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Payload({:02X?})", self.as_ref())
     }
 }
@@ -0,0 +1,44 @@
+use core::fmt;
+
+use crate::config::Configuration;
+use crate::device::{Device, UsingDevice};
+use crate::standby::StandbyMode;
+
+/// Represents **Power Down** mode
+///
+/// The oscillator is off and the chip draws less than 1 µA, but the
+/// registers keep their content and stay writable over SPI.
+pub struct PowerDownMode<D: Device> {
+    device: D,
+}
+
+impl<D: Device> fmt::Debug for PowerDownMode<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PowerDownMode")
+    }
+}
+
+impl<D: Device> UsingDevice<D> for PowerDownMode<D> {
+    fn device(&mut self) -> &mut D {
+        &mut self.device
+    }
+}
+
+impl<D: Device> Configuration<D> for PowerDownMode<D> {}
+
+impl<D: Device> PowerDownMode<D> {
+    /// Relies on `PWR_UP` being cleared by the caller
+    pub(crate) fn new(device: D) -> Self {
+        PowerDownMode { device }
+    }
+
+    /// Power up into Standby-I
+    ///
+    /// Waits for the 1.5 ms start-up time with the delay provider of
+    /// the driver. With [`NoDelay`](timing/struct.NoDelay.html), wait
+    /// [`POWER_UP_US`](timing/constant.POWER_UP_US.html) before
+    /// starting RX or TX.
+    pub fn power_up(self) -> Result<StandbyMode<D>, (D, D::Error)> {
+        StandbyMode::power_up(self.device)
+    }
+}
@@ -0,0 +1,118 @@
+use core::fmt;
+
+use crate::command::{FlushTx, WriteAckPayload};
+use crate::config::{Configuration, Pipe};
+use crate::device::{Device, UsingDevice};
+use crate::payload;
+use crate::registers::{FifoStatus, Status};
+use crate::rx::read_payload;
+use crate::rxtx::{AckQueue, Received};
+use crate::standby::StandbyMode;
+use crate::PIPES_COUNT;
+
+/// In PRX mode, the device receives packets and answers them with
+/// ACK payloads. It's the complement to PTX mode
+pub struct PrxMode<D: Device> {
+    device: D,
+    acks: AckQueue,
+}
+
+impl<D: Device> fmt::Debug for PrxMode<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PrxMode")
+    }
+}
+
+impl<D: Device> UsingDevice<D> for PrxMode<D> {
+    fn device(&mut self) -> &mut D {
+        &mut self.device
+    }
+}
+
+impl<D: Device> Configuration<D> for PrxMode<D> {}
+
+impl<D: Device> PrxMode<D> {
+    /// Relies on everything being set up by `StandbyMode::prx()`, from
+    /// which it is called
+    pub(crate) fn new(device: D) -> Self {
+        PrxMode {
+            device,
+            acks: AckQueue::new(),
+        }
+    }
+
+    /// Disable `CE` so that you can switch into another mode.
+    ///
+    /// ACK payloads that have not been delivered are flushed, so that
+    /// they are not sent as packets in TX mode.
+    pub fn standby(mut self) -> Result<StandbyMode<D>, (D, D::Error)> {
+        if let Err(e) = self.device.send_command(&FlushTx) {
+            return Err((self.device, e));
+        }
+        StandbyMode::from_rx_tx(self.device)
+    }
+
+    /// Queue an ACK payload for the next packet on `pipe`
+    ///
+    /// Returns `false` if the TX FIFO already holds 3 ACK payloads.
+    /// Fails with `ConfigError::PayloadLength` for a `payload` above
+    /// 32 bytes.
+    pub fn queue_ack_payload(&mut self, pipe: Pipe, payload: &[u8]) -> Result<bool, D::Error> {
+        if let Err(e) = payload::check_len(payload) {
+            return Err(self.device.invalid_config(e));
+        }
+        let pipe = pipe.index() as u8;
+
+        if self.acks.is_full() {
+            let (_, fifo_status) = self.device.read_register::<FifoStatus>()?;
+            self.acks.update(false, fifo_status.tx_empty(), false);
+            if self.acks.is_full() {
+                return Ok(false);
+            }
+        }
+        self.device.send_command(&WriteAckPayload::new(pipe, payload))?;
+        self.acks.push(pipe);
+        Ok(true)
+    }
+
+    /// Number of queued ACK payloads that have not been reported as
+    /// delivered yet
+    pub fn pending_ack_payloads(&self) -> usize {
+        self.acks.len()
+    }
+
+    /// Number of ACK payloads per pipe that have been delivered since
+    /// the last call
+    ///
+    /// An ACK payload counts as delivered when the packet it answered
+    /// has been read, or when the TX FIFO has run empty.
+    pub fn take_delivered(&mut self) -> [u8; PIPES_COUNT] {
+        self.acks.take_delivered()
+    }
+
+    /// Read the next received packet, if any
+    ///
+    /// This function acknowledges all interrupts, so the caller must
+    /// repeat the call until the function returns None before waiting
+    /// for the next RX interrupt.
+    pub fn read(&mut self) -> Result<Option<Received>, D::Error> {
+        let (status, fifo_status) = self.device.read_register::<FifoStatus>()?;
+        if status.rx_dr() || status.tx_ds() || status.max_rt() {
+            let mut clear = Status(0);
+            clear.set_rx_dr(true);
+            clear.set_tx_ds(true);
+            clear.set_max_rt(true);
+            self.device.write_register(clear)?;
+        }
+        self.acks
+            .update(status.tx_ds(), fifo_status.tx_empty(), fifo_status.rx_empty());
+        if fifo_status.rx_empty() {
+            return Ok(None);
+        }
+
+        let pipe = status.rx_p_no();
+        let payload = read_payload(&mut self.device, pipe)?;
+        self.acks.received(pipe);
+        Ok(Some(Received { pipe, payload }))
+    }
+}
@@ -2,15 +2,56 @@
 use core::fmt;
 
 use crate::device::{ Device, UsingDevice };
-use crate::command::{ FlushTx, ReadRxPayload, ReadRxPayloadWidth, WriteTxPayload };
-use crate::rxtx::{ Received, SendReceiveResult };
-use crate::registers::{ FifoStatus, Status };
+use crate::command::{ FlushTx, WriteTxPayload, WriteTxPayloadNoAck };
+use crate::rxtx::{ MaxRtPolicy, Outcome, PacketId, PacketQueue, Received, SendReceiveResult, TxShadow };
+use crate::registers::{ Dynpd, EnAa, Feature, FifoStatus, ObserveTx, SetupRetr, Status };
 use crate::config::Configuration;
+use crate::payload;
+use crate::rx::read_payload;
+use crate::standby::StandbyMode;
+use crate::tx::handle_max_rt;
+use crate::PIPES_COUNT;
+
+/// Registers that `StandbyMode::ptx()` changes, as they were before
+pub(crate) struct PtxRegisters {
+    pub(crate) en_aa: EnAa,
+    pub(crate) setup_retr: SetupRetr,
+    pub(crate) feature: Feature,
+    pub(crate) dynpd: Dynpd,
+}
+
+impl PtxRegisters {
+    pub(crate) fn read<D: Device>(device: &mut D) -> Result<Self, D::Error> {
+        let (_, en_aa) = device.read_register()?;
+        let (_, setup_retr) = device.read_register()?;
+        let (_, feature) = device.read_register()?;
+        let (_, dynpd) = device.read_register()?;
+        Ok(PtxRegisters { en_aa, setup_retr, feature, dynpd })
+    }
+
+    pub(crate) fn write<D: Device>(&self, device: &mut D) -> Result<(), D::Error> {
+        device.write_register(self.en_aa.clone())?;
+        device.write_register(self.setup_retr.clone())?;
+        // FEATURE enables DYNPD
+        device.write_register(self.feature.clone())?;
+        device.write_register(self.dynpd.clone())?;
+        Ok(())
+    }
+}
 
 /// In PTX mode, the device transmits packets immediately, and receives packets
 /// only as acknowledge payloads. It's the complement to PRX mode
 pub struct PtxMode<D: Device> {
     device: D,
+    /// `EN_DYN_ACK` has been set
+    dyn_ack: bool,
+    /// Registers before entering this mode
+    saved: PtxRegisters,
+    /// Packets from `send()`
+    queue: PacketQueue,
+    /// Packets from `send_receive()` and `send_no_ack()`, and the
+    /// `MAX_RT` policy
+    shadow: TxShadow,
 }
 
 impl<D: Device> fmt::Debug for PtxMode<D> {
@@ -30,30 +71,181 @@ impl <D: Device> Configuration<D> for PtxMode<D> {
 }
 
 impl<D: Device> PtxMode<D> {
+    /// Wait until the TX FIFO is empty, and disable `CE`
+    ///
+    /// Like `TxMode::standby()`, the `MaxRtPolicy` applies when a
+    /// packet reaches the maximum number of retries. Packets pending from
+    /// `send()` are sent first, and their outcomes are discarded. ACK
+    /// payloads that have not been read stay in the RX FIFO. `EN_AA`, `SETUP_RETR`,
+    /// `FEATURE` and `DYNPD` keep the PTX setup, see
+    /// [`standby_and_restore()`](#method.standby_and_restore).
+    pub fn standby(mut self) -> Result<StandbyMode<D>, (D, D::Error)> {
+        match self.wait_empty() {
+            Ok(()) => StandbyMode::from_rx_tx(self.device),
+            Err(e) => Err((self.device, e)),
+        }
+    }
+
+    /// Like `standby()`, and restore the registers that
+    /// `StandbyMode::ptx()` changed
+    pub fn standby_and_restore(mut self) -> Result<StandbyMode<D>, (D, D::Error)> {
+        match self.wait_empty().and_then(|()| self.saved.write(&mut self.device)) {
+            Ok(()) => StandbyMode::from_rx_tx(self.device),
+            Err(e) => Err((self.device, e)),
+        }
+    }
+
+    fn wait_empty(&mut self) -> Result<(), D::Error> {
+        while self.queue.pending() > 0 {
+            if let Err(nb::Error::Other(e)) = self.poll_outcome() {
+                return Err(e);
+            }
+        }
+        loop {
+            let (status, fifo_status) = self.device.read_register::<FifoStatus>()?;
+            if status.max_rt() {
+                handle_max_rt(&mut self.device, &status, &mut self.shadow)?;
+                continue;
+            }
+            if fifo_status.tx_empty() {
+                break;
+            }
+            self.device.ce_enable()?;
+        }
+        let mut clear = Status(0);
+        clear.set_tx_ds(true);
+        clear.set_max_rt(true);
+        let status = self.device.write_register(clear)?;
+        self.shadow.observe(&status);
+        Ok(())
+    }
+
+    /// Queue a packet, and obtain a token for its outcome
+    ///
+    /// Returns `None` if 3 packets are pending. They are written to
+    /// the chip one at a time, so that
+    /// [`poll_outcome()`](#method.poll_outcome) can tell the
+    /// retransmissions and the ACK payload of each. Fails with
+    /// `Error::Busy` while packets from `send_receive()` or
+    /// `send_no_ack()` are in the TX FIFO, or ACK payloads received
+    /// for them are in the RX FIFO, and with
+    /// `ConfigError::PayloadLength` for a `payload` above 32 bytes.
+    pub fn send(&mut self, payload: &[u8]) -> Result<Option<PacketId>, D::Error> {
+        if let Err(e) = payload::check_len(payload) {
+            return Err(self.device.invalid_config(e));
+        }
+        if self.queue.is_full() {
+            return Ok(None);
+        }
+        if self.queue.pending() == 0 {
+            self.check_idle()?;
+        }
+        let id = self.queue.push(payload);
+        self.start_next()?;
+        Ok(Some(id))
+    }
+
+    /// Number of packets from `send()` whose outcome has not been
+    /// reported yet
+    pub fn pending(&self) -> usize {
+        self.queue.pending()
+    }
+
+    /// Poll the outcome of the packet in the chip, oldest first
+    ///
+    /// The next queued packet is written once the current one has
+    /// been reported, so call this regularly while packets are
+    /// pending. After an ACK payload fails with
+    /// `Error::CorruptPayload`, the next call reports the packet as
+    /// delivered without it.
+    pub fn poll_outcome(&mut self) -> nb::Result<Outcome, D::Error> {
+        let id = match self.queue.in_flight() {
+            Some(id) => id,
+            None => return Err(nb::Error::WouldBlock),
+        };
+        // OBSERVE_TX is reset when the next packet starts
+        let (status, observe_tx) = self.device.read_register::<ObserveTx>()?;
+        let mut clear = Status(0);
+        let outcome = if status.tx_ds() {
+            // RX_P_NO is 0b111 when the RX FIFO has been flushed
+            let ack_payload = if status.rx_dr() && usize::from(status.rx_p_no()) < PIPES_COUNT {
+                Some(read_payload(&mut self.device, status.rx_p_no())?)
+            } else {
+                None
+            };
+            clear.set_tx_ds(true);
+            clear.set_rx_dr(true);
+            self.shadow.observe(&status);
+            Outcome::Delivered { id, retransmits: observe_tx.arc_cnt(), ack_payload }
+        } else if status.max_rt() {
+            // The packet stays in the FIFO until it is flushed
+            self.device.send_command(&FlushTx)?;
+            clear.set_max_rt(true);
+            Outcome::Dropped { id }
+        } else {
+            return Err(nb::Error::WouldBlock);
+        };
+        self.device.write_register(clear)?;
+        self.queue.done();
+        self.start_next()?;
+        Ok(outcome)
+    }
+
+    /// Check that no packet from `send_receive()` or `send_no_ack()`,
+    /// and no ACK payload for them is left, and clear their `TX_DS`
+    /// and `RX_DR` for `poll_outcome()`
+    fn check_idle(&mut self) -> Result<(), D::Error> {
+        let (status, fifo_status) = self.device.read_register::<FifoStatus>()?;
+        if !fifo_status.tx_empty() || !fifo_status.rx_empty() {
+            return Err(self.device.busy());
+        }
+        if status.tx_ds() || status.rx_dr() {
+            let mut clear = Status(0);
+            clear.set_tx_ds(status.tx_ds());
+            clear.set_rx_dr(status.rx_dr());
+            self.device.write_register(clear)?;
+            self.shadow.observe(&status);
+        }
+        Ok(())
+    }
+
+    fn start_next(&mut self) -> Result<(), D::Error> {
+        if let Some(payload) = self.queue.start() {
+            self.device.send_command(&WriteTxPayload::new(payload))?;
+            self.device.ce_enable()?;
+        }
+        Ok(())
+    }
 
     /// Send asynchronously
+    ///
+    /// Fails with `Error::Busy` while packets from `send()` are
+    /// pending, and with `ConfigError::PayloadLength` for a payload
+    /// above 32 bytes.
     pub fn send_receive(
             &mut self,
             send: Option<&[u8]>
         ) -> Result<SendReceiveResult, D::Error> {
+        if self.queue.pending() > 0 {
+            return Err(self.device.busy());
+        }
+        if let Err(e) = payload::check_len(send.unwrap_or_default()) {
+            return Err(self.device.invalid_config(e));
+        }
         let (status, fifo_status) = self.device.read_register::<FifoStatus>()?;
-        let dropped = match status.max_rt() {
-            true => {
-                self.device.send_command(&FlushTx)?;
-                let mut clear_max_rt = Status(0);
-                clear_max_rt.set_max_rt(true);
-                self.device.write_register(clear_max_rt)?;
-                true
-            },
-            false => false
+        let discarded = match status.max_rt() {
+            true => handle_max_rt(&mut self.device, &status, &mut self.shadow)?,
+            false => 0
         };
+        let dropped = discarded > 0;
 
         let sent = match (fifo_status.tx_full(), send) {
                 (true, _) => false,
                 (false, None) => false,
                 (false, Some(payload)) => {
                     self.device.send_command(&WriteTxPayload::new(payload))?;
-                    self.device.ce_enable();
+                    self.shadow.record(payload, false);
+                    self.device.ce_enable()?;
                     true
                 }
             };
@@ -61,18 +253,62 @@ impl<D: Device> PtxMode<D> {
         let received = match fifo_status.rx_empty() {
                 true => None,
                 false => {
-                    let (_, payload_width) = self.device.send_command(&ReadRxPayloadWidth)?;
-                    let (_, payload) = self.device.send_command(&ReadRxPayload::new(payload_width as usize))?;
-                    Some(Received { pipe: status.rx_p_no(), payload: payload})
+                    let payload = read_payload(&mut self.device, status.rx_p_no())?;
+                    Some(Received { pipe: status.rx_p_no(), payload })
                 }
             };
-        Ok(SendReceiveResult { sent, received, dropped })
+        Ok(SendReceiveResult { sent, received, dropped, discarded })
+    }
+
+    /// Send asynchronously, without requesting an acknowledgement
+    ///
+    /// The packet is sent once, and no ACK payload can be received for
+    /// it. Returns `false` if the TX FIFO is full. Enables `EN_DYN_ACK`
+    /// on first use. Fails with `Error::Busy` while packets from
+    /// `send()` are pending, and with `ConfigError::PayloadLength` for
+    /// a `payload` above 32 bytes.
+    pub fn send_no_ack(&mut self, payload: &[u8]) -> Result<bool, D::Error> {
+        if self.queue.pending() > 0 {
+            return Err(self.device.busy());
+        }
+        if let Err(e) = payload::check_len(payload) {
+            return Err(self.device.invalid_config(e));
+        }
+        let (_, fifo_status) = self.device.read_register::<FifoStatus>()?;
+        if fifo_status.tx_full() {
+            return Ok(false);
+        }
+        if !self.dyn_ack {
+            self.device.update_register::<Feature, _, _>(|feature| {
+                feature.set_en_dyn_ack(true);
+            })?;
+            self.dyn_ack = true;
+        }
+        self.device.send_command(&WriteTxPayloadNoAck::new(payload))?;
+        self.shadow.record(payload, true);
+        self.device.ce_enable()?;
+        Ok(true)
     }
 }
 
 impl<D: Device> PtxMode<D> {
     /// Relies on everything being set up by `StandbyMode::ptx()`, from which it is called
-    pub(crate) fn new(device: D) -> Self {
-        PtxMode { device }
+    pub(crate) fn new(device: D, saved: PtxRegisters) -> Self {
+        PtxMode {
+            device,
+            dyn_ack: false,
+            saved,
+            queue: PacketQueue::new(),
+            shadow: TxShadow::new(),
+        }
+    }
+
+    /// Select what happens when a packet from `send_receive()` is not
+    /// acknowledged after the maximum number of retransmits, by
+    /// default [`MaxRtPolicy::FlushAll`](enum.MaxRtPolicy.html#variant.FlushAll)
+    ///
+    /// Packets from `send()` are always reported as dropped.
+    pub fn set_max_rt_policy(&mut self, policy: MaxRtPolicy) {
+        self.shadow.set_policy(policy);
     }
 }
@@ -0,0 +1,432 @@
+use crate::command::{ReadRawRegister, WriteRawRegister};
+use crate::address::Address;
+use crate::config::{auto_retransmit, Channel, CrcMode, DataRate, Pipe, MAX_CHANNEL};
+use crate::device::{Device, UsingDevice};
+use crate::error::ConfigError;
+use crate::registers::{
+    Dynpd, EnAa, EnRxaddr, Feature, RfCh, RfSetup, RxPwP0, RxPwP1, RxPwP2, RxPwP3, RxPwP4,
+    RxPwP5, SetupAw, Value,
+};
+use crate::standby::StandbyMode;
+use crate::variant::ChipVariant;
+use crate::{MAX_ADDR_BYTES, MIN_ADDR_BYTES, PIPES_COUNT};
+
+/// Number of registers written by `RadioConfig`, besides `CONFIG`
+const REGISTERS: usize = 21;
+/// Longest payload
+const MAX_PAYLOAD_LEN: u8 = 32;
+
+/// Settings of one RX pipe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipeConfig {
+    /// Receive on this pipe, `EN_RXADDR`
+    pub enabled: bool,
+    /// Acknowledge received packets, `EN_AA`
+    pub auto_ack: bool,
+    /// * `None`: Dynamic payload length
+    /// * `Some(len)`: Static payload length `len`, `0` leaves the
+    ///   pipe unused
+    pub length: Option<u8>,
+}
+
+/// A complete, validated radio configuration
+///
+/// Build one with [`RadioConfig::builder()`](#method.builder), and
+/// write it with
+/// [`StandbyMode::apply_config()`](struct.StandbyMode.html#method.apply_config).
+#[derive(Debug, Clone, PartialEq)]
+pub struct RadioConfig {
+    channel: u8,
+    data_rate: DataRate,
+    power_dbm: i8,
+    crc: CrcMode,
+    address_width: u8,
+    auto_retransmit: (u8, u8),
+    tx_addr: Address,
+    /// Full addresses of pipes 0 and 1
+    rx_addrs: [Address; 2],
+    /// LSByte of the addresses of pipes 2 to 5
+    rx_suffixes: [u8; PIPES_COUNT - 2],
+    pipes: [PipeConfig; PIPES_COUNT],
+    dynamic_payloads: bool,
+    ack_payloads: bool,
+    dynamic_ack: bool,
+}
+
+impl Default for RadioConfig {
+    /// The reset values of the chip
+    fn default() -> Self {
+        RadioConfig {
+            channel: 2,
+            data_rate: DataRate::R2Mbps,
+            power_dbm: 0,
+            crc: CrcMode::OneByte,
+            address_width: 5,
+            auto_retransmit: (0, 3),
+            tx_addr: Address::from_array([0xE7; MAX_ADDR_BYTES]),
+            rx_addrs: [Address::from_array([0xE7; MAX_ADDR_BYTES]), Address::from_array([0xC2; MAX_ADDR_BYTES])],
+            rx_suffixes: [0xC3, 0xC4, 0xC5, 0xC6],
+            pipes: [
+                PipeConfig { enabled: true, auto_ack: true, length: Some(0) },
+                PipeConfig { enabled: true, auto_ack: true, length: Some(0) },
+                PipeConfig { enabled: false, auto_ack: true, length: Some(0) },
+                PipeConfig { enabled: false, auto_ack: true, length: Some(0) },
+                PipeConfig { enabled: false, auto_ack: true, length: Some(0) },
+                PipeConfig { enabled: false, auto_ack: true, length: Some(0) },
+            ],
+            dynamic_payloads: false,
+            ack_payloads: false,
+            dynamic_ack: false,
+        }
+    }
+}
+
+impl RadioConfig {
+    /// Start from the reset values of the chip
+    pub fn builder() -> RadioConfigBuilder {
+        RadioConfigBuilder {
+            config: RadioConfig::default(),
+            prefixes: [None; PIPES_COUNT - 2],
+            error: None,
+        }
+    }
+
+    /// `RF_CH`, the frequency is `2400 + channel` MHz
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+
+    /// Air data rate
+    pub fn data_rate(&self) -> DataRate {
+        self.data_rate
+    }
+
+    /// Output power in dBm, rounded down to a level of the chip
+    /// variant when applied
+    pub fn power_dbm(&self) -> i8 {
+        self.power_dbm
+    }
+
+    /// CRC mode
+    pub fn crc(&self) -> CrcMode {
+        self.crc
+    }
+
+    /// Address width in bytes
+    pub fn address_width(&self) -> u8 {
+        self.address_width
+    }
+
+    /// Auto-retransmit as `(delay, count)`, see
+    /// `Configuration::set_auto_retransmit()`
+    pub fn auto_retransmit(&self) -> (u8, u8) {
+        self.auto_retransmit
+    }
+
+    /// Address of the TX pipe
+    pub fn tx_addr(&self) -> Address {
+        self.tx_addr
+    }
+
+    /// Address of `pipe`
+    ///
+    /// Pipes 2 to 5 only have the LSByte of their own, and share the
+    /// other bytes with pipe 1.
+    pub fn rx_addr(&self, pipe: Pipe) -> Address {
+        match pipe.index() {
+            index @ (0 | 1) => self.rx_addrs[index],
+            index => self.rx_addrs[1].with_lsbyte(self.rx_suffixes[index - 2]),
+        }
+    }
+
+    /// Settings of all RX pipes
+    pub fn pipes(&self) -> &[PipeConfig; PIPES_COUNT] {
+        &self.pipes
+    }
+
+    /// `EN_DPL`
+    pub fn dynamic_payloads(&self) -> bool {
+        self.dynamic_payloads
+    }
+
+    /// `EN_ACK_PAY`
+    pub fn ack_payloads(&self) -> bool {
+        self.ack_payloads
+    }
+
+    /// `EN_DYN_ACK`
+    pub fn dynamic_ack(&self) -> bool {
+        self.dynamic_ack
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.channel > MAX_CHANNEL {
+            return Err(ConfigError::Channel(self.channel));
+        }
+        let width = usize::from(self.address_width);
+        if !(MIN_ADDR_BYTES..=MAX_ADDR_BYTES).contains(&width) {
+            return Err(ConfigError::AddressWidth(self.address_width));
+        }
+        for addr in self.rx_addrs.iter().chain(Some(&self.tx_addr)) {
+            if addr.len() != width {
+                return Err(ConfigError::AddressLength(addr.len()));
+            }
+        }
+        let (delay, count) = self.auto_retransmit;
+        if delay > 0xF || count > 0xF {
+            return Err(ConfigError::AutoRetransmit(delay, count));
+        }
+        if self.ack_payloads && !self.dynamic_payloads {
+            return Err(ConfigError::AckPayloadWithoutEnDpl);
+        }
+        for (i, pipe) in self.pipes.iter().enumerate() {
+            if pipe.auto_ack && self.crc == CrcMode::Disabled {
+                return Err(ConfigError::AutoAckWithoutCrc);
+            }
+            match pipe.length {
+                Some(len) if len > MAX_PAYLOAD_LEN => return Err(ConfigError::PayloadLength(len)),
+                Some(_) => {}
+                None if !self.dynamic_payloads => return Err(ConfigError::DynamicWithoutEnDpl(i)),
+                None if !pipe.auto_ack => return Err(ConfigError::DynamicWithoutAutoAck(i)),
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Content of all registers but `CONFIG`, in the order they are
+    /// to be written
+    pub(crate) fn registers(&self, variant: ChipVariant) -> [Value; REGISTERS] {
+        let mut setup_aw = SetupAw(0);
+        setup_aw.set_aw(self.address_width - 2);
+
+        let mut rf_ch = RfCh(0);
+        rf_ch.set_rf_ch(self.channel);
+
+        let mut rf_setup = RfSetup(0);
+        let (dr_low, dr_high) = match self.data_rate {
+            DataRate::R250Kbps => (true, false),
+            DataRate::R1Mbps => (false, false),
+            DataRate::R2Mbps => (false, true),
+        };
+        rf_setup.set_rf_dr_low(dr_low);
+        rf_setup.set_rf_dr_high(dr_high);
+        variant.set_power_level(&mut rf_setup, variant.power_level_at_most(self.power_dbm));
+
+        let mut feature = Feature(0);
+        feature.set_en_dpl(self.dynamic_payloads);
+        feature.set_en_ack_pay(self.ack_payloads);
+        feature.set_en_dyn_ack(self.dynamic_ack);
+
+        let enabled = self.pipes.map(|pipe| pipe.enabled);
+        let auto_ack = self.pipes.map(|pipe| pipe.auto_ack);
+        let dynamic = self.pipes.map(|pipe| pipe.length.is_none());
+        let length = self.pipes.map(|pipe| pipe.length.unwrap_or(0));
+        macro_rules! rx_pw {
+            ($name: ident, $index: expr) => {{
+                let mut register = $name(0);
+                register.set(length[$index]);
+                Value::of(register)
+            }};
+        }
+
+        let (delay, count) = self.auto_retransmit;
+        [
+            // SETUP_AW before the addresses
+            Value::of(setup_aw),
+            Value::of(EnAa::from_bools(&auto_ack)),
+            Value::of(EnRxaddr::from_bools(&enabled)),
+            Value::of(auto_retransmit(delay, count)),
+            Value::of(rf_ch),
+            Value::of(rf_setup),
+            Value::raw(0x0A, self.rx_addrs[0].as_bytes()),
+            Value::raw(0x0B, self.rx_addrs[1].as_bytes()),
+            Value::raw(0x0C, &self.rx_suffixes[0..1]),
+            Value::raw(0x0D, &self.rx_suffixes[1..2]),
+            Value::raw(0x0E, &self.rx_suffixes[2..3]),
+            Value::raw(0x0F, &self.rx_suffixes[3..4]),
+            Value::raw(0x10, self.tx_addr.as_bytes()),
+            rx_pw!(RxPwP0, 0),
+            rx_pw!(RxPwP1, 1),
+            rx_pw!(RxPwP2, 2),
+            rx_pw!(RxPwP3, 3),
+            rx_pw!(RxPwP4, 4),
+            rx_pw!(RxPwP5, 5),
+            // FEATURE enables DYNPD
+            Value::of(feature),
+            Value::of(Dynpd::from_bools(&dynamic)),
+        ]
+    }
+}
+
+/// Collects the settings of a [`RadioConfig`](struct.RadioConfig.html)
+///
+/// Invalid values are reported by `build()`.
+#[derive(Debug, Clone)]
+pub struct RadioConfigBuilder {
+    config: RadioConfig,
+    /// Full addresses given for pipes 2 to 5, whose upper bytes must
+    /// be those of pipe 1
+    prefixes: [Option<Address>; PIPES_COUNT - 2],
+    /// First error that cannot be kept until `build()`
+    error: Option<ConfigError>,
+}
+
+impl RadioConfigBuilder {
+    /// Set `RF_CH`
+    pub fn channel(mut self, channel: Channel) -> Self {
+        self.config.channel = channel.get();
+        self
+    }
+
+    /// Set `RF_CH` as read from the chip, for `unchecked()`
+    pub(crate) fn rf_ch(mut self, channel: u8) -> Self {
+        self.config.channel = channel;
+        self
+    }
+
+    /// Set the air data rate
+    pub fn data_rate(mut self, data_rate: DataRate) -> Self {
+        self.config.data_rate = data_rate;
+        self
+    }
+
+    /// Set the output power, see `Configuration::set_power_dbm()`
+    pub fn power_dbm(mut self, dbm: i8) -> Self {
+        self.config.power_dbm = dbm;
+        self
+    }
+
+    /// Set the CRC mode
+    pub fn crc(mut self, crc: CrcMode) -> Self {
+        self.config.crc = crc;
+        self
+    }
+
+    /// Set the address width in bytes, `3..=5`
+    pub fn address_width(mut self, width: u8) -> Self {
+        self.config.address_width = width;
+        self
+    }
+
+    /// Set auto-retransmit `delay` and `count`, both `0..=15`
+    pub fn auto_retransmit(mut self, delay: u8, count: u8) -> Self {
+        self.config.auto_retransmit = (delay, count);
+        self
+    }
+
+    /// Set the address of the TX pipe
+    pub fn tx_addr(mut self, addr: &Address) -> Self {
+        self.config.tx_addr = *addr;
+        self
+    }
+
+    /// Set the address of `pipe`
+    ///
+    /// Pipes 2 to 5 only have the LSByte of their own, so the other
+    /// bytes of `addr` must be those of pipe 1, which `build()`
+    /// checks.
+    pub fn rx_addr(mut self, pipe: Pipe, addr: &Address) -> Self {
+        match pipe.index() {
+            index @ (0 | 1) => self.config.rx_addrs[index] = *addr,
+            index => {
+                self.config.rx_suffixes[index - 2] = addr[0];
+                self.prefixes[index - 2] = Some(*addr);
+            }
+        }
+        self
+    }
+
+    /// Set the LSByte of the address of pipe 2 to 5, which shares the
+    /// other bytes with pipe 1
+    ///
+    /// `build()` fails with `ConfigError::Pipe` for pipes 0 and 1.
+    pub fn rx_addr_suffix(mut self, pipe: Pipe, suffix: u8) -> Self {
+        match pipe.index() {
+            0 | 1 => self.fail(ConfigError::Pipe(pipe.index())),
+            index => {
+                self.config.rx_suffixes[index - 2] = suffix;
+                self.prefixes[index - 2] = None;
+            }
+        }
+        self
+    }
+
+    /// Set up one RX pipe
+    pub fn pipe(mut self, pipe: Pipe, config: PipeConfig) -> Self {
+        self.config.pipes[pipe.index()] = config;
+        self
+    }
+
+    /// Set up all RX pipes the same way
+    pub fn all_pipes(mut self, config: PipeConfig) -> Self {
+        self.config.pipes = [config; PIPES_COUNT];
+        self
+    }
+
+    /// Enable dynamic payload lengths, `EN_DPL`
+    pub fn dynamic_payloads(mut self, enable: bool) -> Self {
+        self.config.dynamic_payloads = enable;
+        self
+    }
+
+    /// Enable ACK payloads, `EN_ACK_PAY`
+    pub fn ack_payloads(mut self, enable: bool) -> Self {
+        self.config.ack_payloads = enable;
+        self
+    }
+
+    /// Enable `W_TX_PAYLOAD_NOACK`, `EN_DYN_ACK`
+    pub fn dynamic_ack(mut self, enable: bool) -> Self {
+        self.config.dynamic_ack = enable;
+        self
+    }
+
+    /// Validate all settings together
+    pub fn build(self) -> Result<RadioConfig, ConfigError> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        self.config.validate()?;
+        let p1 = &self.config.rx_addrs[1];
+        for (i, prefix) in self.prefixes.iter().enumerate() {
+            match prefix {
+                Some(addr) if addr[1..] != p1[1..] => return Err(ConfigError::AddressPrefix(i + 2)),
+                _ => {}
+            }
+        }
+        Ok(self.config)
+    }
+
+    /// Skip validation, for a configuration read from the chip
+    pub(crate) fn unchecked(self) -> RadioConfig {
+        self.config
+    }
+
+    fn fail(&mut self, e: ConfigError) {
+        self.error.get_or_insert(e);
+    }
+}
+
+impl<D: Device> StandbyMode<D> {
+    /// Write a configuration
+    ///
+    /// Only registers whose content differs are written.
+    /// `DataRate::R250Kbps` fails with `Error::Unsupported` on variants
+    /// without it, before anything is written.
+    pub fn apply_config(&mut self, config: &RadioConfig) -> Result<(), D::Error> {
+        let device = self.device();
+        if config.data_rate() == DataRate::R250Kbps && !device.variant().has_250kbps() {
+            return Err(device.unsupported());
+        }
+
+        device.update_config(|register| config.crc().set_config(register))?;
+        for value in config.registers(device.variant()).iter() {
+            let (_, current) = device.send_command(&ReadRawRegister::new(value.addr, value.len))?;
+            if *current != *value.as_bytes() {
+                device.send_command(&WriteRawRegister::new(value.addr, value.as_bytes()))?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -15,8 +15,48 @@ pub trait Register {
     fn decode(data: &[u8]) -> Self;
 }
 
+/// Raw content of one register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Value {
+    pub(crate) addr: u8,
+    bytes: [u8; MAX_ADDR_BYTES],
+    pub(crate) len: usize,
+}
+
+impl Value {
+    pub(crate) fn of<R: Register>(register: R) -> Self {
+        let mut value = Value {
+            addr: R::addr(),
+            bytes: [0; MAX_ADDR_BYTES],
+            len: register.write_len(),
+        };
+        register.encode(&mut value.bytes[0..value.len]);
+        value
+    }
+
+    pub(crate) fn raw(addr: u8, bytes: &[u8]) -> Self {
+        let mut value = Value {
+            addr,
+            bytes: [0; MAX_ADDR_BYTES],
+            len: bytes.len(),
+        };
+        value.bytes[0..bytes.len()].copy_from_slice(bytes);
+        value
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.bytes[0..self.len]
+    }
+
+    /// Decode as register `R`
+    pub(crate) fn get<R: Register>(&self) -> R {
+        R::decode(self.as_bytes())
+    }
+}
+
 macro_rules! def_simple {
     ($name: ident) => {
+        #[derive(Debug)]
         pub struct $name(pub u8);
 
         impl $name {
@@ -66,6 +106,7 @@ macro_rules! impl_register {
 
 macro_rules! def_address_register {
     ($name: ident, $addr: expr) => {
+        #[derive(Debug)]
         pub struct $name {
             addr: [u8; MAX_ADDR_BYTES],
             len: u8,
@@ -75,6 +116,10 @@ macro_rules! def_address_register {
             pub fn new(buf: &[u8]) -> Self {
                 Self::decode(buf)
             }
+
+            pub fn as_bytes(&self) -> &[u8] {
+                &self.addr[0..self.len.into()]
+            }
         }
 
         impl Register for $name {
@@ -225,6 +270,10 @@ bitfield! {
     pub struct RfSetup(u8);
     impl Debug;
 
+    /// Continuous carrier transmit, nRF24L01+ and Si24R1 only
+    pub cont_wave, set_cont_wave: 7;
+    /// Force PLL lock signal, only for testing
+    pub pll_lock, set_pll_lock: 4;
     /// Set for 250 kbps
     pub rf_dr_low, set_rf_dr_low: 5;
     /// Set for 2 Mbps
@@ -235,6 +284,9 @@ bitfield! {
     /// * `10`: -6 dBm
     /// * `11`: 0 dBm
     pub u8, rf_pwr, set_rf_pwr: 2, 1;
+    /// RF output power of the Si24R1, which extends `rf_pwr` by
+    /// the obsolete bit 0
+    pub u8, rf_pwr_si24r1, set_rf_pwr_si24r1: 2, 0;
 }
 impl_register!(RfSetup, 0x06);
 
@@ -254,6 +306,8 @@ bitfield! {
     pub u8, rx_p_no, _: 3, 1;
     /// TX FIFO full flag
     pub tx_full, _: 0;
+    /// Selected register bank, Beken parts only
+    pub rbank, _: 7;
 }
 impl_register!(Status, 0x07);
 
@@ -321,6 +375,7 @@ bitfield! {
 impl_register!(FifoStatus, 0x17);
 
 /// Enable Dynamic Payload length
+#[derive(Debug)]
 pub struct Dynpd(pub u8);
 impl_register!(Dynpd, 0x1C);
 def_pipes_accessors!(Dynpd, 0, dpl_p, set_dpl_p);
@@ -1,11 +1,64 @@
-use crate::command::{ReadRxPayload, ReadRxPayloadWidth};
+use crate::command::{FlushRx, Nop, ReadRxPayload, ReadRxPayloadWidth};
 use crate::config::Configuration;
 use crate::device::{ Device, UsingDevice };
-use crate::payload::Payload;
-use crate::registers::{FifoStatus, Status, CD};
+use crate::payload::{self, Payload};
+use crate::registers::{
+    Dynpd, Feature, FifoStatus, RxPwP0, RxPwP1, RxPwP2, RxPwP3, RxPwP4, RxPwP5, Status, CD,
+};
+use crate::rxtx::Received;
 use crate::standby::StandbyMode;
+use crate::PIPES_COUNT;
 use core::fmt;
 
+/// Read the payload at the head of the RX FIFO, received on `pipe`
+///
+/// Pipes with a static payload length are read with their `RX_PW_Px`
+/// length, all others with `R_RX_PL_WID`. A width above 32 bytes
+/// flushes the RX FIFO, as the datasheet requires, and fails with
+/// `Error::CorruptPayload`.
+pub(crate) fn read_payload<D: Device>(device: &mut D, pipe: u8) -> Result<Payload, D::Error> {
+    let width = match static_payload_width(device, pipe)? {
+        Some(width) => width,
+        None => device.send_command(&ReadRxPayloadWidth)?.1,
+    };
+    if usize::from(width) > payload::MAX_LEN {
+        device.send_command(&FlushRx)?;
+        return Err(device.corrupt_payload(width));
+    }
+    let (_, payload) = device.send_command(&ReadRxPayload::new(width.into()))?;
+    Ok(payload)
+}
+
+/// `RX_PW_Px` of `pipe`, or `None` for a dynamic payload length
+///
+/// `RX_P_NO` of an empty RX FIFO names no pipe, which is taken as
+/// dynamic.
+fn static_payload_width<D: Device>(device: &mut D, pipe: u8) -> Result<Option<u8>, D::Error> {
+    if usize::from(pipe) >= PIPES_COUNT {
+        return Ok(None);
+    }
+    let (_, feature) = device.read_register::<Feature>()?;
+    let (_, dynpd) = device.read_register::<Dynpd>()?;
+    if feature.en_dpl() && dynpd.dpl_p(pipe.into()) {
+        return Ok(None);
+    }
+
+    macro_rules! rx_pw {
+        ($name: ident) => {
+            device.read_register::<$name>()?.1.get()
+        };
+    }
+    let width = match pipe {
+        0 => rx_pw!(RxPwP0),
+        1 => rx_pw!(RxPwP1),
+        2 => rx_pw!(RxPwP2),
+        3 => rx_pw!(RxPwP3),
+        4 => rx_pw!(RxPwP4),
+        _ => rx_pw!(RxPwP5),
+    };
+    Ok(Some(width))
+}
+
 /// Represents **RX Mode**
 pub struct RxMode<D: Device> {
     device: D,
@@ -35,7 +88,7 @@ impl<D: Device> RxMode<D> {
     }
 
     /// Disable `CE` so that you can switch into TX mode.
-    pub fn standby(self) -> StandbyMode<D> {
+    pub fn standby(self) -> Result<StandbyMode<D>, (D, D::Error)> {
         StandbyMode::from_rx_tx(self.device)
     }
 
@@ -77,6 +130,17 @@ impl<D: Device> RxMode<D> {
             .map(|(_, cd)| cd.0 & 1 == 1)
     }
 
+    /// Has a signal above -64 dBm been received?
+    ///
+    /// The `RPD` register replaced carrier detect on the nRF24L01+.
+    /// Fails with `Error::Unsupported` on the nRF24L01.
+    pub fn received_power_detected(&mut self) -> Result<bool, D::Error> {
+        if !self.device.variant().has_rpd() {
+            return Err(self.device.unsupported());
+        }
+        self.has_carrier()
+    }
+
     /// Is the RX queue empty?
     pub fn is_empty(&mut self) -> Result<bool, D::Error> {
         self.device
@@ -92,11 +156,41 @@ impl<D: Device> RxMode<D> {
     }
 
     /// Read the next received packet
+    ///
+    /// Use `receive()` to get the pipe number of the same packet. Like
+    /// `receive()`, static payload lengths are taken into account,
+    /// and a width above 32 bytes fails with `Error::CorruptPayload`.
     pub fn read(&mut self) -> Result<Payload, D::Error> {
-        let (_, payload_width) = self.device.send_command(&ReadRxPayloadWidth)?;
-        let (_, payload) = self
-            .device
-            .send_command(&ReadRxPayload::new(payload_width as usize))?;
-        Ok(payload)
+        let (status, ()) = self.device.send_command(&Nop)?;
+        read_payload(&mut self.device, status.rx_p_no())
+    }
+
+    /// Read the next received packet together with its pipe number,
+    /// if there is any
+    ///
+    /// Unlike `can_read()` followed by `read()`, the pipe number and
+    /// the emptiness of the RX FIFO are taken from the same `STATUS`,
+    /// so they always belong to the packet that is read. Pipes with
+    /// a static payload length are read with their `RX_PW_Px` length.
+    /// A width above 32 bytes flushes the RX FIFO and fails with
+    /// `Error::CorruptPayload`.
+    ///
+    /// Acknowledges all interrupts like `can_read()`, so call it
+    /// until it returns `None` before waiting for the next RX
+    /// interrupt.
+    pub fn receive(&mut self) -> Result<Option<Received>, D::Error> {
+        let mut clear = Status(0);
+        clear.set_rx_dr(true);
+        clear.set_tx_ds(true);
+        clear.set_max_rt(true);
+        let status = self.device.write_register(clear)?;
+        // RX_P_NO is 0b111 when the RX FIFO is empty
+        let pipe = status.rx_p_no();
+        if usize::from(pipe) >= PIPES_COUNT {
+            return Ok(None);
+        }
+
+        let payload = read_payload(&mut self.device, pipe)?;
+        Ok(Some(Received { pipe, payload }))
     }
 }
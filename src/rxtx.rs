@@ -1,14 +1,303 @@
-
 use crate::payload::Payload;
+use crate::registers::Status;
+use crate::PIPES_COUNT;
 
+/// A packet received together with its pipe number
+#[derive(Debug)]
 pub struct Received {
+    /// Pipe on which the packet has been received
     pub pipe: u8,
+    /// Packet content
     pub payload: Payload,
 }
 
+/// Outcome of [`PtxMode::send_receive()`](struct.PtxMode.html#method.send_receive)
 pub struct SendReceiveResult {
+    /// ACK payload that has been received
     pub received: Option<Received>,
+    /// The packet has been queued for sending
     pub sent: bool,
     /// When a packet is unacknowledged after it's maximum retries, this flag is set
     pub dropped: bool,
+    /// Number of packets discarded according to the
+    /// [`MaxRtPolicy`](enum.MaxRtPolicy.html)
+    pub discarded: usize,
+}
+
+/// What to do when a packet reaches the maximum number of
+/// retransmits, and the chip sets `MAX_RT`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MaxRtPolicy {
+    /// Flush the TX FIFO, discarding the packets behind the failed
+    /// one as well
+    #[default]
+    FlushAll,
+    /// Discard only the failed packet, and keep sending the others
+    DropHead,
+    /// Clear `MAX_RT` so that the chip sends the failed packet again,
+    /// up to `retries` times, and then drop it like `DropHead`
+    ///
+    /// The first retry waits `backoff_us`, and every further retry
+    /// twice as long as the one before, using the delay provider of
+    /// the driver. This needs one that actually waits, see
+    /// `NRF24L01::new_with_delay()`: with
+    /// [`NoDelay`](timing/struct.NoDelay.html) the retries follow
+    /// each other at once, and fail for the same reason as the first
+    /// attempt.
+    Retry {
+        /// Retries in software
+        retries: u8,
+        /// Wait before the first retry
+        backoff_us: u32,
+    },
+}
+
+/// Copies of the last packets written to the TX FIFO
+///
+/// The FIFO holds at most the last 3 packets, so after `MAX_RT` the
+/// packets behind the failed one can be written again.
+pub(crate) struct TxShadow {
+    /// Payloads, and whether they were sent without ACK
+    packets: [(Payload, bool); TX_FIFO_DEPTH],
+    /// Index of the next write
+    next: usize,
+    policy: MaxRtPolicy,
+    /// Software retries of the failed packet so far
+    retries: u8,
+}
+
+impl TxShadow {
+    pub fn new() -> Self {
+        TxShadow {
+            packets: [
+                (Payload::new(&[]), false),
+                (Payload::new(&[]), false),
+                (Payload::new(&[]), false),
+            ],
+            next: 0,
+            policy: MaxRtPolicy::default(),
+            retries: 0,
+        }
+    }
+
+    pub fn policy(&self) -> MaxRtPolicy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: MaxRtPolicy) {
+        self.policy = policy;
+        self.retries = 0;
+    }
+
+    /// A packet has been written to the TX FIFO
+    pub fn record(&mut self, payload: &[u8], no_ack: bool) {
+        self.packets[self.next] = (Payload::new(payload), no_ack);
+        self.next = (self.next + 1) % TX_FIFO_DEPTH;
+    }
+
+    /// The last `count` packets written, oldest first
+    pub fn last(&self, count: usize) -> impl Iterator<Item = &(Payload, bool)> {
+        (0..count).map(move |i| &self.packets[(self.next + 2 * TX_FIFO_DEPTH - count + i) % TX_FIFO_DEPTH])
+    }
+
+    /// `TX_DS` has been read in `status`, or is cleared, so that the
+    /// next `MAX_RT` is for a new packet
+    pub fn observe(&mut self, status: &Status) {
+        if status.tx_ds() {
+            self.retries = 0;
+        }
+    }
+
+    /// Time to wait before retrying the failed packet, or `None` if
+    /// it is to be dropped
+    ///
+    /// `delivered` tells that `TX_DS` has been set since the last
+    /// `MAX_RT`, so that the failed packet is a new one.
+    pub fn retry(&mut self, delivered: bool) -> Option<u32> {
+        if delivered {
+            self.retries = 0;
+        }
+        match self.policy {
+            MaxRtPolicy::Retry { retries, backoff_us } if self.retries < retries => {
+                let backoff = backoff_us.saturating_mul(1 << u32::from(self.retries).min(31));
+                self.retries += 1;
+                Some(backoff)
+            }
+            _ => {
+                self.retries = 0;
+                None
+            }
+        }
+    }
+}
+
+/// Token of a packet queued with
+/// [`PtxMode::send()`](struct.PtxMode.html#method.send)
+///
+/// Ids count up from 0 for each `PtxMode`, and wrap around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PacketId(u32);
+
+impl PacketId {
+    /// Sequence number
+    pub fn get(&self) -> u32 {
+        self.0
+    }
+}
+
+/// What happened to a packet queued with
+/// [`PtxMode::send()`](struct.PtxMode.html#method.send)
+#[derive(Debug)]
+pub enum Outcome {
+    /// The packet has been acknowledged
+    Delivered {
+        /// Token returned by `send()`
+        id: PacketId,
+        /// Retransmissions before the ACK, `ARC_CNT`
+        retransmits: u8,
+        /// Payload of the ACK, if any
+        ack_payload: Option<Payload>,
+    },
+    /// The packet has not been acknowledged after the maximum number
+    /// of retransmissions, and has been removed from the TX FIFO
+    Dropped {
+        /// Token returned by `send()`
+        id: PacketId,
+    },
+}
+
+/// Depth of the TX FIFO, which holds the ACK payloads in PRX mode
+const TX_FIFO_DEPTH: usize = 3;
+
+/// Packets queued with `PtxMode::send()`
+///
+/// Only one packet is written to the chip at a time, so that `TX_DS`,
+/// `MAX_RT`, `ARC_CNT` and the ACK payload belong to it.
+pub(crate) struct PacketQueue {
+    /// Packets waiting for the chip, oldest at `head`
+    waiting: [Option<(PacketId, Payload)>; TX_FIFO_DEPTH],
+    head: usize,
+    len: usize,
+    /// Packet in the TX FIFO of the chip
+    in_flight: Option<PacketId>,
+    next_id: u32,
+}
+
+impl PacketQueue {
+    pub fn new() -> Self {
+        PacketQueue {
+            waiting: [None, None, None],
+            head: 0,
+            len: 0,
+            in_flight: None,
+            next_id: 0,
+        }
+    }
+
+    /// Packets that have not been reported yet
+    pub fn pending(&self) -> usize {
+        self.len + usize::from(self.in_flight.is_some())
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.pending() >= TX_FIFO_DEPTH
+    }
+
+    pub fn push(&mut self, payload: &[u8]) -> PacketId {
+        let id = PacketId(self.next_id);
+        self.next_id = self.next_id.wrapping_add(1);
+        self.waiting[(self.head + self.len) % TX_FIFO_DEPTH] = Some((id, Payload::new(payload)));
+        self.len += 1;
+        id
+    }
+
+    pub fn in_flight(&self) -> Option<PacketId> {
+        self.in_flight
+    }
+
+    /// The packet in the chip has been reported
+    pub fn done(&mut self) {
+        self.in_flight = None;
+    }
+
+    /// The next packet to write to the chip, if it is idle
+    pub fn start(&mut self) -> Option<&Payload> {
+        if self.in_flight.is_some() || self.len == 0 {
+            return None;
+        }
+        let index = self.head;
+        self.head = (self.head + 1) % TX_FIFO_DEPTH;
+        self.len -= 1;
+        let (id, payload) = self.waiting[index].as_ref()?;
+        self.in_flight = Some(*id);
+        Some(payload)
+    }
+}
+
+/// Tracks the ACK payloads in the TX FIFO of a PRX
+///
+/// The chip answers the next new packet on a pipe with the oldest ACK
+/// payload of that pipe, removes it from the TX FIFO, and sets
+/// `TX_DS`.
+pub(crate) struct AckQueue {
+    /// Pipes of the queued ACK payloads, oldest first
+    pipes: [u8; TX_FIFO_DEPTH],
+    len: usize,
+    /// `TX_DS` has been seen since the RX FIFO was last empty
+    sent: bool,
+    /// Delivered ACK payloads per pipe
+    delivered: [u8; PIPES_COUNT],
+}
+
+impl AckQueue {
+    pub fn new() -> Self {
+        AckQueue {
+            pipes: [0; TX_FIFO_DEPTH],
+            len: 0,
+            sent: false,
+            delivered: [0; PIPES_COUNT],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len >= TX_FIFO_DEPTH
+    }
+
+    pub fn push(&mut self, pipe: u8) {
+        self.pipes[self.len] = pipe;
+        self.len += 1;
+    }
+
+    /// Account for `TX_DS` and the FIFO flags read before a packet
+    pub fn update(&mut self, tx_ds: bool, tx_empty: bool, rx_empty: bool) {
+        self.sent = (self.sent || tx_ds) && !rx_empty;
+        if tx_empty {
+            for pipe in self.pipes[0..self.len].iter() {
+                let delivered = &mut self.delivered[usize::from(*pipe)];
+                *delivered = delivered.saturating_add(1);
+            }
+            self.len = 0;
+        }
+    }
+
+    /// A packet has been read from `pipe`
+    pub fn received(&mut self, pipe: u8) {
+        if !self.sent {
+            return;
+        }
+        if let Some(index) = self.pipes[0..self.len].iter().position(|p| *p == pipe) {
+            self.pipes.copy_within(index + 1..self.len, index);
+            self.len -= 1;
+            let delivered = &mut self.delivered[usize::from(pipe)];
+            *delivered = delivered.saturating_add(1);
+        }
+    }
+
+    pub fn take_delivered(&mut self) -> [u8; PIPES_COUNT] {
+        core::mem::replace(&mut self.delivered, [0; PIPES_COUNT])
+    }
 }
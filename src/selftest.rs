@@ -0,0 +1,172 @@
+use crate::command::Nop;
+use crate::config::Configuration;
+use crate::device::{Device, UsingDevice};
+use crate::registers::{FifoStatus, RfCh, RxAddrP0, SetupAw, TxAddr};
+use crate::standby::StandbyMode;
+use crate::{MAX_ADDR_BYTES, PIPES_COUNT};
+
+/// Byte patterns written to the address registers
+const PATTERNS: [u8; 4] = [0x55, 0xAA, 0x00, 0xFF];
+/// Patterns for `RF_CH`, which are legal channels and still toggle
+/// every bit
+const CHANNEL_PATTERNS: [u8; 4] = [0x55, 0x2A, 0x00, 0x7D];
+
+/// Verdict of a [`SelfTestReport`](struct.SelfTestReport.html)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diagnosis {
+    /// All test patterns read back correctly
+    Ok,
+    /// Every byte read as `0xFF`: MISO is floating or pulled up, the
+    /// chip is missing or unpowered
+    MisoStuckHigh,
+    /// Every byte read as `0x00`: MISO is shorted to ground, or the
+    /// chip never drives it
+    MisoStuckLow,
+    /// Some bits read back wrong: bad wiring, noise, or SPI clock too
+    /// fast
+    BitErrors,
+    /// Read-back works, but reserved bits are set, so this is not an
+    /// nRF24L01(+)
+    WrongChip,
+}
+
+/// Result of [`StandbyMode::self_test()`](struct.StandbyMode.html#method.self_test)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfTestReport {
+    /// The verdict
+    pub diagnosis: Diagnosis,
+    /// Number of bits that read back differently than written
+    pub bit_errors: u32,
+    /// Bit positions that read back wrong at least once
+    pub error_mask: u8,
+    /// Do the registers that `NRF24L01::new` leaves alone hold their
+    /// reset values? Only meaningful right after power-on.
+    pub reset_defaults: bool,
+}
+
+impl SelfTestReport {
+    /// Is the chip working?
+    pub fn is_ok(&self) -> bool {
+        self.diagnosis == Diagnosis::Ok
+    }
+}
+
+/// Accumulates what was read during the test
+struct Tally {
+    bit_errors: u32,
+    error_mask: u8,
+    all_ones: bool,
+    all_zeros: bool,
+}
+
+impl Tally {
+    fn check(&mut self, written: u8, read: u8, mask: u8) {
+        self.all_ones &= read == 0xFF;
+        self.all_zeros &= read == 0x00;
+        let wrong = (written ^ read) & mask;
+        self.bit_errors += wrong.count_ones();
+        self.error_mask |= wrong;
+    }
+}
+
+impl<D: Device> StandbyMode<D> {
+    /// Check the SPI connection to the chip
+    ///
+    /// Writes test patterns to `TX_ADDR`, `RX_ADDR_P0` and `RF_CH`,
+    /// reads them back, and checks reserved bits. The original register
+    /// values are restored afterwards, even when the test fails. An
+    /// error during the test is returned rather than one during the
+    /// restore.
+    pub fn self_test(&mut self) -> Result<SelfTestReport, D::Error> {
+        let (_, tx_addr) = self.device().read_register::<TxAddr>()?;
+        let (_, rx_addr_p0) = self.device().read_register::<RxAddrP0>()?;
+        let (_, rf_ch) = self.device().read_register::<RfCh>()?;
+        let reset_defaults = self.has_reset_defaults(&tx_addr, &rx_addr_p0, &rf_ch)?;
+
+        let result = self.run_patterns();
+
+        // Restore, even if the patterns failed
+        let restored = self
+            .device()
+            .write_register(tx_addr)
+            .and_then(|_| self.device().write_register(rx_addr_p0))
+            .and_then(|_| self.device().write_register(rf_ch));
+
+        let mut tally = result?;
+        restored?;
+        let (status, ()) = self.device().send_command(&Nop)?;
+        let (_, setup_aw) = self.device().read_register::<SetupAw>()?;
+        let (_, fifo_status) = self.device().read_register::<FifoStatus>()?;
+        for &value in &[status.0, setup_aw.0, fifo_status.0] {
+            tally.check(value, value, 0);
+        }
+        let reserved_set = status.0 & 0x80 != 0 || setup_aw.0 & 0xFC != 0 || fifo_status.0 & 0x8C != 0;
+
+        let diagnosis = if tally.all_ones {
+            Diagnosis::MisoStuckHigh
+        } else if tally.all_zeros {
+            Diagnosis::MisoStuckLow
+        } else if tally.bit_errors > 0 {
+            Diagnosis::BitErrors
+        } else if reserved_set {
+            Diagnosis::WrongChip
+        } else {
+            Diagnosis::Ok
+        };
+        Ok(SelfTestReport {
+            diagnosis,
+            bit_errors: tally.bit_errors,
+            error_mask: tally.error_mask,
+            reset_defaults,
+        })
+    }
+
+    fn run_patterns(&mut self) -> Result<Tally, D::Error> {
+        let mut tally = Tally {
+            bit_errors: 0,
+            error_mask: 0,
+            all_ones: true,
+            all_zeros: true,
+        };
+        for (&pattern, &channel) in PATTERNS.iter().zip(CHANNEL_PATTERNS.iter()) {
+            // Different bytes for each register, to catch crosstalk
+            let mut tx_pattern = [pattern; MAX_ADDR_BYTES];
+            let mut rx_pattern = [!pattern; MAX_ADDR_BYTES];
+            for i in (1..MAX_ADDR_BYTES).step_by(2) {
+                tx_pattern[i] = !pattern;
+                rx_pattern[i] = pattern;
+            }
+            self.device().write_register(TxAddr::new(&tx_pattern))?;
+            self.device().write_register(RxAddrP0::new(&rx_pattern))?;
+            let mut rf_ch = RfCh(0);
+            rf_ch.set_rf_ch(channel);
+            self.device().write_register(rf_ch)?;
+
+            let (_, tx_addr) = self.device().read_register::<TxAddr>()?;
+            for (written, read) in tx_pattern.iter().zip(tx_addr.as_bytes()) {
+                tally.check(*written, *read, 0xFF);
+            }
+            let (_, rx_addr_p0) = self.device().read_register::<RxAddrP0>()?;
+            for (written, read) in rx_pattern.iter().zip(rx_addr_p0.as_bytes()) {
+                tally.check(*written, *read, 0xFF);
+            }
+            let (_, rf_ch) = self.device().read_register::<RfCh>()?;
+            // Bit 7 is reserved and reads 0
+            tally.check(channel, rf_ch.0, 0x7F);
+            if rf_ch.0 & 0x80 != 0 {
+                tally.bit_errors += 1;
+                tally.error_mask |= 0x80;
+            }
+        }
+        Ok(tally)
+    }
+
+    fn has_reset_defaults(&mut self, tx_addr: &TxAddr, rx_addr_p0: &RxAddrP0, rf_ch: &RfCh) -> Result<bool, D::Error> {
+        let (_, setup_aw) = self.device().read_register::<SetupAw>()?;
+        Ok(tx_addr.as_bytes() == [0xE7; MAX_ADDR_BYTES]
+            && rx_addr_p0.as_bytes() == [0xE7; MAX_ADDR_BYTES]
+            && rf_ch.0 == 0x02
+            && setup_aw.0 == 0x03
+            && self.get_auto_ack()? == [true; PIPES_COUNT])
+    }
+}
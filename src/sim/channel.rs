@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+
+use crate::config::DataRate;
+
+/// A frame or an ACK on the air, as seen by a [`ChannelModel`](trait.ChannelModel.html)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transmission {
+    /// [`SimRadio::id()`](struct.SimRadio.html#method.id) of the sender
+    pub sender: usize,
+    /// Start time in µs
+    pub start: u64,
+    /// End time in µs, after `airtime_us()` of the frame
+    pub end: u64,
+    /// `RF_CH` of the sender
+    pub channel: u8,
+    /// Air data rate of the sender
+    pub data_rate: DataRate,
+    /// Output power of the sender in dBm
+    pub power_dbm: i8,
+    /// This is an ACK sent back by a receiver
+    pub is_ack: bool,
+}
+
+impl Transmission {
+    /// Do both transmissions use the air at the same time?
+    pub fn overlaps(&self, other: &Transmission) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+}
+
+/// Decides which transmissions are received
+///
+/// The models added to an [`Ether`](struct.Ether.html) are asked for
+/// every receiver in turn, and all of them must agree.
+pub trait ChannelModel {
+    /// Can `receiver` decode `transmission`? `others` are all other
+    /// transmissions overlapping with it in time, on any channel.
+    fn receives(&mut self, transmission: &Transmission, receiver: usize, others: &[Transmission]) -> bool;
+}
+
+/// Seeded xorshift64* generator, for reproducible losses
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // The state must not be zero
+        Rng(seed ^ 0x9E37_79B9_7F4A_7C15)
+    }
+
+    /// Uniform in `[0, 1)`
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        let x = self.0.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+}
+
+/// Independent random loss with a fixed probability
+#[derive(Debug, Clone)]
+pub struct RandomLoss {
+    probability: f64,
+    rng: Rng,
+}
+
+impl RandomLoss {
+    /// Lose each transmission with `probability`
+    pub fn new(probability: f64, seed: u64) -> Self {
+        RandomLoss {
+            probability,
+            rng: Rng::new(seed),
+        }
+    }
+}
+
+impl ChannelModel for RandomLoss {
+    fn receives(&mut self, _: &Transmission, _: usize, _: &[Transmission]) -> bool {
+        !self.rng.chance(self.probability)
+    }
+}
+
+/// Burst loss with the two-state Gilbert-Elliott model
+///
+/// Every link from a sender to a receiver is either in the good or in
+/// the bad state, and changes state before each transmission.
+#[derive(Debug, Clone)]
+pub struct GilbertElliott {
+    good_to_bad: f64,
+    bad_to_good: f64,
+    loss_good: f64,
+    loss_bad: f64,
+    rng: Rng,
+    /// Links currently in the bad state
+    bad: BTreeMap<(usize, usize), bool>,
+}
+
+impl GilbertElliott {
+    /// `good_to_bad` and `bad_to_good` are the transition
+    /// probabilities, `loss_good` and `loss_bad` the loss probability
+    /// within each state.
+    pub fn new(good_to_bad: f64, bad_to_good: f64, loss_good: f64, loss_bad: f64, seed: u64) -> Self {
+        GilbertElliott {
+            good_to_bad,
+            bad_to_good,
+            loss_good,
+            loss_bad,
+            rng: Rng::new(seed),
+            bad: BTreeMap::new(),
+        }
+    }
+}
+
+impl ChannelModel for GilbertElliott {
+    fn receives(&mut self, transmission: &Transmission, receiver: usize, _: &[Transmission]) -> bool {
+        let bad = self.bad.entry((transmission.sender, receiver)).or_insert(false);
+        let flip = if *bad { self.bad_to_good } else { self.good_to_bad };
+        if self.rng.chance(flip) {
+            *bad = !*bad;
+        }
+        let loss = if *bad { self.loss_bad } else { self.loss_good };
+        !self.rng.chance(loss)
+    }
+}
+
+/// Lose transmissions that overlap with another transmission on the
+/// same channel
+///
+/// Both colliding transmissions are lost, there is no capture effect.
+#[derive(Debug, Clone, Default)]
+pub struct Collisions;
+
+impl ChannelModel for Collisions {
+    fn receives(&mut self, transmission: &Transmission, receiver: usize, others: &[Transmission]) -> bool {
+        !others.iter().any(|other| {
+            // A receiver doesn't hear anything while it is transmitting itself
+            (other.channel == transmission.channel || other.sender == receiver) && other.overlaps(transmission)
+        })
+    }
+}
+
+/// Path loss by distance between radios placed on a plane
+///
+/// Uses the log-distance model `40 dB + 10 * exponent * log10(d / 1 m)`
+/// for 2.4 GHz, and the receiver sensitivity of the nRF24L01+ for
+/// each data rate. Radios that have not been placed are always in
+/// range.
+#[derive(Debug, Clone)]
+pub struct Attenuation {
+    exponent: f64,
+    positions: BTreeMap<usize, (f64, f64)>,
+}
+
+impl Attenuation {
+    /// `exponent` is 2.0 in free space, and about 3.0 indoors
+    pub fn new(exponent: f64) -> Self {
+        Attenuation {
+            exponent,
+            positions: BTreeMap::new(),
+        }
+    }
+
+    /// Place radio `id` at `(x, y)`, in meters
+    pub fn place(&mut self, id: usize, x: f64, y: f64) -> &mut Self {
+        self.positions.insert(id, (x, y));
+        self
+    }
+
+    /// Path loss in dB between two radios
+    pub fn path_loss_db(&self, a: usize, b: usize) -> f64 {
+        match (self.positions.get(&a), self.positions.get(&b)) {
+            (Some((ax, ay)), Some((bx, by))) => {
+                let distance = ((ax - bx).powi(2) + (ay - by).powi(2)).sqrt().max(1.0);
+                40.0 + 10.0 * self.exponent * distance.log10()
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+/// Receiver sensitivity in dBm
+fn sensitivity_dbm(data_rate: DataRate) -> f64 {
+    match data_rate {
+        DataRate::R250Kbps => -94.0,
+        DataRate::R1Mbps => -85.0,
+        DataRate::R2Mbps => -82.0,
+    }
+}
+
+impl ChannelModel for Attenuation {
+    fn receives(&mut self, transmission: &Transmission, receiver: usize, _: &[Transmission]) -> bool {
+        let received_dbm = f64::from(transmission.power_dbm) - self.path_loss_db(transmission.sender, receiver);
+        received_dbm >= sensitivity_dbm(transmission.data_rate)
+    }
+}
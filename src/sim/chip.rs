@@ -0,0 +1,718 @@
+use std::collections::VecDeque;
+use std::vec::Vec;
+
+use crate::config::DataRate;
+use crate::registers::RfSetup;
+use crate::sim::{Ack, Frame};
+use crate::variant::ChipVariant;
+use crate::{MAX_ADDR_BYTES, PIPES_COUNT};
+
+/// Depth of both the TX and the RX FIFO
+pub const FIFO_DEPTH: usize = 3;
+/// Maximum payload length
+pub const MAX_PAYLOAD_LEN: usize = 32;
+/// Time to transfer one byte over SPI at 8 MHz, rounded up
+pub const SPI_BYTE_US: u64 = 1;
+/// **Tpd2stby**, start-up time from power down to standby
+pub const POWER_UP_US: u64 = 1500;
+/// **Tstby2a**, RX/TX settling time
+pub const SETTLING_US: u64 = 130;
+
+const CONFIG: u8 = 0x00;
+const EN_AA: u8 = 0x01;
+const EN_RXADDR: u8 = 0x02;
+const SETUP_AW: u8 = 0x03;
+const SETUP_RETR: u8 = 0x04;
+const RF_CH: u8 = 0x05;
+const RF_SETUP: u8 = 0x06;
+const STATUS: u8 = 0x07;
+const OBSERVE_TX: u8 = 0x08;
+const RPD: u8 = 0x09;
+const RX_ADDR_P0: u8 = 0x0A;
+const RX_ADDR_P1: u8 = 0x0B;
+const RX_ADDR_P2: u8 = 0x0C;
+const TX_ADDR: u8 = 0x10;
+const RX_PW_P0: u8 = 0x11;
+const FIFO_STATUS: u8 = 0x17;
+const DYNPD: u8 = 0x1C;
+const FEATURE: u8 = 0x1D;
+const REGISTERS: usize = 0x1E;
+
+/// Bank 1 registers of Beken parts, `0x0E` is longer
+const BANK1_REGISTERS: usize = 0x0F;
+const BANK1_CHIP_ID: u8 = 0x08;
+const BANK1_RAMP: u8 = 0x0E;
+/// Beken chip ID, reset value of bank 1 register `0x08`
+const BEKEN_CHIP_ID: [u8; 4] = [0x63, 0x00, 0x00, 0x00];
+
+const RBANK: u8 = 1 << 7;
+const RX_DR: u8 = 1 << 6;
+const TX_DS: u8 = 1 << 5;
+const MAX_RT: u8 = 1 << 4;
+
+/// Reset value and writable bits of each single-byte register
+const REGISTER_MAP: [(u8, u8); REGISTERS] = [
+    (0x08, 0x7F), // CONFIG
+    (0x3F, 0x3F), // EN_AA
+    (0x03, 0x3F), // EN_RXADDR
+    (0x03, 0x03), // SETUP_AW
+    (0x03, 0xFF), // SETUP_RETR
+    (0x02, 0x7F), // RF_CH
+    (0x0E, 0xBF), // RF_SETUP
+    (0x0E, 0x00), // STATUS, write 1 to clear
+    (0x00, 0x00), // OBSERVE_TX
+    (0x00, 0x00), // RPD
+    (0x00, 0x00), // RX_ADDR_P0, see `addrs`
+    (0x00, 0x00), // RX_ADDR_P1, see `addrs`
+    (0xC3, 0xFF), // RX_ADDR_P2
+    (0xC4, 0xFF), // RX_ADDR_P3
+    (0xC5, 0xFF), // RX_ADDR_P4
+    (0xC6, 0xFF), // RX_ADDR_P5
+    (0x00, 0x00), // TX_ADDR, see `addrs`
+    (0x00, 0x3F), // RX_PW_P0
+    (0x00, 0x3F), // RX_PW_P1
+    (0x00, 0x3F), // RX_PW_P2
+    (0x00, 0x3F), // RX_PW_P3
+    (0x00, 0x3F), // RX_PW_P4
+    (0x00, 0x3F), // RX_PW_P5
+    (0x11, 0x00), // FIFO_STATUS
+    (0x00, 0x00), // reserved
+    (0x00, 0x00), // reserved
+    (0x00, 0x00), // reserved
+    (0x00, 0x00), // reserved
+    (0x00, 0x3F), // DYNPD
+    (0x00, 0x07), // FEATURE
+];
+
+struct TxEntry {
+    payload: Vec<u8>,
+    no_ack: bool,
+    /// Pipe of an ACK payload queued with `W_ACK_PAYLOAD`
+    ack_pipe: Option<u8>,
+    /// Not transmitted yet, so it gets a new PID
+    fresh: bool,
+}
+
+/// Last acknowledged packet of a pipe, for duplicate detection
+struct LastRx {
+    pid: u8,
+    payload: Vec<u8>,
+    /// The ACK payload that was sent with it
+    ack_payload: Option<Vec<u8>>,
+}
+
+struct RxEntry {
+    pipe: u8,
+    payload: Vec<u8>,
+}
+
+enum State {
+    PowerDown,
+    /// **Standby-I** or **Standby-II**
+    Standby,
+    TxSettling(u64),
+    Transmitting(u64, Frame, bool),
+    /// Transmission has ended, waiting for `transmission_done()`
+    Transmitted(Frame, bool),
+    AwaitAck(u64, Option<Ack>),
+    RxSettling(u64),
+    Rx,
+}
+
+/// Register-accurate model of one nRF24L01+, or of another variant
+pub(crate) struct Chip {
+    variant: ChipVariant,
+    /// `FEATURE` and `DYNPD` unlocked by `ACTIVATE`
+    activated: bool,
+    /// Register bank 1 of Beken parts is selected
+    bank1: bool,
+    bank1_regs: [Vec<u8>; BANK1_REGISTERS],
+    now: u64,
+    regs: [u8; REGISTERS],
+    /// `RX_ADDR_P0`, `RX_ADDR_P1`, `TX_ADDR`
+    addrs: [[u8; MAX_ADDR_BYTES]; 3],
+    ce: bool,
+    state: State,
+    powered_at: u64,
+    tx_fifo: VecDeque<TxEntry>,
+    rx_fifo: VecDeque<RxEntry>,
+    reuse: bool,
+    pid: u8,
+    retries: u8,
+    last_rx: [Option<LastRx>; PIPES_COUNT],
+}
+
+impl Chip {
+    pub fn new(variant: ChipVariant) -> Self {
+        let mut regs = [0; REGISTERS];
+        for (reg, (reset, _)) in regs.iter_mut().zip(REGISTER_MAP.iter()) {
+            *reg = *reset;
+        }
+        if !variant.has_250kbps() {
+            // LNA_HCURR
+            regs[RF_SETUP as usize] = 0x0F;
+        }
+        let mut bank1_regs: [Vec<u8>; BANK1_REGISTERS] = Default::default();
+        for (addr, reg) in bank1_regs.iter_mut().enumerate() {
+            *reg = match addr as u8 {
+                BANK1_CHIP_ID => BEKEN_CHIP_ID.to_vec(),
+                BANK1_RAMP => vec![0; 11],
+                _ => vec![0; 4],
+            };
+        }
+        Chip {
+            variant,
+            activated: false,
+            bank1: false,
+            bank1_regs,
+            now: 0,
+            regs,
+            addrs: [[0xE7; MAX_ADDR_BYTES], [0xC2; MAX_ADDR_BYTES], [0xE7; MAX_ADDR_BYTES]],
+            ce: false,
+            state: State::PowerDown,
+            powered_at: 0,
+            tx_fifo: VecDeque::new(),
+            rx_fifo: VecDeque::new(),
+            reuse: false,
+            pid: 0,
+            retries: 0,
+            last_rx: Default::default(),
+        }
+    }
+
+    pub fn ce(&self) -> bool {
+        self.ce
+    }
+
+    /// Let time pass up to the next event, see `next_event()`
+    pub fn advance_to(&mut self, now: u64) {
+        self.now = now;
+    }
+
+    /// Drive the CE pin
+    pub fn set_ce(&mut self, now: u64, ce: bool) {
+        self.now = now;
+        self.ce = ce;
+        self.reconcile();
+    }
+
+    /// The frame currently being transmitted, and the end time of its
+    /// transmission
+    pub fn in_flight(&self) -> Option<(u64, Frame)> {
+        match &self.state {
+            State::Transmitting(end, frame, _) => Some((*end, frame.clone())),
+            _ => None,
+        }
+    }
+
+    /// Channel of the constant carrier emitted with `CONT_WAVE`
+    pub fn carrier(&self) -> Option<u8> {
+        let config = self.regs[CONFIG as usize];
+        let cont_wave = self.regs[RF_SETUP as usize] & (1 << 7) != 0;
+        match self.state {
+            State::Standby if config & 0b11 == 0b10 && self.ce && cont_wave => Some(self.regs[RF_CH as usize]),
+            _ => None,
+        }
+    }
+
+    /// Output power in dBm
+    pub fn power_dbm(&self) -> i8 {
+        let level = self.variant.power_level(&RfSetup(self.regs[RF_SETUP as usize]));
+        self.variant.power_levels()[usize::from(level)]
+    }
+
+    /// Is the IRQ pin asserted?
+    pub fn irq(&self) -> bool {
+        let config = self.regs[CONFIG as usize];
+        // Mask bits are in the same positions as the flags
+        self.regs[STATUS as usize] & !config & (RX_DR | TX_DS | MAX_RT) != 0
+    }
+
+    pub fn tx_fifo_len(&self) -> usize {
+        self.tx_fifo.len()
+    }
+
+    pub fn rx_fifo_len(&self) -> usize {
+        self.rx_fifo.len()
+    }
+
+    /// Read back a register without side effects
+    pub fn register(&self, addr: u8) -> Vec<u8> {
+        match addr {
+            RX_ADDR_P0 | RX_ADDR_P1 | TX_ADDR => self.addrs[Self::addr_index(addr)].to_vec(),
+            STATUS => vec![self.status()],
+            FIFO_STATUS => vec![self.fifo_status()],
+            _ if (addr as usize) < REGISTERS => vec![self.regs[addr as usize]],
+            _ => vec![0],
+        }
+    }
+
+    /// Read back a bank 1 register of Beken parts
+    pub fn bank1_register(&self, addr: u8) -> Vec<u8> {
+        self.bank1_regs.get(usize::from(addr)).cloned().unwrap_or_default()
+    }
+
+    /// Run one SPI transaction with `mosi` and return the MISO bytes
+    ///
+    /// Effects of the command are applied on the rising edge of CSN,
+    /// that is at `now`.
+    pub fn transaction(&mut self, now: u64, mosi: &[u8]) -> Vec<u8> {
+        self.now = now;
+        let mut miso = vec![0; mosi.len()];
+        if mosi.is_empty() {
+            return miso;
+        }
+        miso[0] = self.status();
+        let data = &mosi[1..];
+        let out = &mut miso[1..];
+
+        match mosi[0] {
+            0b0000_0000..=0b0001_1111 if self.bank1 => {
+                let value = self.bank1_register(mosi[0] & 0x1F);
+                for (o, v) in out.iter_mut().zip(value.iter()) {
+                    *o = *v;
+                }
+            }
+            0b0010_0000..=0b0011_1111 if self.bank1 => {
+                let addr = mosi[0] & 0x1F;
+                if let (Some(reg), true) = (self.bank1_regs.get_mut(usize::from(addr)), addr != BANK1_CHIP_ID) {
+                    for (r, d) in reg.iter_mut().zip(data.iter()) {
+                        *r = *d;
+                    }
+                }
+            }
+            0b0000_0000..=0b0001_1111 => {
+                let value = self.register(mosi[0] & 0x1F);
+                for (o, v) in out.iter_mut().zip(value.iter()) {
+                    *o = *v;
+                }
+            }
+            0b0010_0000..=0b0011_1111 => self.write_register(mosi[0] & 0x1F, data),
+            // R_RX_PAYLOAD
+            0b0110_0001 => {
+                if let Some(entry) = self.rx_fifo.pop_front() {
+                    for (o, v) in out.iter_mut().zip(entry.payload.iter()) {
+                        *o = *v;
+                    }
+                }
+            }
+            // R_RX_PL_WID
+            0b0110_0000 => {
+                if let (Some(o), true) = (out.first_mut(), self.en_dpl()) {
+                    *o = self.rx_fifo.front().map_or(0, |entry| entry.payload.len() as u8);
+                }
+            }
+            // W_TX_PAYLOAD
+            0b1010_0000 => self.push_tx(data, false, None),
+            // W_TX_PAYLOAD_NOACK, enabled by EN_DYN_ACK
+            0b1011_0000 if self.regs[FEATURE as usize] & 0b001 != 0 => self.push_tx(data, true, None),
+            // W_ACK_PAYLOAD, enabled by EN_ACK_PAY
+            0b1010_1000..=0b1010_1101 if self.regs[FEATURE as usize] & 0b010 != 0 => {
+                self.push_tx(data, false, Some(mosi[0] & 0b111))
+            }
+            // FLUSH_TX
+            0b1110_0001 => {
+                self.tx_fifo.clear();
+                self.reuse = false;
+            }
+            // FLUSH_RX
+            0b1110_0010 => self.rx_fifo.clear(),
+            // REUSE_TX_PL
+            0b1110_0011 => self.reuse = true,
+            // ACTIVATE, toggles on the nRF24L01
+            0b0101_0000 if data.first() == Some(&0x73) && self.variant.needs_activate() => {
+                self.activated = !self.activated;
+                if !self.activated {
+                    self.regs[FEATURE as usize] = 0;
+                    self.regs[DYNPD as usize] = 0;
+                }
+            }
+            // ACTIVATE, toggles the register bank of Beken parts
+            0b0101_0000 if data.first() == Some(&0x53) && self.variant.has_bank1() => self.bank1 = !self.bank1,
+            // NOP, and everything undefined
+            _ => {}
+        }
+
+        self.reconcile();
+        miso
+    }
+
+    /// Time of the next internal state change
+    pub fn next_event(&self) -> Option<u64> {
+        match self.state {
+            State::TxSettling(t)
+            | State::Transmitting(t, _, _)
+            | State::AwaitAck(t, _)
+            | State::RxSettling(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// Perform the state change scheduled by `next_event()`
+    ///
+    /// Returns a frame when its transmission has ended. The caller
+    /// must then report the outcome with `transmission_done()`.
+    pub fn fire(&mut self, now: u64) -> Option<Frame> {
+        self.now = now;
+        match core::mem::replace(&mut self.state, State::Standby) {
+            State::TxSettling(_) => {
+                self.start_transmission();
+                None
+            }
+            State::Transmitting(_, frame, expect_ack) => {
+                self.state = State::Transmitted(frame.clone(), expect_ack);
+                Some(frame)
+            }
+            State::AwaitAck(_, Some(ack)) => {
+                self.tx_success(Some(ack));
+                None
+            }
+            State::AwaitAck(_, None) => {
+                if self.retries < self.regs[SETUP_RETR as usize] & 0x0F {
+                    self.retries += 1;
+                    self.start_transmission();
+                } else {
+                    self.regs[STATUS as usize] |= MAX_RT;
+                    let observe = self.regs[OBSERVE_TX as usize];
+                    let plos_cnt = (observe >> 4).saturating_add(1).min(15);
+                    self.regs[OBSERVE_TX as usize] = (plos_cnt << 4) | (observe & 0x0F);
+                    self.state = State::Standby;
+                }
+                None
+            }
+            State::RxSettling(_) => {
+                self.state = State::Rx;
+                None
+            }
+            state => {
+                self.state = state;
+                None
+            }
+        }
+    }
+
+    /// Report the ACK received for the frame returned by `fire()`
+    pub fn transmission_done(&mut self, now: u64, ack: Option<Ack>) {
+        self.now = now;
+        let (frame, expect_ack) = match core::mem::replace(&mut self.state, State::Standby) {
+            State::Transmitted(frame, expect_ack) => (frame, expect_ack),
+            state => {
+                self.state = state;
+                return;
+            }
+        };
+        if !expect_ack {
+            self.tx_success(None);
+            return;
+        }
+
+        // The ACK is received on pipe 0
+        let aw = self.address_width();
+        let ack = ack.filter(|_| self.addrs[0][..aw] == self.addrs[2][..aw]);
+        let ard = 250 * (u64::from(self.regs[SETUP_RETR as usize] >> 4) + 1);
+        // The ACK arrives after the receiver's TX settling, and is lost
+        // when that takes longer than the Auto Retransmit Delay.
+        self.state = match ack {
+            Some(ack) if SETTLING_US + ack.airtime_us(&frame) <= ard => {
+                State::AwaitAck(now + SETTLING_US + ack.airtime_us(&frame), Some(ack))
+            }
+            _ => State::AwaitAck(now + ard, None),
+        };
+    }
+
+    /// A frame on the air reaches this chip
+    ///
+    /// Returns the ACK if the chip has accepted the frame and
+    /// acknowledges it.
+    pub fn receive(&mut self, now: u64, frame: &Frame) -> Option<Ack> {
+        self.now = now;
+        match self.state {
+            State::Rx => {}
+            _ => return None,
+        }
+        if frame.channel != self.regs[RF_CH as usize] {
+            return None;
+        }
+        // Received Power Detector
+        self.regs[RPD as usize] = 1;
+        if frame.data_rate != self.data_rate() || frame.crc_bytes != self.crc_bytes() {
+            return None;
+        }
+
+        let pipe = (0..PIPES_COUNT).find(|&pipe| {
+            self.regs[EN_RXADDR as usize] & (1 << pipe) != 0 && self.pipe_address(pipe) == frame.address
+        })?;
+        if frame.dynamic != self.pipe_dynamic(pipe) {
+            return None;
+        }
+        if !frame.dynamic && usize::from(self.regs[RX_PW_P0 as usize + pipe]) != frame.payload.len() {
+            return None;
+        }
+
+        let auto_ack = self.regs[EN_AA as usize] & (1 << pipe) != 0 && !frame.no_ack;
+        if auto_ack {
+            if let Some(last) = &self.last_rx[pipe] {
+                if last.pid == frame.pid && last.payload == frame.payload {
+                    // Retransmission of a packet whose ACK was lost
+                    return Some(Ack {
+                        payload: last.ack_payload.clone(),
+                    });
+                }
+            }
+        }
+
+        if self.rx_fifo.len() >= FIFO_DEPTH {
+            return None;
+        }
+        self.rx_fifo.push_back(RxEntry {
+            pipe: pipe as u8,
+            payload: frame.payload.clone(),
+        });
+        self.regs[STATUS as usize] |= RX_DR;
+        if !auto_ack {
+            return None;
+        }
+
+        let ack_payload = if self.regs[FEATURE as usize] & 0b110 == 0b110 {
+            let index = self
+                .tx_fifo
+                .iter()
+                .position(|entry| entry.ack_pipe == Some(pipe as u8));
+            index.and_then(|index| self.tx_fifo.remove(index)).map(|entry| {
+                self.regs[STATUS as usize] |= TX_DS;
+                entry.payload
+            })
+        } else {
+            None
+        };
+        self.last_rx[pipe] = Some(LastRx {
+            pid: frame.pid,
+            payload: frame.payload.clone(),
+            ack_payload: ack_payload.clone(),
+        });
+        Some(Ack { payload: ack_payload })
+    }
+
+    fn write_register(&mut self, addr: u8, data: &[u8]) {
+        match addr {
+            RX_ADDR_P0 | RX_ADDR_P1 | TX_ADDR => {
+                let register = &mut self.addrs[Self::addr_index(addr)];
+                for (r, d) in register.iter_mut().zip(data.iter()) {
+                    *r = *d;
+                }
+            }
+            STATUS => {
+                if let Some(value) = data.first() {
+                    self.regs[STATUS as usize] &= !(value & (RX_DR | TX_DS | MAX_RT));
+                }
+            }
+            _ if (addr as usize) < REGISTERS => {
+                if let Some(value) = data.first() {
+                    let writable = self.writable(addr);
+                    let reg = &mut self.regs[addr as usize];
+                    *reg = (*reg & !writable) | (value & writable);
+                    if addr == RF_CH {
+                        // Writing RF_CH resets PLOS_CNT
+                        self.regs[OBSERVE_TX as usize] &= 0x0F;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Writable bits of a single-byte register
+    fn writable(&self, addr: u8) -> u8 {
+        let (_, writable) = REGISTER_MAP[addr as usize];
+        match (self.variant, addr) {
+            // No RF_DR_LOW, RF_DR_HIGH is RF_DR, LNA_HCURR
+            (ChipVariant::Nrf24l01 | ChipVariant::Bk2421, RF_SETUP) => 0x0F,
+            (ChipVariant::Bk2423, RF_SETUP) => 0x2F,
+            (_, FEATURE | DYNPD) if self.variant.needs_activate() && !self.activated => 0,
+            _ => writable,
+        }
+    }
+
+    fn push_tx(&mut self, data: &[u8], no_ack: bool, ack_pipe: Option<u8>) {
+        if self.tx_fifo.len() >= FIFO_DEPTH || data.is_empty() {
+            return;
+        }
+        let len = data.len().min(MAX_PAYLOAD_LEN);
+        self.tx_fifo.push_back(TxEntry {
+            payload: data[..len].to_vec(),
+            no_ack,
+            ack_pipe,
+            fresh: true,
+        });
+        self.reuse = false;
+    }
+
+    /// Follow the state diagram after CE, `CONFIG` or the FIFOs have changed
+    fn reconcile(&mut self) {
+        let config = self.regs[CONFIG as usize];
+        let pwr_up = config & 0b10 != 0;
+        let prim_rx = config & 0b01 != 0;
+
+        if !pwr_up {
+            self.state = State::PowerDown;
+            return;
+        }
+        if let State::PowerDown = self.state {
+            self.powered_at = self.now + POWER_UP_US;
+            self.state = State::Standby;
+        }
+        let settled_at = self.now.max(self.powered_at) + SETTLING_US;
+
+        match self.state {
+            State::RxSettling(_) | State::Rx if !prim_rx || !self.ce => self.state = State::Standby,
+            State::Standby if prim_rx && self.ce => {
+                self.regs[RPD as usize] = 0;
+                self.state = State::RxSettling(settled_at)
+            }
+            State::Standby if !prim_rx && self.ce && self.tx_ready() => {
+                self.state = State::TxSettling(settled_at)
+            }
+            _ => {}
+        }
+    }
+
+    fn tx_ready(&self) -> bool {
+        !self.tx_fifo.is_empty() && self.regs[STATUS as usize] & MAX_RT == 0
+    }
+
+    fn start_transmission(&mut self) {
+        let aw = self.address_width();
+        let dynamic = self.pipe_dynamic(0);
+        let crc_bytes = self.crc_bytes();
+        let data_rate = self.data_rate();
+        let channel = self.regs[RF_CH as usize];
+        let address = self.addrs[2][..aw].to_vec();
+        let en_aa = self.regs[EN_AA as usize] & 1 != 0;
+
+        let entry = match self.tx_fifo.front_mut() {
+            Some(entry) => entry,
+            None => {
+                self.state = State::Standby;
+                return;
+            }
+        };
+        if entry.fresh {
+            entry.fresh = false;
+            self.pid = (self.pid + 1) & 0b11;
+            self.retries = 0;
+        }
+        if self.retries == 0 {
+            // New packet, ARC_CNT restarts
+            self.regs[OBSERVE_TX as usize] &= 0xF0;
+        } else {
+            let observe = self.regs[OBSERVE_TX as usize];
+            self.regs[OBSERVE_TX as usize] = (observe & 0xF0) | self.retries;
+        }
+
+        let frame = Frame {
+            channel,
+            data_rate,
+            address,
+            payload: entry.payload.clone(),
+            pid: self.pid,
+            no_ack: entry.no_ack,
+            dynamic,
+            crc_bytes,
+        };
+        let expect_ack = en_aa && !entry.no_ack;
+        self.state = State::Transmitting(self.now + frame.airtime_us(), frame, expect_ack);
+    }
+
+    fn tx_success(&mut self, ack: Option<Ack>) {
+        self.regs[STATUS as usize] |= TX_DS;
+        if let Some(payload) = ack.and_then(|ack| ack.payload) {
+            if self.rx_fifo.len() < FIFO_DEPTH {
+                self.rx_fifo.push_back(RxEntry { pipe: 0, payload });
+                self.regs[STATUS as usize] |= RX_DR;
+            }
+        }
+        if !self.reuse {
+            self.tx_fifo.pop_front();
+        }
+
+        self.state = State::Standby;
+        self.reconcile();
+    }
+
+    fn status(&self) -> u8 {
+        let rx_p_no = self.rx_fifo.front().map_or(0b111, |entry| entry.pipe);
+        let tx_full = (self.tx_fifo.len() >= FIFO_DEPTH) as u8;
+        let rbank = if self.bank1 { RBANK } else { 0 };
+        rbank | (self.regs[STATUS as usize] & (RX_DR | TX_DS | MAX_RT)) | (rx_p_no << 1) | tx_full
+    }
+
+    fn fifo_status(&self) -> u8 {
+        ((self.reuse as u8) << 6)
+            | (((self.tx_fifo.len() >= FIFO_DEPTH) as u8) << 5)
+            | ((self.tx_fifo.is_empty() as u8) << 4)
+            | (((self.rx_fifo.len() >= FIFO_DEPTH) as u8) << 1)
+            | (self.rx_fifo.is_empty() as u8)
+    }
+
+    fn addr_index(addr: u8) -> usize {
+        match addr {
+            RX_ADDR_P0 => 0,
+            RX_ADDR_P1 => 1,
+            _ => 2,
+        }
+    }
+
+    fn address_width(&self) -> usize {
+        match self.regs[SETUP_AW as usize] & 0b11 {
+            0b01 => 3,
+            0b10 => 4,
+            // 0b00 is illegal, treat like the reset value
+            _ => 5,
+        }
+    }
+
+    /// Pipes 2 to 5 share the upper bytes of pipe 1
+    fn pipe_address(&self, pipe: usize) -> Vec<u8> {
+        let aw = self.address_width();
+        match pipe {
+            0 => self.addrs[0][..aw].to_vec(),
+            _ => {
+                let mut address = self.addrs[1][..aw].to_vec();
+                if pipe > 1 {
+                    address[0] = self.regs[RX_ADDR_P2 as usize + pipe - 2];
+                }
+                address
+            }
+        }
+    }
+
+    fn en_dpl(&self) -> bool {
+        self.regs[FEATURE as usize] & 0b100 != 0
+    }
+
+    fn pipe_dynamic(&self, pipe: usize) -> bool {
+        self.en_dpl() && self.regs[DYNPD as usize] & (1 << pipe) != 0
+    }
+
+    pub fn data_rate(&self) -> DataRate {
+        let rf_setup = self.regs[RF_SETUP as usize];
+        if rf_setup & (1 << 5) != 0 {
+            DataRate::R250Kbps
+        } else if rf_setup & (1 << 3) != 0 {
+            DataRate::R2Mbps
+        } else {
+            DataRate::R1Mbps
+        }
+    }
+
+    /// CRC is forced on while any pipe has auto-ack enabled
+    fn crc_bytes(&self) -> u8 {
+        let config = self.regs[CONFIG as usize];
+        let en_crc = config & 0b1000 != 0 || self.regs[EN_AA as usize] != 0;
+        match (en_crc, config & 0b100 != 0) {
+            (false, _) => 0,
+            (true, false) => 1,
+            (true, true) => 2,
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::boxed::Box;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::vec::Vec;
+
+use crate::sim::channel::{ChannelModel, Transmission};
+use crate::sim::chip::{Chip, SETTLING_US};
+use crate::sim::{Ack, Frame, SimRadio};
+use crate::variant::ChipVariant;
+
+/// How long past transmissions are kept for collision checks
+const HISTORY_US: u64 = 20_000;
+
+/// The medium shared by the chips of one simulation, and its clock
+pub(crate) struct Air {
+    now: u64,
+    chips: Vec<Rc<RefCell<Chip>>>,
+    /// Transmitted frames with the index of their sender
+    transmitted: Vec<(usize, Frame)>,
+    models: Vec<Box<dyn ChannelModel>>,
+    /// Recent transmissions of frames and ACKs
+    history: Vec<Transmission>,
+}
+
+impl Air {
+    pub fn new() -> Self {
+        Air {
+            now: 0,
+            chips: Vec::new(),
+            transmitted: Vec::new(),
+            models: Vec::new(),
+            history: Vec::new(),
+        }
+    }
+
+    pub fn add_model(&mut self, model: Box<dyn ChannelModel>) {
+        self.models.push(model);
+    }
+
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// Attach a chip, returning its index
+    pub fn attach(&mut self, chip: Rc<RefCell<Chip>>) -> usize {
+        chip.borrow_mut().advance_to(self.now);
+        self.chips.push(chip);
+        self.chips.len() - 1
+    }
+
+    /// Run the state changes of all chips up to `time`, in order
+    ///
+    /// Events at the same time are processed in the order the chips
+    /// were attached.
+    pub fn run_until(&mut self, time: u64) {
+        loop {
+            let next = self
+                .chips
+                .iter()
+                .enumerate()
+                .filter_map(|(index, chip)| chip.borrow().next_event().map(|event| (event, index)))
+                .min();
+            let (event, index) = match next {
+                Some((event, index)) if event <= time => (event, index),
+                _ => break,
+            };
+
+            self.now = event;
+            let frame = self.chips[index].borrow_mut().fire(event);
+            if let Some(frame) = frame {
+                let ack = self.transmit(index, &frame);
+                self.chips[index].borrow_mut().transmission_done(event, ack);
+                self.transmitted.push((index, frame));
+            }
+        }
+
+        self.now = self.now.max(time);
+        for chip in &self.chips {
+            chip.borrow_mut().advance_to(self.now);
+        }
+    }
+
+    /// Deliver `frame`, whose transmission has just ended, to all
+    /// other chips that the channel models let receive it
+    ///
+    /// If several receivers acknowledge, the sender gets the first
+    /// ACK that makes it back.
+    fn transmit(&mut self, sender: usize, frame: &Frame) -> Option<Ack> {
+        let now = self.now;
+        self.history.retain(|transmission| transmission.end + HISTORY_US >= now);
+        let transmission = Transmission {
+            sender,
+            start: now - frame.airtime_us(),
+            end: now,
+            channel: frame.channel,
+            data_rate: frame.data_rate,
+            power_dbm: self.chips[sender].borrow().power_dbm(),
+            is_ack: false,
+        };
+        let others = self.overlapping(&transmission);
+
+        let mut ack = None;
+        for index in 0..self.chips.len() {
+            if index == sender || !self.receives(&transmission, index, &others) {
+                continue;
+            }
+            let chip_ack = self.chips[index].borrow_mut().receive(now, frame);
+            if let (None, Some(chip_ack)) = (&ack, chip_ack) {
+                let start = now + SETTLING_US;
+                let ack_transmission = Transmission {
+                    sender: index,
+                    start,
+                    end: start + chip_ack.airtime_us(frame),
+                    is_ack: true,
+                    power_dbm: self.chips[index].borrow().power_dbm(),
+                    ..transmission.clone()
+                };
+                let ack_others = self.overlapping(&ack_transmission);
+                if self.receives(&ack_transmission, sender, &ack_others) {
+                    ack = Some(chip_ack);
+                }
+                self.history.push(ack_transmission);
+            }
+        }
+        self.history.push(transmission);
+        ack
+    }
+
+    /// Ask all channel models
+    fn receives(&mut self, transmission: &Transmission, receiver: usize, others: &[Transmission]) -> bool {
+        self.models
+            .iter_mut()
+            .all(|model| model.receives(transmission, receiver, others))
+    }
+
+    /// Past and ongoing transmissions overlapping with `transmission`
+    fn overlapping(&self, transmission: &Transmission) -> Vec<Transmission> {
+        let in_flight = self.chips.iter().enumerate().filter_map(|(index, chip)| {
+            let chip = chip.borrow();
+            chip.in_flight().map(|(end, frame)| Transmission {
+                sender: index,
+                start: end - frame.airtime_us(),
+                end,
+                channel: frame.channel,
+                data_rate: frame.data_rate,
+                power_dbm: chip.power_dbm(),
+                is_ack: false,
+            })
+        });
+        self.history
+            .iter()
+            .cloned()
+            .chain(in_flight)
+            .filter(|other| other != transmission && other.overlaps(transmission))
+            .collect()
+    }
+
+    /// Take the frames transmitted by chip `index`
+    pub fn take_transmitted(&mut self, index: usize) -> Vec<Frame> {
+        let (taken, kept) = core::mem::take(&mut self.transmitted)
+            .into_iter()
+            .partition(|(sender, _)| *sender == index);
+        self.transmitted = kept;
+        taken.into_iter().map(|(_, frame)| frame).collect()
+    }
+}
+
+/// Virtual air connecting several simulated radios
+///
+/// A frame transmitted by one radio reaches every other radio that
+/// listens on the same channel with the same data rate, CRC length
+/// and a matching pipe address. Auto-ack, ACK payloads and
+/// retransmission happen like on the real chips. All radios share
+/// one clock, which advances with the SPI traffic of any of them.
+///
+/// Losses are added with [`add_model()`](#method.add_model).
+///
+/// ```
+/// # use embedded_nrf24l01::NRF24L01;
+/// # use embedded_nrf24l01::sim::Ether;
+/// let ether = Ether::new();
+/// let (a, b) = (ether.radio(), ether.radio());
+/// let standby_a = NRF24L01::new(a.ce(), a.spi()).unwrap();
+/// let standby_b = NRF24L01::new(b.ce(), b.spi()).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct Ether {
+    air: Rc<RefCell<Air>>,
+}
+
+impl Default for Ether {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ether {
+    /// Empty air
+    pub fn new() -> Self {
+        Ether {
+            air: Rc::new(RefCell::new(Air::new())),
+        }
+    }
+
+    /// Attach a new nRF24L01+ in its reset state
+    pub fn radio(&self) -> SimRadio {
+        self.radio_with_variant(ChipVariant::Nrf24l01Plus)
+    }
+
+    /// Attach a new chip of another variant in its reset state
+    pub fn radio_with_variant(&self, variant: ChipVariant) -> SimRadio {
+        SimRadio::attach(self.air.clone(), variant)
+    }
+
+    /// Add a channel model that decides which transmissions are
+    /// received
+    ///
+    /// Without any model, every frame reaches every radio. With
+    /// several models, a transmission must pass all of them.
+    pub fn add_model<M: ChannelModel + 'static>(&self, model: M) {
+        self.air.borrow_mut().add_model(Box::new(model));
+    }
+
+    /// Current virtual time in µs
+    pub fn now(&self) -> u64 {
+        self.air.borrow().now()
+    }
+
+    /// Let `us` µs pass
+    pub fn advance(&self, us: u64) {
+        let mut air = self.air.borrow_mut();
+        let time = air.now() + us;
+        air.run_until(time);
+    }
+}
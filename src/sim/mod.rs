@@ -0,0 +1,432 @@
+//! Software model of the nRF24L01+ for running the driver in `cargo test`
+//!
+//! [`SimRadio`](struct.SimRadio.html) keeps the full register map, the
+//! 3-deep TX/RX FIFOs and the status flags, and decodes every SPI
+//! command. Its handles implement the embedded-hal traits that
+//! [`NRF24L01::new`](../struct.NRF24L01.html#method.new) takes:
+//!
+//! ```
+//! # use embedded_nrf24l01::NRF24L01;
+//! # use embedded_nrf24l01::sim::SimRadio;
+//! let radio = SimRadio::new();
+//! let standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+//! ```
+//!
+//! Time is virtual and measured in µs. It advances with the length of
+//! every SPI transaction, and explicitly with
+//! [`advance()`](struct.SimRadio.html#method.advance) or a
+//! [`SimDelay`](struct.SimDelay.html).
+//!
+//! Several radios can talk to each other through an
+//! [`Ether`](struct.Ether.html).
+//!
+//! With the `async` feature, the handles also implement the
+//! embedded-hal-async traits for the driver in `mod asynch`. Their
+//! futures never return `Pending`: waiting on the **IRQ** pin lets
+//! virtual time pass until the chip asserts it.
+
+use core::convert::Infallible;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::vec::Vec;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{ErrorType as PinErrorType, InputPin, OutputPin};
+use embedded_hal::spi::{ErrorType as SpiErrorType, Operation, SpiDevice};
+
+use crate::config::DataRate;
+use crate::variant::ChipVariant;
+
+mod chip;
+use self::chip::Chip;
+pub use self::chip::{FIFO_DEPTH, MAX_PAYLOAD_LEN, POWER_UP_US, SETTLING_US, SPI_BYTE_US};
+mod ether;
+use self::ether::Air;
+pub use self::ether::Ether;
+mod channel;
+pub use self::channel::{Attenuation, ChannelModel, Collisions, GilbertElliott, RandomLoss, Transmission};
+
+/// Step of virtual time while waiting on the **IRQ** pin
+#[cfg(feature = "async")]
+const IRQ_POLL_US: u64 = 10;
+
+/// A packet on the air
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// `RF_CH` of the transmitter
+    pub channel: u8,
+    /// Air data rate of the transmitter
+    pub data_rate: DataRate,
+    /// Destination address, `TX_ADDR` of the transmitter
+    pub address: Vec<u8>,
+    /// Packet content
+    pub payload: Vec<u8>,
+    /// 2-bit packet identity, incremented for every new payload
+    pub pid: u8,
+    /// Sent with `W_TX_PAYLOAD_NOACK`
+    pub no_ack: bool,
+    /// Carries a dynamic payload length
+    pub dynamic: bool,
+    /// CRC length in bytes
+    pub crc_bytes: u8,
+}
+
+impl Frame {
+    /// A frame with the chip's reset configuration for RF and CRC
+    pub fn new(channel: u8, address: &[u8], payload: &[u8]) -> Self {
+        Frame {
+            channel,
+            data_rate: DataRate::R2Mbps,
+            address: address.to_vec(),
+            payload: payload.to_vec(),
+            pid: 0,
+            no_ack: false,
+            dynamic: false,
+            crc_bytes: 1,
+        }
+    }
+
+    /// Time on the air
+    ///
+    /// Preamble, address, 9 bits of packet control field, payload and CRC.
+    pub fn airtime_us(&self) -> u64 {
+        let preamble = match self.data_rate {
+            DataRate::R2Mbps => 2,
+            _ => 1,
+        };
+        let bytes = preamble + self.address.len() + self.payload.len() + usize::from(self.crc_bytes);
+        let bits = 8 * bytes as u64 + 9;
+        match self.data_rate {
+            DataRate::R250Kbps => bits * 4,
+            DataRate::R1Mbps => bits,
+            DataRate::R2Mbps => bits.div_ceil(2),
+        }
+    }
+}
+
+/// An acknowledgement sent back to the transmitter of a frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ack {
+    /// ACK payload queued with `W_ACK_PAYLOAD`
+    pub payload: Option<Vec<u8>>,
+}
+
+impl Ack {
+    /// Time on the air when acknowledging `frame`
+    pub fn airtime_us(&self, frame: &Frame) -> u64 {
+        Frame {
+            payload: self.payload.clone().unwrap_or_default(),
+            no_ack: true,
+            dynamic: true,
+            ..frame.clone()
+        }
+        .airtime_us()
+    }
+}
+
+/// A simulated chip
+///
+/// Clones refer to the same chip. A radio created with `new()` is
+/// alone on its air, so nothing ever acknowledges its frames. Use
+/// [`Ether::radio()`](struct.Ether.html#method.radio) to connect
+/// several radios.
+#[derive(Clone)]
+pub struct SimRadio {
+    chip: Rc<RefCell<Chip>>,
+    air: Rc<RefCell<Air>>,
+    index: usize,
+}
+
+impl Default for SimRadio {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimRadio {
+    /// An nRF24L01+ in its reset state
+    pub fn new() -> Self {
+        Self::with_variant(ChipVariant::Nrf24l01Plus)
+    }
+
+    /// A chip of another variant in its reset state
+    pub fn with_variant(variant: ChipVariant) -> Self {
+        Self::attach(Rc::new(RefCell::new(Air::new())), variant)
+    }
+
+    pub(crate) fn attach(air: Rc<RefCell<Air>>, variant: ChipVariant) -> Self {
+        let chip = Rc::new(RefCell::new(Chip::new(variant)));
+        let index = air.borrow_mut().attach(chip.clone());
+        SimRadio { chip, air, index }
+    }
+
+    /// Identifies this radio in its [`Ether`](struct.Ether.html), for
+    /// use with channel models
+    pub fn id(&self) -> usize {
+        self.index
+    }
+
+    /// The **CE** pin
+    pub fn ce(&self) -> SimCe {
+        SimCe { radio: self.clone() }
+    }
+
+    /// The SPI bus, with **CSN**
+    pub fn spi(&self) -> SimSpi {
+        SimSpi { radio: self.clone() }
+    }
+
+    /// The **IRQ** pin
+    pub fn irq(&self) -> SimIrq {
+        SimIrq { radio: self.clone() }
+    }
+
+    /// A delay that lets virtual time pass
+    pub fn delay(&self) -> SimDelay {
+        SimDelay { radio: self.clone() }
+    }
+
+    /// Current virtual time in µs
+    pub fn now(&self) -> u64 {
+        self.air.borrow().now()
+    }
+
+    /// Let `us` µs pass
+    pub fn advance(&self, us: u64) {
+        let mut air = self.air.borrow_mut();
+        let time = air.now() + us;
+        air.run_until(time);
+    }
+
+    /// Read a register without side effects
+    ///
+    /// Address registers return all 5 bytes.
+    pub fn register(&self, addr: u8) -> Vec<u8> {
+        self.chip.borrow().register(addr)
+    }
+
+    /// Read a register of the second bank of a Beken part
+    pub fn bank1_register(&self, addr: u8) -> Vec<u8> {
+        self.chip.borrow().bank1_register(addr)
+    }
+
+    /// Is **CE** high?
+    pub fn ce_is_high(&self) -> bool {
+        self.chip.borrow().ce()
+    }
+
+    /// Channel of the constant carrier in `CarrierTestMode`
+    pub fn carrier(&self) -> Option<u8> {
+        self.chip.borrow().carrier()
+    }
+
+    /// Is the **IRQ** pin asserted?
+    pub fn irq_is_asserted(&self) -> bool {
+        self.chip.borrow().irq()
+    }
+
+    /// Let time pass until **IRQ** is `asserted`
+    #[cfg(feature = "async")]
+    fn wait_irq(&self, asserted: bool) {
+        while self.irq_is_asserted() != asserted {
+            self.advance(IRQ_POLL_US);
+        }
+    }
+
+    /// Number of payloads in the TX FIFO
+    pub fn tx_fifo_len(&self) -> usize {
+        self.chip.borrow().tx_fifo_len()
+    }
+
+    /// Number of payloads in the RX FIFO
+    pub fn rx_fifo_len(&self) -> usize {
+        self.chip.borrow().rx_fifo_len()
+    }
+
+    /// Let a frame arrive from outside the simulation
+    ///
+    /// Returns the ACK if the chip accepted and acknowledged it.
+    pub fn deliver(&self, frame: &Frame) -> Option<Ack> {
+        let now = self.now();
+        self.chip.borrow_mut().receive(now, frame)
+    }
+
+    /// Take all frames this radio has transmitted so far
+    pub fn take_transmitted(&self) -> Vec<Frame> {
+        self.air.borrow_mut().take_transmitted(self.index)
+    }
+
+    /// Run one transaction, which takes `delay_us` longer than the
+    /// transfer of `mosi`
+    fn transaction(&self, mosi: &[u8], delay_us: u64) -> Vec<u8> {
+        let mut air = self.air.borrow_mut();
+        let time = air.now() + SPI_BYTE_US * mosi.len() as u64 + delay_us;
+        air.run_until(time);
+        self.chip.borrow_mut().transaction(time, mosi)
+    }
+
+    fn set_ce(&self, ce: bool) {
+        let now = self.now();
+        self.chip.borrow_mut().set_ce(now, ce);
+    }
+}
+
+/// **CE** pin of a [`SimRadio`](struct.SimRadio.html)
+pub struct SimCe {
+    radio: SimRadio,
+}
+
+impl PinErrorType for SimCe {
+    type Error = Infallible;
+}
+
+impl OutputPin for SimCe {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.radio.set_ce(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.radio.set_ce(true);
+        Ok(())
+    }
+}
+
+/// Delay in the virtual time of a [`SimRadio`](struct.SimRadio.html)
+pub struct SimDelay {
+    radio: SimRadio,
+}
+
+impl DelayNs for SimDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.radio.advance(u64::from(ns.div_ceil(1000)));
+    }
+}
+
+/// **IRQ** pin of a [`SimRadio`](struct.SimRadio.html), active low
+pub struct SimIrq {
+    radio: SimRadio,
+}
+
+impl PinErrorType for SimIrq {
+    type Error = Infallible;
+}
+
+impl InputPin for SimIrq {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.radio.irq_is_asserted())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.radio.irq_is_asserted())
+    }
+}
+
+/// SPI device of a [`SimRadio`](struct.SimRadio.html)
+///
+/// Each transaction is one command, framed by **CSN**. Delays within
+/// a transaction let virtual time pass before **CSN** rises.
+pub struct SimSpi {
+    radio: SimRadio,
+}
+
+impl SpiErrorType for SimSpi {
+    type Error = Infallible;
+}
+
+impl SpiDevice<u8> for SimSpi {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        self.run(operations);
+        Ok(())
+    }
+}
+
+impl SimSpi {
+    fn run(&self, operations: &mut [Operation<'_, u8>]) {
+        let mut mosi = Vec::new();
+        let mut delay_ns = 0u64;
+        for operation in operations.iter() {
+            match operation {
+                Operation::Read(buf) => mosi.extend(core::iter::repeat_n(0xFF, buf.len())),
+                Operation::Write(buf) => mosi.extend_from_slice(buf),
+                Operation::Transfer(read, write) => {
+                    let len = read.len().max(write.len());
+                    mosi.extend(write.iter().cloned().chain(core::iter::repeat(0xFF)).take(len));
+                }
+                Operation::TransferInPlace(buf) => mosi.extend_from_slice(buf),
+                Operation::DelayNs(ns) => delay_ns += u64::from(*ns),
+            }
+        }
+
+        let miso = self.radio.transaction(&mosi, delay_ns.div_ceil(1000));
+
+        let mut miso = miso.iter();
+        for operation in operations.iter_mut() {
+            match operation {
+                Operation::Read(buf) | Operation::TransferInPlace(buf) => {
+                    for (b, m) in buf.iter_mut().zip(&mut miso) {
+                        *b = *m;
+                    }
+                }
+                Operation::Write(buf) => {
+                    (&mut miso).take(buf.len()).for_each(drop);
+                }
+                Operation::Transfer(read, write) => {
+                    let len = read.len().max(write.len());
+                    for (i, m) in (&mut miso).take(len).enumerate() {
+                        if let Some(b) = read.get_mut(i) {
+                            *b = *m;
+                        }
+                    }
+                }
+                Operation::DelayNs(_) => {}
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::spi::SpiDevice<u8> for SimSpi {
+    async fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        self.run(operations);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::delay::DelayNs for SimDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        DelayNs::delay_ns(self, ns);
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::digital::Wait for SimIrq {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        self.radio.wait_irq(false);
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        self.radio.wait_irq(true);
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        self.radio.wait_irq(true);
+        self.radio.wait_irq(false);
+        Ok(())
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        self.radio.wait_irq(false);
+        self.radio.wait_irq(true);
+        Ok(())
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        let asserted = self.radio.irq_is_asserted();
+        self.radio.wait_irq(!asserted);
+        Ok(())
+    }
+}
@@ -0,0 +1,262 @@
+use core::fmt;
+
+use crate::device::{Device, UsingDevice};
+use crate::registers::{
+    Config, Dynpd, EnAa, EnRxaddr, Feature, FifoStatus, ObserveTx, Register, RfCh, RfSetup,
+    RxAddrP0, RxAddrP1, RxAddrP2, RxAddrP3, RxAddrP4, RxAddrP5, RxPwP0, RxPwP1, RxPwP2, RxPwP3,
+    RxPwP4, RxPwP5, SetupAw, SetupRetr, Status, TxAddr, Value, CD,
+};
+use crate::standby::StandbyMode;
+
+/// Number of registers in a snapshot
+const COUNT: usize = 26;
+
+/// A bit field as `(name, high bit, low bit)`
+type Field = (&'static str, u8, u8);
+
+/// Name, address, and fields of every register
+const LAYOUT: [(&str, u8, &[Field]); COUNT] = [
+    (
+        "CONFIG",
+        0x00,
+        &[
+            ("MASK_RX_DR", 6, 6),
+            ("MASK_TX_DS", 5, 5),
+            ("MASK_MAX_RT", 4, 4),
+            ("EN_CRC", 3, 3),
+            ("CRCO", 2, 2),
+            ("PWR_UP", 1, 1),
+            ("PRIM_RX", 0, 0),
+        ],
+    ),
+    ("EN_AA", 0x01, PIPE_BITS),
+    ("EN_RXADDR", 0x02, PIPE_BITS),
+    ("SETUP_AW", 0x03, &[("AW", 1, 0)]),
+    ("SETUP_RETR", 0x04, &[("ARD", 7, 4), ("ARC", 3, 0)]),
+    ("RF_CH", 0x05, &[("RF_CH", 6, 0)]),
+    (
+        "RF_SETUP",
+        0x06,
+        &[
+            ("CONT_WAVE", 7, 7),
+            ("RF_DR_LOW", 5, 5),
+            ("PLL_LOCK", 4, 4),
+            ("RF_DR_HIGH", 3, 3),
+            ("RF_PWR", 2, 1),
+        ],
+    ),
+    (
+        "STATUS",
+        0x07,
+        &[
+            ("RX_DR", 6, 6),
+            ("TX_DS", 5, 5),
+            ("MAX_RT", 4, 4),
+            ("RX_P_NO", 3, 1),
+            ("TX_FULL", 0, 0),
+        ],
+    ),
+    ("OBSERVE_TX", 0x08, &[("PLOS_CNT", 7, 4), ("ARC_CNT", 3, 0)]),
+    ("RPD", 0x09, &[("RPD", 0, 0)]),
+    ("RX_ADDR_P0", 0x0A, &[]),
+    ("RX_ADDR_P1", 0x0B, &[]),
+    ("RX_ADDR_P2", 0x0C, &[]),
+    ("RX_ADDR_P3", 0x0D, &[]),
+    ("RX_ADDR_P4", 0x0E, &[]),
+    ("RX_ADDR_P5", 0x0F, &[]),
+    ("TX_ADDR", 0x10, &[]),
+    ("RX_PW_P0", 0x11, &[("RX_PW", 5, 0)]),
+    ("RX_PW_P1", 0x12, &[("RX_PW", 5, 0)]),
+    ("RX_PW_P2", 0x13, &[("RX_PW", 5, 0)]),
+    ("RX_PW_P3", 0x14, &[("RX_PW", 5, 0)]),
+    ("RX_PW_P4", 0x15, &[("RX_PW", 5, 0)]),
+    ("RX_PW_P5", 0x16, &[("RX_PW", 5, 0)]),
+    (
+        "FIFO_STATUS",
+        0x17,
+        &[
+            ("TX_REUSE", 6, 6),
+            ("TX_FULL", 5, 5),
+            ("TX_EMPTY", 4, 4),
+            ("RX_FULL", 1, 1),
+            ("RX_EMPTY", 0, 0),
+        ],
+    ),
+    ("DYNPD", 0x1C, PIPE_BITS),
+    (
+        "FEATURE",
+        0x1D,
+        &[("EN_DPL", 2, 2), ("EN_ACK_PAY", 1, 1), ("EN_DYN_ACK", 0, 0)],
+    ),
+];
+
+const PIPE_BITS: &[Field] = &[
+    ("P5", 5, 5),
+    ("P4", 4, 4),
+    ("P3", 3, 3),
+    ("P2", 2, 2),
+    ("P1", 1, 1),
+    ("P0", 0, 0),
+];
+
+fn read<D: Device, R: Register>(device: &mut D) -> Result<Value, D::Error> {
+    let (_, register) = device.read_register::<R>()?;
+    Ok(Value::of(register))
+}
+
+/// Write register `R` from `snapshot`
+fn write<D: Device, R: Register>(device: &mut D, snapshot: &RegisterSnapshot) -> Result<(), D::Error> {
+    if let Some(value) = snapshot.value(R::addr()) {
+        device.write_register(value.get::<R>())?;
+    }
+    Ok(())
+}
+
+/// Content of all registers, read at once
+///
+/// `Display` renders a decoded dump with one register per line.
+/// Multi-byte addresses are shown LSByte first, as on the wire.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RegisterSnapshot {
+    values: [Value; COUNT],
+}
+
+impl RegisterSnapshot {
+    pub(crate) fn read<D: Device>(device: &mut D) -> Result<Self, D::Error> {
+        macro_rules! read_all {
+            ($($name: ident),+) => {
+                [$( read::<D, $name>(device)? ),+]
+            };
+        }
+        let values = read_all!(
+            Config, EnAa, EnRxaddr, SetupAw, SetupRetr, RfCh, RfSetup, Status, ObserveTx, CD,
+            RxAddrP0, RxAddrP1, RxAddrP2, RxAddrP3, RxAddrP4, RxAddrP5, TxAddr, RxPwP0, RxPwP1,
+            RxPwP2, RxPwP3, RxPwP4, RxPwP5, FifoStatus, Dynpd, Feature
+        );
+        Ok(RegisterSnapshot { values })
+    }
+
+    /// Content of the register at `addr`, if it is part of the snapshot
+    pub fn register(&self, addr: u8) -> Option<&[u8]> {
+        self.value(addr).map(Value::as_bytes)
+    }
+
+    fn value(&self, addr: u8) -> Option<&Value> {
+        self.values.iter().find(|value| value.addr == addr)
+    }
+
+    /// `LAYOUT` entries with their values
+    fn entries(&self) -> impl Iterator<Item = (&'static str, u8, &'static [Field], &Value)> + '_ {
+        LAYOUT.iter().filter_map(move |(name, addr, fields)| {
+            self.value(*addr).map(|value| (*name, *addr, *fields, value))
+        })
+    }
+
+    /// Registers that differ from `other`
+    pub fn diff<'a>(
+        &'a self,
+        other: &'a RegisterSnapshot,
+    ) -> impl Iterator<Item = RegisterChange<'a>> + 'a {
+        self.entries()
+            .filter_map(move |(name, addr, _, before)| {
+                other.value(addr).map(|after| (name, addr, before, after))
+            })
+            .filter(|(_, _, before, after)| before != after)
+            .map(|(name, addr, before, after)| RegisterChange {
+                name,
+                addr,
+                before: before.as_bytes(),
+                after: after.as_bytes(),
+            })
+    }
+}
+
+fn write_bytes(f: &mut fmt::Formatter, bytes: &[u8]) -> fmt::Result {
+    for (i, byte) in bytes.iter().enumerate() {
+        if i > 0 {
+            write!(f, " ")?;
+        }
+        write!(f, "{:02X}", byte)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for RegisterSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, addr, fields, value) in self.entries() {
+            write!(f, "{:<11} 0x{:02X}: ", name, addr)?;
+            write_bytes(f, value.as_bytes())?;
+            for (field, high, low) in fields.iter() {
+                let width = high - low + 1;
+                let field_value = (value.as_bytes()[0] >> low) & (0xFF >> (8 - width));
+                write!(f, " {}={}", field, field_value)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for RegisterSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut s = f.debug_struct("RegisterSnapshot");
+        for (name, _, _, value) in self.entries() {
+            s.field(name, &value.as_bytes());
+        }
+        s.finish()
+    }
+}
+
+/// A register that differs between two snapshots, see
+/// [`RegisterSnapshot::diff()`](struct.RegisterSnapshot.html#method.diff)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterChange<'a> {
+    /// Register name as in the datasheet
+    pub name: &'static str,
+    /// Register address
+    pub addr: u8,
+    /// Content in the first snapshot
+    pub before: &'a [u8],
+    /// Content in the other snapshot
+    pub after: &'a [u8],
+}
+
+impl fmt::Display for RegisterChange<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} 0x{:02X}: ", self.name, self.addr)?;
+        write_bytes(f, self.before)?;
+        write!(f, " -> ")?;
+        write_bytes(f, self.after)
+    }
+}
+
+impl<D: Device> StandbyMode<D> {
+    /// Write a snapshot back to the chip
+    ///
+    /// All writable registers are restored, except for `PWR_UP` and
+    /// `PRIM_RX` in `CONFIG`, which belong to the current mode.
+    /// `STATUS`, `OBSERVE_TX`, `RPD` and `FIFO_STATUS` are read-only
+    /// or reflect events, and are skipped.
+    pub fn restore(&mut self, snapshot: &RegisterSnapshot) -> Result<(), D::Error> {
+        macro_rules! write_all {
+            ($($name: ident),+) => {
+                $( write::<D, $name>(self.device(), snapshot)?; )+
+            };
+        }
+        // SETUP_AW before the addresses, and FEATURE enables DYNPD
+        write_all!(
+            SetupAw, EnAa, EnRxaddr, SetupRetr, RfCh, RfSetup, RxAddrP0, RxAddrP1, RxAddrP2,
+            RxAddrP3, RxAddrP4, RxAddrP5, TxAddr, RxPwP0, RxPwP1, RxPwP2, RxPwP3, RxPwP4, RxPwP5,
+            Feature, Dynpd
+        );
+
+        let config = match snapshot.value(Config::addr()) {
+            Some(value) => value.get::<Config>().0,
+            None => return Ok(()),
+        };
+        self.device().update_config(|current| {
+            let mode_bits = 0b11;
+            *current = Config((config & !mode_bits) | (current.0 & mode_bits));
+        })
+    }
+}
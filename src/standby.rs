@@ -1,11 +1,19 @@
 use core::fmt;
 
-use crate::config::{ Configuration, auto_retransmit, auto_ack };
+use crate::config::{ Channel, Configuration, auto_retransmit, auto_ack };
 use crate::device::{ Device, UsingDevice };
+use crate::error::ConfigError;
 use crate::rx::RxMode;
 use crate::tx::TxMode;
-use crate::ptx::PtxMode;
-use crate::registers::{ Feature, Dynpd };
+use crate::ptx::{PtxMode, PtxRegisters};
+use crate::prx::PrxMode;
+use crate::beacon::BeaconMode;
+use crate::carrier::CarrierTestMode;
+use crate::powerdown::PowerDownMode;
+use crate::command::{FlushTx, ReuseTxPayload, WriteTxPayloadNoAck};
+use crate::payload;
+use crate::registers::{ Config, Feature, Dynpd, RfCh, RfSetup };
+use crate::timing::{POWER_UP_US, SETTLING_US};
 use crate::PIPES_COUNT;
 
 /// Represents **Standby-I** mode
@@ -36,34 +44,50 @@ impl<D: Device> Configuration<D> for StandbyMode<D> {
 impl<D: Device> StandbyMode<D> {
     /// Constructor
     ///
-    /// Puts the `device` into standy mode
+    /// Puts the `device` into standy mode, and waits for the start-up
+    /// time if it was powered down
     pub fn power_up(mut device: D) -> Result<Self, (D, D::Error)> {
-        match device.update_config(|config| config.set_pwr_up(true)) {
-            Ok(()) => Ok(StandbyMode { device }),
+        let power_up = |config: &mut Config| {
+            let was_down = !config.pwr_up();
+            config.set_pwr_up(true);
+            was_down
+        };
+        match device.update_config(power_up) {
+            Ok(was_down) => {
+                if was_down {
+                    device.delay_us(POWER_UP_US);
+                }
+                Ok(StandbyMode { device })
+            }
             Err(e) => Err((device, e)),
         }
     }
 
-    /// Should be a no-op
-    pub fn power_down(mut self) -> Result<D, (Self, D::Error)> {
+    /// Power down, keeping the register content
+    pub fn power_down(mut self) -> Result<PowerDownMode<D>, (Self, D::Error)> {
         match self.device.update_config(|config| config.set_pwr_up(false)) {
-            Ok(()) => Ok(self.device),
+            Ok(()) => Ok(PowerDownMode::new(self.device)),
             Err(e) => Err((self, e)),
         }
     }
 
-    pub(crate) fn from_rx_tx(mut device: D) -> Self {
-        device.ce_disable();
-        StandbyMode { device }
+    pub(crate) fn from_rx_tx(mut device: D) -> Result<Self, (D, D::Error)> {
+        match device.ce_disable() {
+            Ok(()) => Ok(StandbyMode { device }),
+            Err(e) => Err((device, e)),
+        }
     }
 
-    /// Go into RX mode
+    /// Go into RX mode, and wait for the 130 µs settling time
     pub fn rx(self) -> Result<RxMode<D>, (D, D::Error)> {
         let mut device = self.device;
 
-        match device.update_config(|config| config.set_prim_rx(true)) {
+        match device
+            .update_config(|config| config.set_prim_rx(true))
+            .and_then(|()| device.ce_enable())
+        {
             Ok(()) => {
-                device.ce_enable();
+                device.delay_us(SETTLING_US);
                 Ok(RxMode::new(device))
             }
             Err(e) => Err((device, e)),
@@ -83,12 +107,26 @@ impl<D: Device> StandbyMode<D> {
         }
     }
 
-    /// Enter PTX mode. 
+    /// Enter PTX mode
+    ///
+    /// Enables auto-ack, ACK payloads, and dynamic payload lengths on
+    /// all pipes, and auto-retransmit with `delay` and `retries`, see
+    /// `set_auto_retransmit()`. Fails with
+    /// `ConfigError::AutoRetransmit` for values above 15. If writing
+    /// fails partway, the registers are restored.
     pub fn ptx(self, delay: u8, retries: u8) -> Result<PtxMode<D>, (D, D::Error)> {
-
         let mut device = self.device;
-        let mut config_ptx =  || {
-            device.write_register(auto_ack(&[ true; 6 ]))?;
+        if delay > 0xF || retries > 0xF {
+            let e = device.invalid_config(ConfigError::AutoRetransmit(delay, retries));
+            return Err((device, e));
+        }
+        let saved = match PtxRegisters::read(&mut device) {
+            Ok(saved) => saved,
+            Err(e) => return Err((device, e)),
+        };
+
+        let mut config_ptx = || {
+            device.write_register(auto_ack(&[true; PIPES_COUNT]))?;
             device.write_register(auto_retransmit(delay, retries))?;
             // Enable ack payload and dynamic payload features
             device.update_register::<Feature, _, _>(|feature| {
@@ -97,21 +135,107 @@ impl<D: Device> StandbyMode<D> {
             })?;
             // Enable dynamic payload on all pipes
             device.write_register(Dynpd::from_bools(&[true; PIPES_COUNT]))?;
-            Ok(())
+            device.update_config(|config| config.set_prim_rx(false))
         };
 
         match config_ptx() {
-            Ok(()) => {
-                match device.update_config(|config| config.set_prim_rx(false)) {
-                    Ok(()) => {
-                        // No need to device.ce_enable(); yet
-                        Ok(PtxMode::new(device))
-                    }
-                    Err(e) => Err((device, e)),
-                }
-            },
-            Err(e) => Err((device, e))
+            // No need to device.ce_enable(); yet
+            Ok(()) => Ok(PtxMode::new(device, saved)),
+            Err(e) => {
+                // The first error is the one to report
+                let _ = saved.write(&mut device);
+                Err((device, e))
+            }
         }
     }
-}
 
+    /// Enter PRX mode
+    ///
+    /// Enables auto-ack, ACK payloads, and dynamic payload lengths on
+    /// all pipes, and flushes the TX FIFO that holds the ACK payloads.
+    pub fn prx(self) -> Result<PrxMode<D>, (D, D::Error)> {
+        let mut device = self.device;
+        let mut config_prx = || {
+            device.write_register(auto_ack(&[true; PIPES_COUNT]))?;
+            device.update_register::<Feature, _, _>(|feature| {
+                feature.set_en_ack_pay(true);
+                feature.set_en_dpl(true);
+            })?;
+            device.write_register(Dynpd::from_bools(&[true; PIPES_COUNT]))?;
+            device.send_command(&FlushTx)?;
+            device.update_config(|config| config.set_prim_rx(true))?;
+            device.ce_enable()?;
+            device.delay_us(SETTLING_US);
+            Ok(())
+        };
+
+        match config_prx() {
+            Ok(()) => Ok(PrxMode::new(device)),
+            Err(e) => Err((device, e)),
+        }
+    }
+
+    /// Enter beacon mode, which sends `payload` repeatedly
+    ///
+    /// The TX FIFO is flushed, and `payload` is loaded once with
+    /// `W_TX_PAYLOAD_NOACK` and `REUSE_TX_PL`. Fails with
+    /// `ConfigError::PayloadLength` for a `payload` above 32 bytes.
+    pub fn beacon(self, payload: &[u8]) -> Result<BeaconMode<D>, (D, D::Error)> {
+        let mut device = self.device;
+        if let Err(e) = payload::check_len(payload) {
+            let e = device.invalid_config(e);
+            return Err((device, e));
+        }
+        let mut config_beacon = || {
+            device.update_config(|config| config.set_prim_rx(false))?;
+            device.send_command(&FlushTx)?;
+            device.update_register::<Feature, _, _>(|feature| {
+                feature.set_en_dyn_ack(true);
+            })?;
+            device.send_command(&WriteTxPayloadNoAck::new(payload))?;
+            device.send_command(&ReuseTxPayload)?;
+            Ok(())
+        };
+
+        match config_beacon() {
+            Ok(()) => Ok(BeaconMode::new(device)),
+            Err(e) => Err((device, e)),
+        }
+    }
+
+    /// Emit a constant carrier on `channel`, at the highest output
+    /// power that does not exceed `dbm`
+    ///
+    /// Fails with `Error::Unsupported` on variants without
+    /// `CONT_WAVE`.
+    pub fn carrier_test(self, channel: Channel, dbm: i8) -> Result<CarrierTestMode<D>, (D, D::Error)> {
+        let mut device = self.device;
+        let variant = device.variant();
+        if !variant.has_cont_wave() {
+            let e = device.unsupported();
+            return Err((device, e));
+        }
+        let mut config_carrier = || {
+            let (_, rf_setup) = device.read_register::<RfSetup>()?;
+            let level = variant.power_level_at_most(dbm);
+            let mut carrier = RfSetup(rf_setup.0);
+            carrier.set_cont_wave(true);
+            carrier.set_pll_lock(true);
+            variant.set_power_level(&mut carrier, level);
+
+            let mut rf_ch = RfCh(0);
+            rf_ch.set_rf_ch(channel.get());
+            device.update_config(|config| config.set_prim_rx(false))?;
+            device.write_register(carrier)?;
+            device.write_register(rf_ch)?;
+            device.ce_enable()?;
+            device.delay_us(SETTLING_US);
+            Ok(rf_setup)
+        };
+
+        match config_carrier() {
+            Ok(rf_setup) => Ok(CarrierTestMode::new(device, rf_setup)),
+            Err(e) => Err((device, e)),
+        }
+    }
+}
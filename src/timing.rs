@@ -0,0 +1,29 @@
+//! Datasheet timing, and the delay provider used by mode transitions
+//!
+//! Drivers constructed with `new()` use [`NoDelay`](struct.NoDelay.html),
+//! and the caller has to wait for these times itself. With
+//! `new_with_delay()` transitions wait for them before returning.
+
+use embedded_hal::delay::DelayNs;
+
+/// **Tpd2stby**, start-up time from Power Down to Standby-I
+///
+/// 1.5 ms is the worst case, with an external clock it is 150 µs.
+pub const POWER_UP_US: u32 = 1500;
+/// **Tstby2a**, settling time from Standby to RX or TX
+pub const SETTLING_US: u32 = 130;
+/// **Thce**, minimum CE high time that starts a transmission
+pub const CE_PULSE_US: u32 = 10;
+
+/// Delay provider that returns at once
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoDelay;
+
+impl DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::delay::DelayNs for NoDelay {
+    async fn delay_ns(&mut self, _ns: u32) {}
+}
@@ -1,10 +1,63 @@
-use crate::command::{FlushTx, WriteTxPayload};
+use crate::command::{FlushTx, WriteTxPayload, WriteTxPayloadNoAck};
 use crate::config::Configuration;
 use crate::device::{ Device, UsingDevice };
-use crate::registers::{FifoStatus, ObserveTx, Status};
+use crate::payload;
+use crate::registers::{Feature, FifoStatus, ObserveTx, Status};
+use crate::rxtx::{MaxRtPolicy, TxShadow};
 use crate::standby::StandbyMode;
 use core::fmt;
 
+/// Apply the policy of `shadow` to `MAX_RT` in `status`, and clear
+/// `TX_DS` and `MAX_RT`. Returns the number of discarded packets.
+pub(crate) fn handle_max_rt<D: Device>(
+    device: &mut D,
+    status: &Status,
+    shadow: &mut TxShadow,
+) -> Result<usize, D::Error> {
+    let discarded = match shadow.retry(status.tx_ds()) {
+        Some(backoff_us) => {
+            device.delay_us(backoff_us);
+            0
+        }
+        None => {
+            let queued = queued_on_max_rt(device)?;
+            device.send_command(&FlushTx)?;
+            if shadow.policy() == MaxRtPolicy::FlushAll {
+                queued
+            } else {
+                for (payload, no_ack) in shadow.last(queued).skip(1) {
+                    if *no_ack {
+                        device.send_command(&WriteTxPayloadNoAck::new(payload))?;
+                    } else {
+                        device.send_command(&WriteTxPayload::new(payload))?;
+                    }
+                }
+                1
+            }
+        }
+    };
+    let mut clear = Status(0);
+    clear.set_tx_ds(true);
+    clear.set_max_rt(true);
+    device.write_register(clear)?;
+    Ok(discarded)
+}
+
+/// Number of packets in the TX FIFO while `MAX_RT` halts it
+///
+/// `FIFO_STATUS` cannot tell one packet from two, so a probe is
+/// written that fills the FIFO only in the latter case. It is flushed
+/// along with the others.
+fn queued_on_max_rt<D: Device>(device: &mut D) -> Result<usize, D::Error> {
+    let (_, fifo_status) = device.read_register::<FifoStatus>()?;
+    if fifo_status.tx_full() {
+        return Ok(3);
+    }
+    device.send_command(&WriteTxPayload::new(&[0]))?;
+    let (_, fifo_status) = device.read_register::<FifoStatus>()?;
+    Ok(if fifo_status.tx_full() { 2 } else { 1 })
+}
+
 /// Represents **TX Mode** and the associated **TX Settling** and
 /// **Standby-II** states
 ///
@@ -18,6 +71,12 @@ use core::fmt;
 /// warranty could get void.
 pub struct TxMode<D: Device> {
     device: D,
+    /// `EN_DYN_ACK` has been set
+    dyn_ack: bool,
+    /// Packets in the TX FIFO, and the `MAX_RT` policy
+    shadow: TxShadow,
+    /// Packets discarded since `poll_send()` last returned
+    discarded: usize,
 }
 
 impl<D: Device> fmt::Debug for TxMode<D> {
@@ -40,14 +99,27 @@ impl<D: Device> TxMode<D> {
     /// Relies on everything being set up by `StandbyMode::tx()`, from
     /// which it is called
     pub(crate) fn new(device: D) -> Self {
-        TxMode { device }
+        TxMode {
+            device,
+            dyn_ack: false,
+            shadow: TxShadow::new(),
+            discarded: 0,
+        }
     }
 
-    /// Disable `CE` so that you can switch into RX mode.
-    pub fn standby(mut self) -> Result<StandbyMode<D>, D::Error> {
-        self.wait_empty()?;
+    /// Select what happens when a packet is not acknowledged after the
+    /// maximum number of retransmits, by default
+    /// [`MaxRtPolicy::FlushAll`](enum.MaxRtPolicy.html#variant.FlushAll)
+    pub fn set_max_rt_policy(&mut self, policy: MaxRtPolicy) {
+        self.shadow.set_policy(policy);
+    }
 
-        Ok(StandbyMode::from_rx_tx(self.device))
+    /// Disable `CE` so that you can switch into RX mode.
+    pub fn standby(mut self) -> Result<StandbyMode<D>, (D, D::Error)> {
+        match self.wait_empty() {
+            Ok(_) => StandbyMode::from_rx_tx(self.device),
+            Err(e) => Err((self.device, e)),
+        }
     }
 
     /// Is TX FIFO empty?
@@ -69,32 +141,61 @@ impl<D: Device> TxMode<D> {
     }
 
     /// Send asynchronously
+    ///
+    /// Fails with `ConfigError::PayloadLength` for a `packet` above
+    /// 32 bytes.
     pub fn send(&mut self, packet: &[u8]) -> Result<(), D::Error> {
+        if let Err(e) = payload::check_len(packet) {
+            return Err(self.device.invalid_config(e));
+        }
         self.device.send_command(&WriteTxPayload::new(packet))?;
-        self.device.ce_enable();
+        self.shadow.record(packet, false);
+        self.device.ce_enable()?;
+        Ok(())
+    }
+
+    /// Send asynchronously, without requesting an acknowledgement
+    ///
+    /// The receiver does not acknowledge the packet even with auto-ack
+    /// enabled, so it is sent once. Enables `EN_DYN_ACK` on first use.
+    /// Fails with `ConfigError::PayloadLength` for a `packet` above 32
+    /// bytes.
+    pub fn send_no_ack(&mut self, packet: &[u8]) -> Result<(), D::Error> {
+        if let Err(e) = payload::check_len(packet) {
+            return Err(self.device.invalid_config(e));
+        }
+        if !self.dyn_ack {
+            self.device.update_register::<Feature, _, _>(|feature| {
+                feature.set_en_dyn_ack(true);
+            })?;
+            self.dyn_ack = true;
+        }
+        self.device.send_command(&WriteTxPayloadNoAck::new(packet))?;
+        self.shadow.record(packet, true);
+        self.device.ce_enable()?;
         Ok(())
     }
 
-    /// Poll completion of one or multiple send operations and check whether transmission was
-    /// successful.
+    /// Poll completion of one or multiple send operations, and
+    /// return the number of packets that have been discarded
     ///
-    /// This function behaves like `wait_empty()`, except that it returns whether sending was
-    /// successful and that it provides an asynchronous interface.
-    pub fn poll_send(&mut self) -> nb::Result<bool, D::Error> {
-        let (status, fifo_status) = self.device.read_register::<FifoStatus>()?;
-        // We need to clear all the TX interrupts whenever we return Ok here so that the next call
-        // to poll_send correctly recognizes max_rt and send completion.
+    /// This function behaves like `wait_empty()`, except that it provides an asynchronous
+    /// interface. `0` means that all packets have been sent.
+    pub fn poll_send(&mut self) -> nb::Result<usize, D::Error> {
+        let (status, mut fifo_status) = self.device.read_register::<FifoStatus>()?;
         if status.max_rt() {
-            // If MAX_RT is set, the packet is not removed from the FIFO, so if we do not flush
-            // the FIFO, we end up in an infinite loop
-            self.device.send_command(&FlushTx)?;
-            self.clear_interrupts_and_ce()?;
-            Ok(false)
-        } else if fifo_status.tx_empty() {
+            // TX won't continue while MAX_RT is set
+            self.discarded += handle_max_rt(&mut self.device, &status, &mut self.shadow)?;
+            let (_, after) = self.device.read_register::<FifoStatus>()?;
+            fifo_status = after;
+        }
+        if fifo_status.tx_empty() {
+            // We need to clear all the TX interrupts whenever we return Ok here so that the next
+            // call to poll_send correctly recognizes max_rt and send completion.
             self.clear_interrupts_and_ce()?;
-            Ok(true)
+            Ok(core::mem::replace(&mut self.discarded, 0))
         } else {
-            self.device.ce_enable();
+            self.device.ce_enable()?;
             Err(nb::Error::WouldBlock)
         }
     }
@@ -103,44 +204,40 @@ impl<D: Device> TxMode<D> {
         let mut clear = Status(0);
         clear.set_tx_ds(true);
         clear.set_max_rt(true);
-        self.device.write_register(clear)?;
+        let status = self.device.write_register(clear)?;
+        self.shadow.observe(&status);
 
         // Can save power now
-        self.device.ce_disable();
+        self.device.ce_disable()?;
 
         Ok(())
     }
 
-    /// Wait until TX FIFO is empty
+    /// Wait until TX FIFO is empty, and return the number of packets
+    /// that have been discarded
     ///
-    /// If any packet cannot be delivered and the maximum amount of retries is
-    /// reached, the TX FIFO is flushed and all other packets in the FIFO are
-    /// lost.
-    pub fn wait_empty(&mut self) -> Result<(), D::Error> {
-        let mut empty = false;
-        while !empty {
+    /// When a packet cannot be delivered and the maximum amount of
+    /// retries is reached, the [`MaxRtPolicy`](enum.MaxRtPolicy.html)
+    /// applies. By default the TX FIFO is flushed and all other
+    /// packets in the FIFO are lost.
+    pub fn wait_empty(&mut self) -> Result<usize, D::Error> {
+        let mut discarded = core::mem::replace(&mut self.discarded, 0);
+        loop {
             let (status, fifo_status) = self.device.read_register::<FifoStatus>()?;
-            empty = fifo_status.tx_empty();
-            if !empty {
-                self.device.ce_enable();
-            }
-
             // TX won't continue while MAX_RT is set
             if status.max_rt() {
-                let mut clear = Status(0);
-                // If MAX_RT is set, the packet is not removed from the FIFO, so if we do not flush
-                // the FIFO, we end up in an infinite loop
-                self.flush_tx()?;
-                // Clear TX interrupts
-                clear.set_tx_ds(true);
-                clear.set_max_rt(true);
-                self.device.write_register(clear)?;
+                discarded += handle_max_rt(&mut self.device, &status, &mut self.shadow)?;
+                continue;
+            }
+            if fifo_status.tx_empty() {
+                break;
             }
+            self.device.ce_enable()?;
         }
         // Can save power now
-        self.device.ce_disable();
+        self.device.ce_disable()?;
 
-        Ok(())
+        Ok(discarded)
     }
 
     /// Read the `OBSERVE_TX` register
@@ -0,0 +1,256 @@
+use crate::command::{Activate, Nop, WriteRawRegister};
+use crate::device::Device;
+use crate::error::ConfigError;
+use crate::registers::{Feature, RfSetup};
+
+/// Chip variants that differ in features
+///
+/// The constructor tells them apart by probing reserved bits, which
+/// is a heuristic: a clone that is not listed here, or a listed one
+/// with another die revision, may be detected as the wrong variant.
+/// `Configuration::set_variant()` overrides the detection.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ChipVariant {
+    /// The original nRF24L01
+    ///
+    /// Has no 250 kbps data rate, register `0x09` is a carrier detect
+    /// instead of a received power detector, and `FEATURE`/`DYNPD`
+    /// need the `ACTIVATE` command.
+    Nrf24l01,
+    /// The nRF24L01+
+    Nrf24l01Plus,
+    /// The Si24R1 clone of the nRF24L01+
+    ///
+    /// `RF_PWR` is 3 bits wide and goes up to +7 dBm. It cannot be
+    /// told apart from the nRF24L01+, so it is only used once
+    /// selected with `Configuration::set_variant()`.
+    Si24r1,
+    /// The Beken BK2421, also sold as RFM70
+    ///
+    /// Like the nRF24L01, but with a second register bank that must
+    /// be initialised.
+    Bk2421,
+    /// The Beken BK2423, a BK2421 with 250 kbps
+    Bk2423,
+}
+
+/// Output power of `RF_PWR` of the Nordic parts
+const NORDIC_POWER_LEVELS: [i8; 4] = [-18, -12, -6, 0];
+/// Output power of `RF_PWR`, extended by bit 0, of the Si24R1
+const SI24R1_POWER_LEVELS: [i8; 8] = [-12, -6, -4, 0, 1, 3, 4, 7];
+/// Output power of `RF_PWR` of the Beken parts
+const BEKEN_POWER_LEVELS: [i8; 4] = [-10, -5, 0, 5];
+
+/// Highest register address of `R_REGISTER` and `W_REGISTER`
+const MAX_REGISTER: u8 = 0x1F;
+/// Longest register content that a command carries
+const MAX_REGISTER_LEN: usize = 32;
+
+/// Bank 1 registers `0x00..=0x0D` as recommended by Beken, LSByte
+/// first. `0x06..=0x0B` are reserved.
+const BANK1_INIT: [[u8; 4]; 14] = [
+    [0x40, 0x4B, 0x01, 0xE2],
+    [0xC0, 0x4B, 0x00, 0x00],
+    [0xD0, 0xFC, 0x8C, 0x02],
+    [0x99, 0x00, 0x39, 0x41],
+    [0xF9, 0x9E, 0x86, 0x0B],
+    [0x24, 0x06, 0x7F, 0xA6],
+    [0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x12, 0x73, 0x00],
+    [0x36, 0xB4, 0x80, 0x00],
+];
+/// Bank 1 register `0x0E`, the ramp curve
+const BANK1_RAMP: [u8; 11] = [0x41, 0x20, 0x08, 0x04, 0x81, 0x20, 0xCF, 0xF7, 0xFE, 0xFF, 0xFF];
+/// Bits 25 and 26 of bank 1 register `0x04` must be toggled after
+/// the init sequence.
+const BANK1_REG4_TOGGLED: [u8; 4] = {
+    let mut reg4 = BANK1_INIT[4];
+    reg4[0] |= 0x06;
+    reg4
+};
+
+impl ChipVariant {
+    /// Supports `DataRate::R250Kbps`
+    pub fn has_250kbps(&self) -> bool {
+        match *self {
+            ChipVariant::Nrf24l01Plus | ChipVariant::Si24r1 | ChipVariant::Bk2423 => true,
+            ChipVariant::Nrf24l01 | ChipVariant::Bk2421 => false,
+        }
+    }
+
+    /// Register `0x09` is `RPD`, with a -64 dBm threshold
+    pub fn has_rpd(&self) -> bool {
+        *self == ChipVariant::Nrf24l01Plus
+    }
+
+    /// `RF_SETUP` has `CONT_WAVE` for a constant carrier
+    pub fn has_cont_wave(&self) -> bool {
+        matches!(*self, ChipVariant::Nrf24l01Plus | ChipVariant::Si24r1)
+    }
+
+    /// `FEATURE` and `DYNPD` are locked until `ACTIVATE`
+    pub fn needs_activate(&self) -> bool {
+        match *self {
+            ChipVariant::Nrf24l01 | ChipVariant::Bk2421 | ChipVariant::Bk2423 => true,
+            ChipVariant::Nrf24l01Plus | ChipVariant::Si24r1 => false,
+        }
+    }
+
+    /// Has a second register bank, selected with `ACTIVATE`
+    pub fn has_bank1(&self) -> bool {
+        matches!(*self, ChipVariant::Bk2421 | ChipVariant::Bk2423)
+    }
+
+    /// Output power in dBm of every power level, lowest first
+    pub fn power_levels(&self) -> &'static [i8] {
+        match *self {
+            ChipVariant::Nrf24l01 | ChipVariant::Nrf24l01Plus => &NORDIC_POWER_LEVELS,
+            ChipVariant::Si24r1 => &SI24R1_POWER_LEVELS,
+            ChipVariant::Bk2421 | ChipVariant::Bk2423 => &BEKEN_POWER_LEVELS,
+        }
+    }
+
+    /// The highest power level that does not exceed `dbm`, or the
+    /// lowest one
+    pub(crate) fn power_level_at_most(&self, dbm: i8) -> u8 {
+        let level = self.power_levels().iter().rposition(|level| *level <= dbm);
+        level.unwrap_or(0) as u8
+    }
+
+    pub(crate) fn power_level(&self, rf_setup: &RfSetup) -> u8 {
+        match *self {
+            ChipVariant::Si24r1 => rf_setup.rf_pwr_si24r1(),
+            _ => rf_setup.rf_pwr(),
+        }
+    }
+
+    pub(crate) fn set_power_level(&self, rf_setup: &mut RfSetup, level: u8) {
+        match *self {
+            ChipVariant::Si24r1 => rf_setup.set_rf_pwr_si24r1(level),
+            _ => rf_setup.set_rf_pwr(level),
+        }
+    }
+}
+
+/// `RF_SETUP` with the bits set that tell the variants apart, see
+/// `classify()`
+pub(crate) fn rf_setup_probe(rf_setup: &RfSetup) -> RfSetup {
+    let mut probe = RfSetup(rf_setup.0);
+    probe.set_rf_dr_low(true);
+    probe
+}
+
+/// The variant, from whether `ACTIVATE` switched to a second
+/// register bank, and from `RF_SETUP` read back after writing
+/// `rf_setup_probe()`
+///
+/// * Beken parts indicate the second register bank in bit 7 of
+///   `STATUS`, which is reserved on the others.
+/// * `RF_DR_LOW` is reserved, and reads back as `0`, on the
+///   nRF24L01 and the BK2421.
+///
+/// The Si24R1 is reported as an nRF24L01+: bit 0 of `RF_SETUP`,
+/// which belongs to its `RF_PWR`, reads back as written on many
+/// genuine nRF24L01+ too.
+pub(crate) fn classify(beken: bool, probed: &RfSetup) -> ChipVariant {
+    match (beken, probed.rf_dr_low()) {
+        (true, false) => ChipVariant::Bk2421,
+        (true, true) => ChipVariant::Bk2423,
+        (false, false) => ChipVariant::Nrf24l01,
+        (false, true) => ChipVariant::Nrf24l01Plus,
+    }
+}
+
+/// Writes of the bank 1 init sequence of Beken parts, in order, as
+/// `(address, value)`
+pub(crate) fn bank1_init() -> impl Iterator<Item = (u8, &'static [u8])> {
+    let registers = BANK1_INIT.iter().enumerate().map(|(addr, value)| (addr as u8, &value[..]));
+    registers.chain([
+        (0x0E, &BANK1_RAMP[..]),
+        (0x04, &BANK1_REG4_TOGGLED[..]),
+        (0x04, &BANK1_INIT[4][..]),
+    ])
+}
+
+/// `FEATURE` with a flipped bit, which sticks only once activated
+pub(crate) fn feature_probe(feature: &Feature) -> Feature {
+    let mut probe = Feature(feature.0);
+    probe.set_en_dyn_ack(!feature.en_dyn_ack());
+    probe
+}
+
+/// Tell the variants apart, see `classify()`
+///
+/// This is a heuristic, as no register identifies the chip.
+pub(crate) fn detect<D: Device>(device: &mut D) -> Result<ChipVariant, D::Error> {
+    device.send_command(&Activate::Bank)?;
+    let (status, ()) = device.send_command(&Nop)?;
+    let beken = status.rbank();
+    if beken {
+        device.send_command(&Activate::Bank)?;
+    }
+
+    let (_, rf_setup) = device.read_register::<RfSetup>()?;
+    device.write_register(rf_setup_probe(&rf_setup))?;
+    let (_, probed) = device.read_register::<RfSetup>()?;
+    device.write_register(rf_setup)?;
+
+    Ok(classify(beken, &probed))
+}
+
+/// Fail for a register address or content that `R_REGISTER` and
+/// `W_REGISTER` cannot carry
+pub(crate) fn check_bank1_register(addr: u8, len: usize) -> Result<(), ConfigError> {
+    if addr > MAX_REGISTER {
+        return Err(ConfigError::Register(addr));
+    }
+    if len > MAX_REGISTER_LEN {
+        return Err(ConfigError::RegisterLength(len));
+    }
+    Ok(())
+}
+
+/// Switch Beken parts to register bank 0 or 1
+///
+/// `ACTIVATE` toggles, so the current bank is checked first. Other
+/// variants ignore this.
+pub(crate) fn select_bank<D: Device>(device: &mut D, bank1: bool) -> Result<(), D::Error> {
+    let (status, ()) = device.send_command(&Nop)?;
+    if status.rbank() != bank1 {
+        device.send_command(&Activate::Bank)?;
+    }
+    Ok(())
+}
+
+/// Run the bank 1 init sequence of Beken parts
+pub(crate) fn init_bank1<D: Device>(device: &mut D) -> Result<(), D::Error> {
+    select_bank(device, true)?;
+    for (addr, value) in bank1_init() {
+        device.send_command(&WriteRawRegister::new(addr, value))?;
+    }
+    select_bank(device, false)
+}
+
+/// Unlock `FEATURE` and `DYNPD`
+///
+/// `ACTIVATE` toggles, and a chip that kept power across an MCU
+/// reset may already be activated. So it is only sent if a write to
+/// `FEATURE` does not stick.
+pub(crate) fn activate<D: Device>(device: &mut D) -> Result<(), D::Error> {
+    let (_, feature) = device.read_register::<Feature>()?;
+    let probe = feature_probe(&feature);
+    device.write_register(Feature(probe.0))?;
+    let (_, probed) = device.read_register::<Feature>()?;
+
+    if probed == probe {
+        device.write_register(feature)?;
+    } else {
+        device.send_command(&Activate::Features)?;
+    }
+    Ok(())
+}
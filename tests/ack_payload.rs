@@ -0,0 +1,84 @@
+//! ACK payloads of `PrxMode`, blocking and async
+#![cfg(feature = "sim")]
+
+use embedded_hal::spi::SpiDevice;
+use embedded_nrf24l01::sim::SimRadio;
+use embedded_nrf24l01::{ConfigError, Error, Pipe, NRF24L01};
+
+const FLUSH_TX: u8 = 0b1110_0001;
+
+fn flush_tx(radio: &SimRadio) {
+    radio.spi().write(&[FLUSH_TX]).unwrap();
+}
+
+#[test]
+fn rejects_long_ack_payload() {
+    let radio = SimRadio::new();
+    let mut prx = NRF24L01::new(radio.ce(), radio.spi()).unwrap().prx().unwrap();
+    let pipe = Pipe::new(0).unwrap();
+    match prx.queue_ack_payload(pipe, &[0; 33]) {
+        Err(Error::Config(ConfigError::PayloadLength(33))) => {}
+        result => panic!("{:?}", result),
+    }
+    match prx.queue_ack_payload(pipe, &[0; 300]) {
+        Err(Error::Config(ConfigError::PayloadLength(255))) => {}
+        result => panic!("{:?}", result),
+    }
+    assert_eq!(radio.tx_fifo_len(), 0);
+    assert!(prx.queue_ack_payload(pipe, &[0; 32]).unwrap());
+    assert_eq!(radio.tx_fifo_len(), 1);
+}
+
+#[test]
+fn delivered_count_saturates() {
+    let radio = SimRadio::new();
+    let mut prx = NRF24L01::new(radio.ce(), radio.spi()).unwrap().prx().unwrap();
+    let pipe = Pipe::new(0).unwrap();
+    // A TX FIFO that has run empty counts the queued ACK payloads as
+    // delivered
+    for _ in 0..100 {
+        while prx.queue_ack_payload(pipe, b"ack").unwrap() {}
+        flush_tx(&radio);
+    }
+    prx.queue_ack_payload(pipe, b"ack").unwrap();
+    assert_eq!(prx.take_delivered(), [255, 0, 0, 0, 0, 0]);
+    assert_eq!(prx.take_delivered(), [0; 6]);
+}
+
+#[cfg(feature = "async")]
+mod asynch {
+    use super::*;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use embedded_nrf24l01::asynch;
+
+    /// The simulated chip never lets a future return `Pending`
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut context = Context::from_waker(Waker::noop());
+        match pin!(future).poll(&mut context) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("pending"),
+        }
+    }
+
+    #[test]
+    fn rejects_long_ack_payload() {
+        let radio = SimRadio::new();
+        let standby = block_on(asynch::NRF24L01::new(radio.ce(), radio.irq(), radio.spi())).unwrap();
+        let mut prx = block_on(standby.prx()).unwrap();
+        let pipe = Pipe::new(0).unwrap();
+        match block_on(prx.queue_ack_payload(pipe, &[0; 33])) {
+            Err(Error::Config(ConfigError::PayloadLength(33))) => {}
+            result => panic!("{:?}", result),
+        }
+        assert_eq!(radio.tx_fifo_len(), 0);
+
+        for _ in 0..100 {
+            while block_on(prx.queue_ack_payload(pipe, b"ack")).unwrap() {}
+            flush_tx(&radio);
+        }
+        block_on(prx.queue_ack_payload(pipe, b"ack")).unwrap();
+        assert_eq!(prx.take_delivered(), [255, 0, 0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,126 @@
+//! `BeaconMode` on a simulated chip
+#![cfg(feature = "sim")]
+
+use core::convert::Infallible;
+
+use embedded_hal::digital::{ErrorType, OutputPin};
+use embedded_nrf24l01::sim::SimRadio;
+use embedded_nrf24l01::{ConfigError, Error, NRF24L01};
+
+/// A **CE** pin that is not connected
+struct Unconnected;
+
+impl ErrorType for Unconnected {
+    type Error = Infallible;
+}
+
+impl OutputPin for Unconnected {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+#[test]
+fn rejects_long_payload() {
+    let radio = SimRadio::new();
+    let standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    match standby.beacon(&[0; 33]) {
+        Err((_, Error::Config(ConfigError::PayloadLength(33)))) => {}
+        Err((_, e)) => panic!("{:?}", e),
+        Ok(_) => panic!("accepted 33 bytes"),
+    }
+    assert_eq!(radio.tx_fifo_len(), 0);
+}
+
+#[test]
+fn repeat_sends_count_times() {
+    let radio = SimRadio::new();
+    let standby = NRF24L01::new_with_delay(radio.ce(), radio.spi(), radio.delay()).unwrap();
+    let mut beacon = standby.beacon(b"beacon").unwrap();
+    radio.advance(2000);
+    let start = radio.now();
+    beacon.repeat(3, 100).unwrap();
+    // Two intervals waited with the delay provider of the driver
+    assert!(radio.now() >= start + 200);
+    let frames = radio.take_transmitted();
+    assert_eq!(frames.len(), 3);
+    assert!(frames.iter().all(|frame| frame.payload == b"beacon" && frame.no_ack));
+    beacon.standby().unwrap();
+    assert_eq!(radio.tx_fifo_len(), 0);
+}
+
+#[test]
+fn repeat_without_delay() {
+    let radio = SimRadio::new();
+    let standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    let mut beacon = standby.beacon(b"beacon").unwrap();
+    radio.advance(2000);
+    // Polls until TX_DS, as there is nothing to measure a timeout with
+    beacon.repeat(3, 0).unwrap();
+    assert_eq!(radio.take_transmitted().len(), 3);
+    beacon.standby().unwrap();
+}
+
+#[test]
+fn repeat_times_out() {
+    let radio = SimRadio::new();
+    let standby = NRF24L01::new_with_delay(Unconnected, radio.spi(), radio.delay()).unwrap();
+    let mut beacon = standby.beacon(b"beacon").unwrap();
+    radio.advance(2000);
+    let start = radio.now();
+    match beacon.repeat(2, 0) {
+        Err(Error::Timeout) => {}
+        result => panic!("{:?}", result),
+    }
+    assert!(radio.now() >= start + 1500);
+    assert!(radio.take_transmitted().is_empty());
+}
+
+#[cfg(feature = "async")]
+mod asynch {
+    use super::*;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use embedded_nrf24l01::asynch;
+
+    /// The simulated chip never lets a future return `Pending`
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut context = Context::from_waker(Waker::noop());
+        match pin!(future).poll(&mut context) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("pending"),
+        }
+    }
+
+    #[test]
+    fn rejects_long_payload() {
+        let radio = SimRadio::new();
+        let standby = block_on(asynch::NRF24L01::new(radio.ce(), radio.irq(), radio.spi())).unwrap();
+        match block_on(standby.beacon(&[0; 40])) {
+            Err((_, Error::Config(ConfigError::PayloadLength(40)))) => {}
+            Err((_, e)) => panic!("{:?}", e),
+            Ok(_) => panic!("accepted 40 bytes"),
+        }
+        assert_eq!(radio.tx_fifo_len(), 0);
+    }
+
+    #[test]
+    fn repeat_sends_count_times() {
+        let radio = SimRadio::new();
+        let standby =
+            block_on(asynch::NRF24L01::new_with_delay(radio.ce(), radio.irq(), radio.spi(), radio.delay())).unwrap();
+        let mut beacon = block_on(standby.beacon(b"beacon")).unwrap();
+        radio.advance(2000);
+        let start = radio.now();
+        block_on(beacon.repeat(3, 100)).unwrap();
+        assert!(radio.now() >= start + 200);
+        assert_eq!(radio.take_transmitted().len(), 3);
+        block_on(beacon.standby()).unwrap();
+        assert_eq!(radio.tx_fifo_len(), 0);
+    }
+}
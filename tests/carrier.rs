@@ -0,0 +1,125 @@
+//! `CarrierTestMode` on a simulated chip
+#![cfg(feature = "sim")]
+
+use embedded_nrf24l01::sim::SimRadio;
+use embedded_nrf24l01::{Channel, ChipVariant, Configuration, Error, NRF24L01};
+
+const RF_CH: u8 = 0x05;
+const RF_SETUP: u8 = 0x06;
+const CONT_WAVE: u8 = 1 << 7;
+const PLL_LOCK: u8 = 1 << 4;
+
+fn channel(channel: u8) -> Channel {
+    Channel::new(channel).unwrap()
+}
+
+#[test]
+fn carrier_hop_standby() {
+    let radio = SimRadio::new();
+    let standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    let before = radio.register(RF_SETUP)[0];
+    radio.advance(2000);
+
+    // -6 dBm is RF_PWR 0b10
+    let mut carrier = standby.carrier_test(channel(40), -6).unwrap();
+    let rf_setup = radio.register(RF_SETUP)[0];
+    assert_eq!(rf_setup & (CONT_WAVE | PLL_LOCK), CONT_WAVE | PLL_LOCK);
+    assert_eq!(rf_setup & 0b110, 0b100);
+    assert_eq!(radio.register(RF_CH), [40]);
+    assert!(radio.ce_is_high());
+    assert_eq!(radio.carrier(), Some(40));
+
+    carrier.hop(channel(80)).unwrap();
+    assert!(radio.ce_is_high());
+    assert_eq!(radio.carrier(), Some(80));
+    assert_eq!(radio.register(RF_SETUP)[0], rf_setup);
+
+    carrier.standby().unwrap();
+    assert!(!radio.ce_is_high());
+    assert_eq!(radio.carrier(), None);
+    assert_eq!(radio.register(RF_SETUP), [before]);
+    // The last channel is kept
+    assert_eq!(radio.register(RF_CH), [80]);
+}
+
+#[test]
+fn carrier_power_of_variant() {
+    let radio = SimRadio::with_variant(ChipVariant::Si24r1);
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    standby.set_variant(ChipVariant::Si24r1).unwrap();
+    let carrier = standby.carrier_test(channel(2), 7).unwrap();
+    assert_eq!(radio.register(RF_SETUP)[0] & 0b111, 0b111);
+    let standby = carrier.standby().unwrap();
+
+    // Below the lowest level
+    let _carrier = standby.carrier_test(channel(2), -40).unwrap();
+    assert_eq!(radio.register(RF_SETUP)[0] & 0b111, 0);
+}
+
+#[test]
+fn carrier_unsupported() {
+    let radio = SimRadio::with_variant(ChipVariant::Nrf24l01);
+    let standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    let before = radio.register(RF_SETUP);
+    match standby.carrier_test(channel(40), 0) {
+        Err((_, Error::Unsupported)) => {}
+        Err((_, e)) => panic!("{:?}", e),
+        Ok(_) => panic!("carrier on the nRF24L01"),
+    }
+    assert_eq!(radio.register(RF_SETUP), before);
+    assert!(!radio.ce_is_high());
+}
+
+#[cfg(feature = "async")]
+mod asynch {
+    use super::*;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use embedded_nrf24l01::asynch;
+
+    /// The simulated chip never lets a future return `Pending`
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut context = Context::from_waker(Waker::noop());
+        match pin!(future).poll(&mut context) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("pending"),
+        }
+    }
+
+    #[test]
+    fn carrier_hop_standby() {
+        let radio = SimRadio::new();
+        let standby = block_on(asynch::NRF24L01::new(radio.ce(), radio.irq(), radio.spi())).unwrap();
+        let before = radio.register(RF_SETUP)[0];
+        radio.advance(2000);
+
+        let mut carrier = block_on(standby.carrier_test(channel(40), -6)).unwrap();
+        let rf_setup = radio.register(RF_SETUP)[0];
+        assert_eq!(rf_setup & (CONT_WAVE | PLL_LOCK), CONT_WAVE | PLL_LOCK);
+        assert_eq!(rf_setup & 0b110, 0b100);
+        assert_eq!(radio.carrier(), Some(40));
+
+        block_on(carrier.hop(channel(80))).unwrap();
+        assert_eq!(radio.carrier(), Some(80));
+
+        block_on(carrier.standby()).unwrap();
+        assert!(!radio.ce_is_high());
+        assert_eq!(radio.carrier(), None);
+        assert_eq!(radio.register(RF_SETUP), [before]);
+        assert_eq!(radio.register(RF_CH), [80]);
+    }
+
+    #[test]
+    fn carrier_unsupported() {
+        let radio = SimRadio::with_variant(ChipVariant::Bk2421);
+        let standby = block_on(asynch::NRF24L01::new(radio.ce(), radio.irq(), radio.spi())).unwrap();
+        let before = radio.register(RF_SETUP);
+        match block_on(standby.carrier_test(channel(40), 0)) {
+            Err((_, Error::Unsupported)) => {}
+            Err((_, e)) => panic!("{:?}", e),
+            Ok(_) => panic!("carrier on the BK2421"),
+        }
+        assert_eq!(radio.register(RF_SETUP), before);
+    }
+}
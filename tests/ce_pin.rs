@@ -0,0 +1,167 @@
+//! Errors of the **CE** pin through the mode transitions, blocking
+//! and async
+#![cfg(feature = "sim")]
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use embedded_hal::digital::{self, ErrorKind, ErrorType, OutputPin};
+use embedded_nrf24l01::sim::{SimCe, SimRadio};
+use embedded_nrf24l01::{Error, StandbyMode, NRF24L01};
+
+#[derive(Debug, PartialEq)]
+struct Failed;
+
+impl digital::Error for Failed {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// A **CE** pin that fails while `broken` is set
+struct Ce {
+    inner: SimCe,
+    broken: Rc<Cell<bool>>,
+}
+
+impl ErrorType for Ce {
+    type Error = Failed;
+}
+
+impl OutputPin for Ce {
+    fn set_low(&mut self) -> Result<(), Failed> {
+        if self.broken.get() {
+            return Err(Failed);
+        }
+        self.inner.set_low().map_err(|e| match e {})
+    }
+
+    fn set_high(&mut self) -> Result<(), Failed> {
+        if self.broken.get() {
+            return Err(Failed);
+        }
+        self.inner.set_high().map_err(|e| match e {})
+    }
+}
+
+fn ce(radio: &SimRadio) -> (Ce, Rc<Cell<bool>>) {
+    let broken = Rc::new(Cell::new(false));
+    (Ce { inner: radio.ce(), broken: broken.clone() }, broken)
+}
+
+fn check<T>(result: Result<T, Error<impl core::fmt::Debug, Failed>>) {
+    match result {
+        Err(Error::GpioError(Failed)) => {}
+        Err(e) => panic!("{:?}", e),
+        Ok(_) => panic!("CE pin error lost"),
+    }
+}
+
+#[test]
+fn transitions_report_gpio_error() {
+    let radio = SimRadio::new();
+    let (ce, broken) = ce(&radio);
+    let standby = NRF24L01::new(ce, radio.spi()).unwrap();
+
+    // CE is raised on entering RX
+    broken.set(true);
+    let device = match standby.rx() {
+        Err((device, e)) => {
+            check::<()>(Err(e));
+            device
+        }
+        Ok(_) => panic!("entered RX"),
+    };
+    broken.set(false);
+    let mut rx = StandbyMode::power_up(device).unwrap().rx().unwrap();
+    assert!(radio.ce_is_high());
+    assert!(rx.can_read().unwrap().is_none());
+
+    // CE is lowered on leaving RX
+    broken.set(true);
+    let device = match rx.standby() {
+        Err((device, e)) => {
+            check::<()>(Err(e));
+            device
+        }
+        Ok(_) => panic!("left RX"),
+    };
+    broken.set(false);
+    let standby = StandbyMode::power_up(device).unwrap();
+
+    // TX raises CE per packet, and lowers it on leaving
+    let mut tx = standby.tx().unwrap();
+    broken.set(true);
+    check(tx.send(b"packet"));
+    let device = match tx.standby() {
+        Err((device, e)) => {
+            check::<()>(Err(e));
+            device
+        }
+        Ok(_) => panic!("left TX"),
+    };
+    broken.set(false);
+    let standby = StandbyMode::power_up(device).unwrap();
+
+    let mut ptx = standby.ptx(2, 3).unwrap();
+    broken.set(true);
+    check(ptx.send_no_ack(b"packet"));
+    let device = match ptx.standby() {
+        Err((device, e)) => {
+            check::<()>(Err(e));
+            device
+        }
+        Ok(_) => panic!("left PTX"),
+    };
+
+    // PRX raises CE right away
+    let standby = StandbyMode::power_up(device).unwrap();
+    match standby.prx() {
+        Err((_, e)) => check::<()>(Err(e)),
+        Ok(_) => panic!("entered PRX"),
+    }
+}
+
+#[cfg(feature = "async")]
+mod asynch {
+    use super::*;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use embedded_nrf24l01::asynch;
+
+    /// The simulated chip never lets a future return `Pending`
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut context = Context::from_waker(Waker::noop());
+        match pin!(future).poll(&mut context) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("pending"),
+        }
+    }
+
+    #[test]
+    fn transitions_report_gpio_error() {
+        let radio = SimRadio::new();
+        let (ce, broken) = ce(&radio);
+        let standby = block_on(asynch::NRF24L01::new(ce, radio.irq(), radio.spi())).unwrap();
+
+        broken.set(true);
+        let device = match block_on(standby.rx()) {
+            Err((device, e)) => {
+                check::<()>(Err(e));
+                device
+            }
+            Ok(_) => panic!("entered RX"),
+        };
+        broken.set(false);
+        let standby = block_on(asynch::StandbyMode::power_up(device)).unwrap();
+
+        let mut tx = block_on(standby.tx()).unwrap();
+        broken.set(true);
+        check(block_on(tx.send(b"packet")));
+        match block_on(tx.standby()) {
+            Err((_, e)) => check::<()>(Err(e)),
+            Ok(_) => panic!("left TX"),
+        }
+    }
+}
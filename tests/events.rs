@@ -0,0 +1,138 @@
+//! `poll_events()` and the **IRQ** pin, blocking and async
+#![cfg(feature = "sim")]
+
+use embedded_hal::digital::{self, ErrorKind, ErrorType, InputPin};
+use embedded_hal::spi::SpiDevice;
+use embedded_nrf24l01::sim::{Frame, SimRadio};
+use embedded_nrf24l01::timing::NoDelay;
+use embedded_nrf24l01::{Configuration, Error, Event, NRF24L01};
+
+const R_RX_PAYLOAD: u8 = 0b0110_0001;
+
+/// Address of pipe 0 after reset
+const P0: [u8; 5] = [0xE7; 5];
+
+/// Error of the **IRQ** pin only, unlike the infallible **CE** pin
+#[derive(Debug, PartialEq)]
+struct Broken;
+
+impl digital::Error for Broken {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// An **IRQ** pin that cannot be read
+struct BrokenIrq;
+
+impl ErrorType for BrokenIrq {
+    type Error = Broken;
+}
+
+impl InputPin for BrokenIrq {
+    fn is_high(&mut self) -> Result<bool, Broken> {
+        Err(Broken)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Broken> {
+        Err(Broken)
+    }
+}
+
+/// Receive a packet on pipe 0, and read it without clearing `RX_DR`
+fn receive_and_drain(radio: &SimRadio) {
+    radio.advance(2000);
+    let mut frame = Frame::new(2, &P0, b"data");
+    frame.dynamic = true;
+    assert!(radio.deliver(&frame).is_some());
+    let mut buf = [R_RX_PAYLOAD, 0, 0, 0, 0];
+    radio.spi().transfer_in_place(&mut buf).unwrap();
+    assert_eq!(radio.rx_fifo_len(), 0);
+}
+
+#[test]
+fn irq_pin_error() {
+    let radio = SimRadio::new();
+    let mut standby = NRF24L01::new_with_irq(radio.ce(), BrokenIrq, radio.spi(), NoDelay).unwrap();
+    match standby.poll_events() {
+        Err(Error::IrqError(Broken)) => {}
+        result => panic!("{:?}", result),
+    }
+}
+
+#[test]
+fn reports_drained_rx() {
+    let radio = SimRadio::new();
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    standby.set_pipes_rx_lengths(&[None; 6]).unwrap();
+    let mut rx = standby.rx().unwrap();
+    receive_and_drain(&radio);
+    let events = rx.poll_events().unwrap();
+    assert!(events.rx_drained());
+    assert_eq!(events.rx_ready(), None);
+    assert_eq!(events.iter().collect::<Vec<_>>(), [Event::RxDrained]);
+    assert!(rx.poll_events().unwrap().is_empty());
+}
+
+#[cfg(feature = "async")]
+mod asynch {
+    use super::*;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use embedded_hal_async::digital::Wait;
+    use embedded_nrf24l01::asynch::{self, Configuration as _};
+
+    /// The simulated chip never lets a future return `Pending`
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut context = Context::from_waker(Waker::noop());
+        match pin!(future).poll(&mut context) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("pending"),
+        }
+    }
+
+    impl Wait for BrokenIrq {
+        async fn wait_for_high(&mut self) -> Result<(), Broken> {
+            Err(Broken)
+        }
+
+        async fn wait_for_low(&mut self) -> Result<(), Broken> {
+            Err(Broken)
+        }
+
+        async fn wait_for_rising_edge(&mut self) -> Result<(), Broken> {
+            Err(Broken)
+        }
+
+        async fn wait_for_falling_edge(&mut self) -> Result<(), Broken> {
+            Err(Broken)
+        }
+
+        async fn wait_for_any_edge(&mut self) -> Result<(), Broken> {
+            Err(Broken)
+        }
+    }
+
+    #[test]
+    fn irq_pin_error() {
+        let radio = SimRadio::new();
+        let mut standby = block_on(asynch::NRF24L01::new(radio.ce(), BrokenIrq, radio.spi())).unwrap();
+        match block_on(standby.wait_events()) {
+            Err(Error::IrqError(Broken)) => {}
+            result => panic!("{:?}", result),
+        }
+    }
+
+    #[test]
+    fn reports_drained_rx() {
+        let radio = SimRadio::new();
+        let mut standby = block_on(asynch::NRF24L01::new(radio.ce(), radio.irq(), radio.spi())).unwrap();
+        block_on(standby.set_pipes_rx_lengths(&[None; 6])).unwrap();
+        let mut rx = block_on(standby.rx()).unwrap();
+        receive_and_drain(&radio);
+        let events = block_on(rx.wait_events()).unwrap();
+        assert!(events.contains(Event::RxDrained));
+        assert_eq!(events.rx_ready(), None);
+    }
+}
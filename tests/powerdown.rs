@@ -0,0 +1,56 @@
+//! `PowerDownMode` on a simulated chip, blocking and async
+#![cfg(feature = "sim")]
+
+use embedded_nrf24l01::sim::SimRadio;
+use embedded_nrf24l01::timing::POWER_UP_US;
+use embedded_nrf24l01::NRF24L01;
+
+const CONFIG: u8 = 0x00;
+const PWR_UP: u8 = 0b10;
+
+#[test]
+fn power_up_waits_with_driver_delay() {
+    let radio = SimRadio::new();
+    let standby = NRF24L01::new_with_delay(radio.ce(), radio.spi(), radio.delay()).unwrap();
+    let pd = standby.power_down().unwrap();
+    assert_eq!(radio.register(CONFIG)[0] & PWR_UP, 0);
+    let start = radio.now();
+    pd.power_up().unwrap();
+    assert_eq!(radio.register(CONFIG)[0] & PWR_UP, PWR_UP);
+    assert!(radio.now() >= start + u64::from(POWER_UP_US));
+}
+
+#[cfg(feature = "async")]
+mod asynch {
+    use super::*;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use embedded_nrf24l01::asynch;
+
+    /// The simulated chip never lets a future return `Pending`
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut context = Context::from_waker(Waker::noop());
+        match pin!(future).poll(&mut context) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("pending"),
+        }
+    }
+
+    #[test]
+    fn power_up_waits_with_driver_delay() {
+        let radio = SimRadio::new();
+        let pd = block_on(asynch::NRF24L01::new_powered_down_with_delay(
+            radio.ce(),
+            radio.irq(),
+            radio.spi(),
+            radio.delay(),
+        ))
+        .unwrap();
+        assert_eq!(radio.register(CONFIG)[0] & PWR_UP, 0);
+        let start = radio.now();
+        block_on(pd.power_up()).unwrap();
+        assert_eq!(radio.register(CONFIG)[0] & PWR_UP, PWR_UP);
+        assert!(radio.now() >= start + u64::from(POWER_UP_US));
+    }
+}
@@ -0,0 +1,340 @@
+//! `PtxMode`, and `get_rf()`, on a simulated chip
+#![cfg(feature = "sim")]
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use embedded_hal::spi::{self, ErrorKind, ErrorType, Operation, SpiDevice};
+use embedded_nrf24l01::sim::{Ether, SimRadio, SimSpi};
+use embedded_nrf24l01::{ConfigError, Configuration, DataRate, Error, Outcome, Pipe, PowerLevel, NRF24L01};
+
+/// `EN_AA`, `SETUP_RETR`, `FEATURE` and `DYNPD`
+const PTX_REGISTERS: [u8; 4] = [0x01, 0x04, 0x1D, 0x1C];
+
+fn ptx_registers(radio: &SimRadio) -> Vec<Vec<u8>> {
+    PTX_REGISTERS.iter().map(|addr| radio.register(*addr)).collect()
+}
+
+#[derive(Debug, PartialEq)]
+struct Failed;
+
+impl spi::Error for Failed {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// Fails the transaction number `fail_at` only
+struct Spi {
+    inner: SimSpi,
+    count: Rc<Cell<usize>>,
+    fail_at: Rc<Cell<usize>>,
+}
+
+impl Spi {
+    fn run(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Failed> {
+        let count = self.count.get();
+        self.count.set(count + 1);
+        if count == self.fail_at.get() {
+            return Err(Failed);
+        }
+        self.inner.transaction(operations).map_err(|e| match e {})
+    }
+}
+
+impl ErrorType for Spi {
+    type Error = Failed;
+}
+
+impl SpiDevice<u8> for Spi {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Failed> {
+        self.run(operations)
+    }
+}
+
+fn spi(radio: &SimRadio) -> Spi {
+    Spi {
+        inner: radio.spi(),
+        count: Rc::new(Cell::new(0)),
+        fail_at: Rc::new(Cell::new(usize::MAX)),
+    }
+}
+
+#[test]
+fn rejects_auto_retransmit_above_15() {
+    let radio = SimRadio::new();
+    let before = ptx_registers(&radio);
+    for (delay, retries) in [(16, 3), (0, 16), (255, 255)] {
+        let standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+        match standby.ptx(delay, retries) {
+            Err((_, Error::Config(ConfigError::AutoRetransmit(d, r)))) => assert_eq!((d, r), (delay, retries)),
+            Err((_, e)) => panic!("{:?}", e),
+            Ok(_) => panic!("accepted {} {}", delay, retries),
+        }
+        assert_eq!(ptx_registers(&radio), before);
+    }
+    let ptx = NRF24L01::new(radio.ce(), radio.spi()).unwrap().ptx(15, 15).unwrap();
+    assert_ne!(ptx_registers(&radio), before);
+    ptx.standby_and_restore().unwrap();
+    assert_eq!(ptx_registers(&radio), before);
+}
+
+#[test]
+fn restores_registers_on_failure() {
+    // Fail each of the writes in turn
+    for step in 4..9 {
+        let radio = SimRadio::new();
+        let spi = spi(&radio);
+        let (count, fail_at) = (spi.count.clone(), spi.fail_at.clone());
+        let standby = NRF24L01::new(radio.ce(), spi).unwrap();
+        let before = ptx_registers(&radio);
+        fail_at.set(count.get() + step);
+        match standby.ptx(2, 5) {
+            Err((_, Error::SpiError(Failed))) => {}
+            Err((_, e)) => panic!("{:?}", e),
+            Ok(_) => panic!("step {} did not fail", step),
+        }
+        assert_eq!(ptx_registers(&radio), before, "step {}", step);
+    }
+}
+
+#[test]
+fn get_rf_round_trips() {
+    let radio = SimRadio::new();
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    for (rate, level) in [(DataRate::R1Mbps, 1), (DataRate::R250Kbps, 0), (DataRate::R2Mbps, 3)] {
+        let level = PowerLevel::new(level).unwrap();
+        standby.set_rf(&rate, level).unwrap();
+        assert_eq!(standby.get_rf().unwrap(), (rate, level));
+    }
+}
+
+#[test]
+fn send_methods_do_not_mix() {
+    let radio = SimRadio::new();
+    let mut ptx = NRF24L01::new(radio.ce(), radio.spi()).unwrap().ptx(1, 1).unwrap();
+    radio.advance(2000);
+
+    let id = ptx.send(b"tracked").unwrap().unwrap();
+    match ptx.send_receive(Some(b"untracked")) {
+        Err(Error::Busy) => {}
+        result => panic!("{:?}", result.map(|r| r.sent)),
+    }
+    match ptx.send_no_ack(b"untracked") {
+        Err(Error::Busy) => {}
+        result => panic!("{:?}", result),
+    }
+    // Nobody acknowledges
+    let outcome = loop {
+        match ptx.poll_outcome() {
+            Ok(outcome) => break outcome,
+            Err(nb::Error::WouldBlock) => radio.advance(100),
+            Err(nb::Error::Other(e)) => panic!("{:?}", e),
+        }
+    };
+    assert!(matches!(outcome, Outcome::Dropped { id: dropped } if dropped == id));
+
+    assert!(ptx.send_no_ack(b"untracked").unwrap());
+    match ptx.send(b"tracked") {
+        Err(Error::Busy) => {}
+        result => panic!("{:?}", result),
+    }
+    radio.advance(2000);
+    assert_eq!(radio.tx_fifo_len(), 0);
+    // TX_DS of the packet without ACK is not taken for this one
+    ptx.send(b"tracked").unwrap().unwrap();
+    assert!(matches!(ptx.poll_outcome(), Err(nb::Error::WouldBlock)));
+    let frames = radio.take_transmitted();
+    assert_eq!(frames.iter().filter(|frame| frame.payload == b"untracked").count(), 1);
+}
+
+#[test]
+fn rejects_long_payload() {
+    let radio = SimRadio::new();
+    let mut ptx = NRF24L01::new(radio.ce(), radio.spi()).unwrap().ptx(1, 1).unwrap();
+    match ptx.send_receive(Some(&[0; 33])) {
+        Err(Error::Config(ConfigError::PayloadLength(33))) => {}
+        result => panic!("{:?}", result.map(|r| r.sent)),
+    }
+    match ptx.send_no_ack(&[0; 40]) {
+        Err(Error::Config(ConfigError::PayloadLength(40))) => {}
+        result => panic!("{:?}", result),
+    }
+    match ptx.send(&[0; 33]) {
+        Err(Error::Config(ConfigError::PayloadLength(33))) => {}
+        result => panic!("{:?}", result),
+    }
+    assert_eq!(radio.tx_fifo_len(), 0);
+    assert_eq!(ptx.pending(), 0);
+}
+
+#[test]
+fn send_after_send_receive() {
+    let ether = Ether::new();
+    let (a, b) = (ether.radio(), ether.radio());
+    let mut prx = NRF24L01::new(a.ce(), a.spi()).unwrap().prx().unwrap();
+    let pipe = Pipe::new(0).unwrap();
+    let mut ptx = NRF24L01::new(b.ce(), b.spi()).unwrap().ptx(2, 3).unwrap();
+    ether.advance(2000);
+
+    prx.queue_ack_payload(pipe, b"ack1").unwrap();
+    assert!(ptx.send_receive(Some(b"untracked")).unwrap().sent);
+    ether.advance(1000);
+    assert_eq!(b.rx_fifo_len(), 1);
+    // The ACK payload is for send_receive() to read
+    match ptx.send(b"tracked") {
+        Err(Error::Busy) => {}
+        result => panic!("{:?}", result),
+    }
+    let received = ptx.send_receive(None).unwrap().received.unwrap();
+    assert_eq!(received.payload.as_ref(), b"ack1");
+
+    prx.queue_ack_payload(pipe, b"ack2").unwrap();
+    let id = ptx.send(b"tracked").unwrap().unwrap();
+    let outcome = loop {
+        match ptx.poll_outcome() {
+            Ok(outcome) => break outcome,
+            Err(nb::Error::WouldBlock) => ether.advance(100),
+            Err(nb::Error::Other(e)) => panic!("{:?}", e),
+        }
+    };
+    match outcome {
+        Outcome::Delivered { id: delivered, ack_payload: Some(payload), .. } => {
+            assert_eq!(delivered, id);
+            assert_eq!(payload.as_ref(), b"ack2");
+        }
+        outcome => panic!("{:?}", outcome),
+    }
+}
+
+#[cfg(feature = "async")]
+mod asynch {
+    use super::*;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use embedded_nrf24l01::asynch::{self, Configuration as _};
+
+    /// The simulated chip never lets a future return `Pending`
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut context = Context::from_waker(Waker::noop());
+        match pin!(future).poll(&mut context) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("pending"),
+        }
+    }
+
+    impl embedded_hal_async::spi::SpiDevice<u8> for Spi {
+        async fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Failed> {
+            self.run(operations)
+        }
+    }
+
+    #[test]
+    fn rejects_auto_retransmit_above_15() {
+        let radio = SimRadio::new();
+        let standby = block_on(asynch::NRF24L01::new(radio.ce(), radio.irq(), radio.spi())).unwrap();
+        let before = ptx_registers(&radio);
+        match block_on(standby.ptx(3, 16)) {
+            Err((_, Error::Config(ConfigError::AutoRetransmit(3, 16)))) => {}
+            Err((_, e)) => panic!("{:?}", e),
+            Ok(_) => panic!("accepted 16 retries"),
+        }
+        assert_eq!(ptx_registers(&radio), before);
+    }
+
+    #[test]
+    fn restores_registers_on_failure() {
+        for step in 4..9 {
+            let radio = SimRadio::new();
+            let spi = spi(&radio);
+            let (count, fail_at) = (spi.count.clone(), spi.fail_at.clone());
+            let standby = block_on(asynch::NRF24L01::new(radio.ce(), radio.irq(), spi)).unwrap();
+            let before = ptx_registers(&radio);
+            fail_at.set(count.get() + step);
+            match block_on(standby.ptx(2, 5)) {
+                Err((_, Error::SpiError(Failed))) => {}
+                Err((_, e)) => panic!("{:?}", e),
+                Ok(_) => panic!("step {} did not fail", step),
+            }
+            assert_eq!(ptx_registers(&radio), before, "step {}", step);
+        }
+    }
+
+    #[test]
+    fn send_methods_do_not_mix() {
+        let radio = SimRadio::new();
+        let standby = block_on(asynch::NRF24L01::new(radio.ce(), radio.irq(), radio.spi())).unwrap();
+        let mut ptx = block_on(standby.ptx(1, 1)).unwrap();
+        radio.advance(2000);
+
+        let id = block_on(ptx.send(b"tracked")).unwrap().unwrap();
+        assert!(matches!(block_on(ptx.send_receive(Some(b"untracked"))), Err(Error::Busy)));
+        assert!(matches!(block_on(ptx.send_no_ack(b"untracked")), Err(Error::Busy)));
+        match block_on(ptx.wait_outcome()).unwrap() {
+            Some(Outcome::Dropped { id: dropped }) => assert_eq!(dropped, id),
+            outcome => panic!("{:?}", outcome),
+        }
+
+        assert!(block_on(ptx.send_no_ack(b"untracked")).unwrap());
+        assert!(matches!(block_on(ptx.send(b"tracked")), Err(Error::Busy)));
+        radio.advance(2000);
+        block_on(ptx.send(b"tracked")).unwrap().unwrap();
+        assert!(block_on(ptx.poll_outcome()).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_long_payload() {
+        let radio = SimRadio::new();
+        let standby = block_on(asynch::NRF24L01::new(radio.ce(), radio.irq(), radio.spi())).unwrap();
+        let mut ptx = block_on(standby.ptx(1, 1)).unwrap();
+        assert!(matches!(
+            block_on(ptx.send_receive(Some(&[0; 33]))),
+            Err(Error::Config(ConfigError::PayloadLength(33)))
+        ));
+        assert!(matches!(
+            block_on(ptx.send_no_ack(&[0; 40])),
+            Err(Error::Config(ConfigError::PayloadLength(40)))
+        ));
+        assert!(matches!(block_on(ptx.send(&[0; 33])), Err(Error::Config(ConfigError::PayloadLength(33)))));
+        assert_eq!(radio.tx_fifo_len(), 0);
+        assert_eq!(ptx.pending(), 0);
+    }
+
+    #[test]
+    fn send_after_send_receive() {
+        let ether = Ether::new();
+        let (a, b) = (ether.radio(), ether.radio());
+        let mut prx = NRF24L01::new(a.ce(), a.spi()).unwrap().prx().unwrap();
+        let pipe = Pipe::new(0).unwrap();
+        let standby = block_on(asynch::NRF24L01::new(b.ce(), b.irq(), b.spi())).unwrap();
+        let mut ptx = block_on(standby.ptx(2, 3)).unwrap();
+        ether.advance(2000);
+
+        prx.queue_ack_payload(pipe, b"ack1").unwrap();
+        assert!(block_on(ptx.send_receive(Some(b"untracked"))).unwrap().sent);
+        ether.advance(1000);
+        assert!(matches!(block_on(ptx.send(b"tracked")), Err(Error::Busy)));
+        let received = block_on(ptx.send_receive(None)).unwrap().received.unwrap();
+        assert_eq!(received.payload.as_ref(), b"ack1");
+
+        prx.queue_ack_payload(pipe, b"ack2").unwrap();
+        let id = block_on(ptx.send(b"tracked")).unwrap().unwrap();
+        match block_on(ptx.wait_outcome()).unwrap() {
+            Some(Outcome::Delivered { id: delivered, ack_payload: Some(payload), .. }) => {
+                assert_eq!(delivered, id);
+                assert_eq!(payload.as_ref(), b"ack2");
+            }
+            outcome => panic!("{:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn get_rf_round_trips() {
+        let radio = SimRadio::new();
+        let mut standby = block_on(asynch::NRF24L01::new(radio.ce(), radio.irq(), radio.spi())).unwrap();
+        let level = PowerLevel::new(2).unwrap();
+        block_on(standby.set_rf(&DataRate::R1Mbps, level)).unwrap();
+        assert_eq!(block_on(standby.get_rf()).unwrap(), (DataRate::R1Mbps, level));
+    }
+}
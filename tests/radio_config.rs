@@ -0,0 +1,114 @@
+//! `RadioConfig` built, applied and read back on a simulated chip
+#![cfg(feature = "sim")]
+
+use embedded_nrf24l01::sim::SimRadio;
+use embedded_nrf24l01::{
+    Address, Channel, ConfigError, Configuration, CrcMode, DataRate, Pipe, PipeConfig, RadioConfig,
+    RadioConfigBuilder, NRF24L01,
+};
+
+fn pipe(index: usize) -> Pipe {
+    Pipe::new(index).unwrap()
+}
+
+fn addr(bytes: &[u8]) -> Address {
+    Address::new(bytes).unwrap()
+}
+
+fn builder() -> RadioConfigBuilder {
+    RadioConfig::builder()
+        .channel(Channel::new(76).unwrap())
+        .data_rate(DataRate::R1Mbps)
+        .crc(CrcMode::TwoBytes)
+        .address_width(4)
+        .auto_retransmit(5, 10)
+        .tx_addr(&addr(b"abcd"))
+        .rx_addr(pipe(0), &addr(b"abcd"))
+        .rx_addr(pipe(1), &addr(b"1xyz"))
+        .rx_addr(pipe(2), &addr(b"2xyz"))
+        .rx_addr_suffix(pipe(3), b'3')
+        .dynamic_payloads(true)
+        .all_pipes(PipeConfig { enabled: true, auto_ack: true, length: None })
+        .pipe(pipe(5), PipeConfig { enabled: false, auto_ack: true, length: Some(8) })
+}
+
+#[test]
+fn typed_builder() {
+    let config = builder().build().unwrap();
+    assert_eq!(config.channel(), 76);
+    assert_eq!(config.tx_addr(), addr(b"abcd"));
+    assert_eq!(config.rx_addr(pipe(0)), addr(b"abcd"));
+    assert_eq!(config.rx_addr(pipe(1)), addr(b"1xyz"));
+    assert_eq!(config.rx_addr(pipe(2)), addr(b"2xyz"));
+    assert_eq!(config.rx_addr(pipe(3)), addr(b"3xyz"));
+    assert_eq!(config.rx_addr(pipe(4)), addr(&[0xC5, b'x', b'y', b'z']));
+    assert_eq!(config.pipes()[5].length, Some(8));
+
+    // The getters feed the builder
+    let copy = builder().tx_addr(&config.tx_addr()).rx_addr(pipe(4), &config.rx_addr(pipe(4)));
+    assert_eq!(copy.build().unwrap(), config);
+}
+
+#[test]
+fn builder_errors() {
+    // Pipes 2 to 5 share the upper bytes of pipe 1
+    match builder().rx_addr(pipe(4), &addr(b"4abc")).build() {
+        Err(ConfigError::AddressPrefix(4)) => {}
+        result => panic!("{:?}", result),
+    }
+    // Checked against the final address of pipe 1
+    let config = builder().rx_addr(pipe(4), &addr(b"4abc")).rx_addr(pipe(1), &addr(b"1abc"));
+    match config.build() {
+        Err(ConfigError::AddressPrefix(2)) => {}
+        result => panic!("{:?}", result),
+    }
+    // A suffix replaces the full address
+    let config = builder().rx_addr(pipe(4), &addr(b"4abc")).rx_addr_suffix(pipe(4), b'4');
+    assert_eq!(config.build().unwrap().rx_addr(pipe(4)), addr(b"4xyz"));
+
+    match builder().rx_addr_suffix(pipe(1), 0).build() {
+        Err(ConfigError::Pipe(1)) => {}
+        result => panic!("{:?}", result),
+    }
+    match builder().address_width(5).build() {
+        Err(ConfigError::AddressLength(4)) => {}
+        result => panic!("{:?}", result),
+    }
+}
+
+#[test]
+fn apply_and_read_back() {
+    let config = builder().build().unwrap();
+    let radio = SimRadio::new();
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    standby.apply_config(&config).unwrap();
+    assert_eq!(standby.get_config().unwrap(), config);
+    assert_eq!(standby.get_rx_addr(pipe(2)).unwrap(), config.rx_addr(pipe(2)));
+}
+
+#[cfg(feature = "async")]
+mod asynch {
+    use super::*;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use embedded_nrf24l01::asynch::{self, Configuration as _};
+
+    /// The simulated chip never lets a future return `Pending`
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut context = Context::from_waker(Waker::noop());
+        match pin!(future).poll(&mut context) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("pending"),
+        }
+    }
+
+    #[test]
+    fn apply_and_read_back() {
+        let config = builder().build().unwrap();
+        let radio = SimRadio::new();
+        let mut standby = block_on(asynch::NRF24L01::new(radio.ce(), radio.irq(), radio.spi())).unwrap();
+        block_on(standby.apply_config(&config)).unwrap();
+        assert_eq!(block_on(standby.get_config()).unwrap(), config);
+    }
+}
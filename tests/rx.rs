@@ -0,0 +1,212 @@
+//! Payload reads of `RxMode`, `PrxMode` and `PtxMode` on simulated
+//! chips, blocking and async
+#![cfg(feature = "sim")]
+
+use core::convert::Infallible;
+use std::cell::Cell;
+use std::rc::Rc;
+
+use embedded_hal::spi::{ErrorType, Operation, SpiDevice};
+use embedded_nrf24l01::sim::{Ether, Frame, SimRadio, SimSpi};
+use embedded_nrf24l01::{Configuration, Error, Outcome, Pipe, NRF24L01};
+
+const R_RX_PL_WID: u8 = 0b0110_0000;
+
+/// Address of pipe 0 after reset
+const P0: [u8; 5] = [0xE7; 5];
+
+/// A frame with a dynamic payload length on the reset channel
+fn frame(payload: &[u8]) -> Frame {
+    let mut frame = Frame::new(2, &P0, payload);
+    frame.dynamic = true;
+    frame
+}
+
+/// Reports a payload width of 40 bytes for the next `R_RX_PL_WID`
+/// while `armed`
+struct CorruptWidth {
+    inner: SimSpi,
+    armed: Rc<Cell<bool>>,
+}
+
+impl CorruptWidth {
+    fn new(inner: SimSpi) -> Self {
+        CorruptWidth { inner, armed: Rc::new(Cell::new(true)) }
+    }
+
+    fn run(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Infallible> {
+        let width = matches!(operations, [Operation::TransferInPlace(buf)] if buf[0] == R_RX_PL_WID);
+        self.inner.transaction(operations)?;
+        if let (true, true, [Operation::TransferInPlace(buf)]) = (width, self.armed.get(), operations) {
+            buf[1] = 40;
+            self.armed.set(false);
+        }
+        Ok(())
+    }
+}
+
+impl ErrorType for CorruptWidth {
+    type Error = Infallible;
+}
+
+impl SpiDevice<u8> for CorruptWidth {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Infallible> {
+        self.run(operations)
+    }
+}
+
+#[test]
+fn read_static_pipe() {
+    let radio = SimRadio::new();
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    standby.set_pipes_rx_lengths(&[Some(4); 6]).unwrap();
+    let mut rx = standby.rx().unwrap();
+    radio.advance(2000);
+    assert!(radio.deliver(&Frame::new(2, &P0, b"abcd")).is_some());
+    assert_eq!(rx.read().unwrap().as_ref(), b"abcd");
+    assert_eq!(radio.rx_fifo_len(), 0);
+}
+
+#[test]
+fn corrupt_width_flushes_rx_fifo() {
+    let radio = SimRadio::new();
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    standby.set_pipes_rx_lengths(&[None; 6]).unwrap();
+    let mut rx = standby.rx().unwrap();
+    radio.advance(2000);
+    radio.deliver(&frame(&[0; 40]));
+    radio.deliver(&frame(b"next"));
+    match rx.read() {
+        Err(Error::CorruptPayload(40)) => {}
+        result => panic!("{:?}", result.map(|p| p.len())),
+    }
+    assert_eq!(radio.rx_fifo_len(), 0);
+
+    let mut prx = rx.standby().unwrap().prx().unwrap();
+    radio.advance(2000);
+    radio.deliver(&frame(&[0; 40]));
+    match prx.read() {
+        Err(Error::CorruptPayload(40)) => {}
+        result => panic!("{:?}", result.map(|r| r.map(|r| r.pipe))),
+    }
+    assert_eq!(radio.rx_fifo_len(), 0);
+}
+
+#[test]
+fn corrupt_ack_payload_width() {
+    let ether = Ether::new();
+    let (a, b) = (ether.radio(), ether.radio());
+    let mut prx = NRF24L01::new(a.ce(), a.spi()).unwrap().prx().unwrap();
+    let pipe = Pipe::new(0).unwrap();
+    let spi = CorruptWidth::new(b.spi());
+    let armed = spi.armed.clone();
+    let mut ptx = NRF24L01::new(b.ce(), spi).unwrap().ptx(2, 3).unwrap();
+    ether.advance(2000);
+
+    prx.queue_ack_payload(pipe, b"reply").unwrap();
+    ptx.send(b"tracked").unwrap();
+    ether.advance(1000);
+    match ptx.poll_outcome() {
+        Err(nb::Error::Other(Error::CorruptPayload(40))) => {}
+        Err(e) => panic!("{:?}", e),
+        Ok(_) => panic!("read the ACK payload"),
+    }
+    assert_eq!(b.rx_fifo_len(), 0);
+    match ptx.poll_outcome() {
+        Ok(Outcome::Delivered { ack_payload: None, .. }) => {}
+        result => panic!("{:?}", result),
+    }
+
+    armed.set(true);
+    prx.queue_ack_payload(pipe, b"reply").unwrap();
+    assert!(ptx.send_receive(Some(b"untracked")).unwrap().sent);
+    ether.advance(1000);
+    match ptx.send_receive(None) {
+        Err(Error::CorruptPayload(40)) => {}
+        result => panic!("{:?}", result.map(|r| r.sent)),
+    }
+    assert_eq!(b.rx_fifo_len(), 0);
+}
+
+#[cfg(feature = "async")]
+mod asynch {
+    use super::*;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use embedded_nrf24l01::asynch::{self, Configuration as _};
+
+    /// The simulated chip never lets a future return `Pending`
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut context = Context::from_waker(Waker::noop());
+        match pin!(future).poll(&mut context) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("pending"),
+        }
+    }
+
+    impl embedded_hal_async::spi::SpiDevice<u8> for CorruptWidth {
+        async fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Infallible> {
+            self.run(operations)
+        }
+    }
+
+    #[test]
+    fn read_static_pipe() {
+        let radio = SimRadio::new();
+        let mut standby = block_on(asynch::NRF24L01::new(radio.ce(), radio.irq(), radio.spi())).unwrap();
+        block_on(standby.set_pipes_rx_lengths(&[Some(4); 6])).unwrap();
+        let mut rx = block_on(standby.rx()).unwrap();
+        radio.advance(2000);
+        radio.deliver(&Frame::new(2, &P0, b"abcd"));
+        assert_eq!(block_on(rx.read()).unwrap().as_ref(), b"abcd");
+    }
+
+    #[test]
+    fn corrupt_width_flushes_rx_fifo() {
+        let radio = SimRadio::new();
+        let mut standby = block_on(asynch::NRF24L01::new(radio.ce(), radio.irq(), radio.spi())).unwrap();
+        block_on(standby.set_pipes_rx_lengths(&[None; 6])).unwrap();
+        let mut rx = block_on(standby.rx()).unwrap();
+        radio.advance(2000);
+        radio.deliver(&frame(&[0; 40]));
+        radio.deliver(&frame(b"next"));
+        assert!(matches!(block_on(rx.read()), Err(Error::CorruptPayload(40))));
+        assert_eq!(radio.rx_fifo_len(), 0);
+
+        let mut prx = block_on(rx.standby().unwrap().prx()).unwrap();
+        radio.advance(2000);
+        radio.deliver(&frame(&[0; 40]));
+        assert!(matches!(block_on(prx.read()), Err(Error::CorruptPayload(40))));
+        assert_eq!(radio.rx_fifo_len(), 0);
+    }
+
+    #[test]
+    fn corrupt_ack_payload_width() {
+        let ether = Ether::new();
+        let (a, b) = (ether.radio(), ether.radio());
+        let mut prx = NRF24L01::new(a.ce(), a.spi()).unwrap().prx().unwrap();
+        let pipe = Pipe::new(0).unwrap();
+        let spi = CorruptWidth::new(b.spi());
+        let armed = spi.armed.clone();
+        let standby = block_on(asynch::NRF24L01::new(b.ce(), b.irq(), spi)).unwrap();
+        let mut ptx = block_on(standby.ptx(2, 3)).unwrap();
+        ether.advance(2000);
+
+        prx.queue_ack_payload(pipe, b"reply").unwrap();
+        block_on(ptx.send(b"tracked")).unwrap();
+        assert!(matches!(block_on(ptx.wait_outcome()), Err(Error::CorruptPayload(40))));
+        assert_eq!(b.rx_fifo_len(), 0);
+        match block_on(ptx.wait_outcome()).unwrap() {
+            Some(Outcome::Delivered { ack_payload: None, .. }) => {}
+            outcome => panic!("{:?}", outcome),
+        }
+
+        armed.set(true);
+        prx.queue_ack_payload(pipe, b"reply").unwrap();
+        assert!(block_on(ptx.send_receive(Some(b"untracked"))).unwrap().sent);
+        ether.advance(1000);
+        assert!(matches!(block_on(ptx.send_receive(None)), Err(Error::CorruptPayload(40))));
+        assert_eq!(b.rx_fifo_len(), 0);
+    }
+}
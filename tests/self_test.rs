@@ -0,0 +1,95 @@
+//! `StandbyMode::self_test()` on a simulated chip
+#![cfg(feature = "sim")]
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use embedded_hal::spi::{self, ErrorKind, ErrorType, Operation, SpiDevice};
+use embedded_nrf24l01::sim::{SimRadio, SimSpi};
+use embedded_nrf24l01::{Diagnosis, Error, NRF24L01};
+
+const RF_CH: u8 = 0x05;
+
+/// Failure of the `n`-th transaction
+#[derive(Debug, PartialEq)]
+struct Failed(usize);
+
+impl spi::Error for Failed {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// Fails every transaction from `fail_at` on, and records the
+/// channels written to `RF_CH`
+struct Spi {
+    inner: SimSpi,
+    count: Rc<Cell<usize>>,
+    fail_at: Rc<Cell<usize>>,
+    channels: Rc<RefCell<Vec<u8>>>,
+}
+
+impl ErrorType for Spi {
+    type Error = Failed;
+}
+
+impl SpiDevice<u8> for Spi {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Failed> {
+        let count = self.count.get();
+        self.count.set(count + 1);
+        if count >= self.fail_at.get() {
+            return Err(Failed(count));
+        }
+        if let [Operation::TransferInPlace(buf)] = operations {
+            if buf.len() == 2 && buf[0] == 0x20 | RF_CH {
+                self.channels.borrow_mut().push(buf[1]);
+            }
+        }
+        self.inner.transaction(operations).map_err(|e| match e {})
+    }
+}
+
+fn spi(radio: &SimRadio) -> Spi {
+    Spi {
+        inner: radio.spi(),
+        count: Rc::new(Cell::new(0)),
+        fail_at: Rc::new(Cell::new(usize::MAX)),
+        channels: Rc::new(RefCell::new(Vec::new())),
+    }
+}
+
+#[test]
+fn passes_and_restores() {
+    let radio = SimRadio::new();
+    let spi = spi(&radio);
+    let channels = spi.channels.clone();
+    let mut standby = NRF24L01::new(radio.ce(), spi).unwrap();
+    let before = (radio.register(0x10), radio.register(0x0A), radio.register(RF_CH));
+
+    let report = standby.self_test().unwrap();
+    assert_eq!(report.diagnosis, Diagnosis::Ok);
+    assert_eq!(report.bit_errors, 0);
+    assert!(report.reset_defaults);
+    assert_eq!(before, (radio.register(0x10), radio.register(0x0A), radio.register(RF_CH)));
+    // Only legal channels, and the original one last
+    let channels = channels.borrow();
+    assert!(channels.iter().all(|channel| *channel <= 125), "{:?}", channels);
+    assert_eq!(channels.last(), Some(&2));
+}
+
+#[test]
+fn reports_the_first_error() {
+    let radio = SimRadio::new();
+    let spi = spi(&radio);
+    let (count, fail_at, channels) = (spi.count.clone(), spi.fail_at.clone(), spi.channels.clone());
+    let mut standby = NRF24L01::new(radio.ce(), spi).unwrap();
+
+    // Fail within the patterns, so that the restore fails as well
+    fail_at.set(count.get() + 8);
+    match standby.self_test() {
+        Err(Error::SpiError(failed)) => assert_eq!(failed, Failed(fail_at.get())),
+        result => panic!("{:?}", result),
+    }
+    assert!(count.get() > fail_at.get() + 1);
+    assert!(channels.borrow().iter().all(|channel| *channel <= 125));
+}
@@ -0,0 +1,155 @@
+//! Channel models of the simulated ether
+#![cfg(feature = "sim")]
+
+use embedded_nrf24l01::sim::{
+    Attenuation, ChannelModel, Collisions, Ether, GilbertElliott, RandomLoss, SimCe, SimRadio, SimSpi,
+    Transmission,
+};
+use embedded_nrf24l01::{Configuration, DataRate, TxMode, NRF24L01};
+
+fn transmission(sender: usize, start: u64, channel: u8) -> Transmission {
+    Transmission {
+        sender,
+        start,
+        end: start + 100,
+        channel,
+        data_rate: DataRate::R2Mbps,
+        power_dbm: 0,
+        is_ack: false,
+    }
+}
+
+/// Which of `count` transmissions from radio 0 reach radio 1
+fn pattern<M: ChannelModel>(model: &mut M, count: u64) -> Vec<bool> {
+    (0..count)
+        .map(|i| model.receives(&transmission(0, i * 1000, 2), 1, &[]))
+        .collect()
+}
+
+type Tx = TxMode<NRF24L01<SimCe, SimSpi>>;
+
+/// TX mode without auto-ack
+fn tx(radio: &SimRadio) -> Tx {
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    standby.set_pipes_rx_lengths(&[None; 6]).unwrap();
+    standby.set_auto_ack(&[false; 6]).unwrap();
+    standby.set_auto_retransmit(0, 0).unwrap();
+    standby.tx().unwrap()
+}
+
+fn send(ether: &Ether, tx: &mut Tx) {
+    tx.send(b"ping").unwrap();
+    ether.advance(1000);
+    nb::block!(tx.poll_send()).unwrap();
+}
+
+/// Keep `radio` in RX mode
+fn listen(radio: &SimRadio) {
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    standby.set_pipes_rx_lengths(&[None; 6]).unwrap();
+    standby.set_auto_ack(&[false; 6]).unwrap();
+    standby.rx().unwrap();
+}
+
+#[test]
+fn random_loss_is_reproducible() {
+    let first = pattern(&mut RandomLoss::new(0.3, 42), 500);
+    assert_eq!(first, pattern(&mut RandomLoss::new(0.3, 42), 500));
+    assert_ne!(first, pattern(&mut RandomLoss::new(0.3, 43), 500));
+
+    let lost = first.iter().filter(|received| !**received).count();
+    assert!((100..200).contains(&lost), "{} lost", lost);
+    assert!(pattern(&mut RandomLoss::new(0.0, 1), 100).iter().all(|r| *r));
+    assert!(pattern(&mut RandomLoss::new(1.0, 1), 100).iter().all(|r| !*r));
+}
+
+#[test]
+fn gilbert_elliott_is_reproducible() {
+    let model = || GilbertElliott::new(0.05, 0.2, 0.0, 1.0, 7);
+    let first = pattern(&mut model(), 1000);
+    assert_eq!(first, pattern(&mut model(), 1000));
+
+    // Losses come in bursts
+    let lost = first.iter().filter(|received| !**received).count();
+    let bursts = first.windows(2).filter(|w| w[0] && !w[1]).count();
+    assert!(lost > 0);
+    assert!(bursts < lost, "{} losses in {} bursts", lost, bursts);
+}
+
+#[test]
+fn seeded_loss_is_reproducible_on_the_ether() {
+    let run = |seed| {
+        let ether = Ether::new();
+        ether.add_model(RandomLoss::new(0.5, seed));
+        let (a, b) = (ether.radio(), ether.radio());
+        listen(&a);
+        let mut tx = tx(&b);
+        ether.advance(2000);
+        (0..20)
+            .map(|_| {
+                send(&ether, &mut tx);
+                let received = a.rx_fifo_len() > 0;
+                NRF24L01::new(a.ce(), a.spi()).unwrap().flush_rx().unwrap();
+                listen(&a);
+                ether.advance(200);
+                received
+            })
+            .collect::<Vec<_>>()
+    };
+    let first = run(3);
+    assert_eq!(first, run(3));
+    assert!(first.contains(&true));
+    assert!(first.contains(&false));
+}
+
+#[test]
+fn overlapping_frames_collide() {
+    let mut model = Collisions;
+    let frame = transmission(0, 1000, 2);
+    assert!(!model.receives(&frame, 2, &[transmission(1, 1050, 2)]));
+    assert!(model.receives(&frame, 2, &[transmission(1, 1050, 3)]));
+    assert!(model.receives(&frame, 2, &[transmission(1, 1200, 2)]));
+    // A transmitting receiver hears nothing, on any channel
+    assert!(!model.receives(&frame, 1, &[transmission(1, 1050, 3)]));
+
+    let ether = Ether::new();
+    ether.add_model(Collisions);
+    let (a, b, c) = (ether.radio(), ether.radio(), ether.radio());
+    listen(&a);
+    let (mut tx_b, mut tx_c) = (tx(&b), tx(&c));
+    ether.advance(2000);
+    // Both start transmitting within a few µs
+    tx_b.send(b"ping").unwrap();
+    tx_c.send(b"pong").unwrap();
+    ether.advance(1000);
+    assert_eq!(b.take_transmitted().len(), 1);
+    assert_eq!(c.take_transmitted().len(), 1);
+    assert_eq!(a.rx_fifo_len(), 0);
+}
+
+#[test]
+fn radios_out_of_range_do_not_receive() {
+    let mut model = Attenuation::new(2.0);
+    model.place(0, 0.0, 0.0).place(1, 10.0, 0.0).place(2, 10_000.0, 0.0);
+    assert!(model.path_loss_db(0, 1) < model.path_loss_db(0, 2));
+    assert!(model.receives(&transmission(0, 0, 2), 1, &[]));
+    assert!(!model.receives(&transmission(0, 0, 2), 2, &[]));
+    // Unplaced radios are always in range
+    assert!(model.receives(&transmission(0, 0, 2), 3, &[]));
+
+    let ether = Ether::new();
+    let (near, far, tx) = (ether.radio(), ether.radio(), ether.radio());
+    let mut model = Attenuation::new(2.0);
+    model
+        .place(near.id(), 5.0, 0.0)
+        .place(far.id(), 10_000.0, 0.0)
+        .place(tx.id(), 0.0, 0.0);
+    ether.add_model(model);
+    listen(&near);
+    listen(&far);
+    let mut tx = self::tx(&tx);
+    ether.advance(2000);
+    send(&ether, &mut tx);
+    assert_eq!(near.rx_fifo_len(), 1);
+    assert_eq!(far.rx_fifo_len(), 0);
+}
@@ -0,0 +1,175 @@
+//! The driver against one simulated chip
+#![cfg(feature = "sim")]
+
+use embedded_hal::spi::SpiDevice;
+use embedded_nrf24l01::sim::{Frame, SimRadio, FIFO_DEPTH};
+use embedded_nrf24l01::{ChipVariant, Configuration, NRF24L01};
+
+const STATUS: u8 = 0x07;
+const FIFO_STATUS: u8 = 0x17;
+const DYNPD: u8 = 0x1C;
+const FEATURE: u8 = 0x1D;
+
+/// Addresses of pipe 0 and 1 after reset
+const P0: [u8; 5] = [0xE7; 5];
+const P1: [u8; 5] = [0xC2; 5];
+
+/// A frame with a dynamic payload length on the reset channel
+fn frame(address: &[u8], payload: &[u8]) -> Frame {
+    let mut frame = Frame::new(2, address, payload);
+    frame.dynamic = true;
+    frame
+}
+
+fn rx_p_no(radio: &SimRadio) -> u8 {
+    (radio.register(STATUS)[0] >> 1) & 0b111
+}
+
+/// Run a raw command, returning the MISO bytes
+fn command(radio: &SimRadio, mosi: &[u8]) -> Vec<u8> {
+    let mut buf = mosi.to_vec();
+    radio.spi().transfer_in_place(&mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn tx_fifo_holds_three_payloads() {
+    let radio = SimRadio::new();
+    let mut tx = NRF24L01::new(radio.ce(), radio.spi()).unwrap().tx().unwrap();
+    for i in 0..FIFO_DEPTH {
+        assert!(tx.can_send().unwrap());
+        tx.send(&[i as u8; 4]).unwrap();
+    }
+    assert!(!tx.can_send().unwrap());
+    assert_eq!(radio.tx_fifo_len(), FIFO_DEPTH);
+    // TX_FULL in STATUS and FIFO_STATUS
+    assert_eq!(radio.register(STATUS)[0] & 1, 1);
+    assert_eq!(radio.register(FIFO_STATUS)[0] & 0b10_0000, 0b10_0000);
+    // A fourth payload is dropped by the chip
+    command(&radio, &[0b1010_0000, 0xAA]);
+    assert_eq!(radio.tx_fifo_len(), FIFO_DEPTH);
+}
+
+#[test]
+fn rx_fifo_holds_three_payloads() {
+    let radio = SimRadio::new();
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    standby.set_pipes_rx_lengths(&[None; 6]).unwrap();
+    let mut rx = standby.rx().unwrap();
+    radio.advance(2000);
+
+    for i in 0..FIFO_DEPTH {
+        assert!(radio.deliver(&frame(&P0, &[i as u8; 4])).is_some());
+    }
+    assert!(rx.is_full().unwrap());
+    assert!(radio.deliver(&frame(&P0, &[9; 4])).is_none());
+    for i in 0..FIFO_DEPTH {
+        assert!(rx.can_read().unwrap().is_some());
+        assert_eq!(rx.read().unwrap().as_ref(), &[i as u8; 4]);
+    }
+    assert!(rx.is_empty().unwrap());
+}
+
+#[test]
+fn status_reports_pipe_of_fifo_head() {
+    let radio = SimRadio::new();
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    standby.set_pipes_rx_lengths(&[None; 6]).unwrap();
+    standby.set_pipes_rx_enable(&[true, true, true, false, false, false]).unwrap();
+    let mut rx = standby.rx().unwrap();
+    radio.advance(2000);
+    assert_eq!(rx_p_no(&radio), 0b111);
+
+    let mut p2 = P1;
+    p2[0] = 0xC3;
+    radio.deliver(&frame(&P1, b"one"));
+    radio.deliver(&frame(&p2, b"two"));
+    radio.deliver(&frame(&P0, b"thr"));
+    // RX_DR is set with the first packet
+    assert_eq!(radio.register(STATUS)[0] & (1 << 6), 1 << 6);
+
+    for (pipe, payload) in [(1, b"one"), (2, b"two"), (0, b"thr")] {
+        assert_eq!(rx_p_no(&radio), pipe);
+        assert_eq!(rx.can_read().unwrap(), Some(pipe));
+        assert_eq!(rx.read().unwrap().as_ref(), payload);
+    }
+    assert_eq!(rx.can_read().unwrap(), None);
+    assert_eq!(rx_p_no(&radio), 0b111);
+    // can_read() has cleared RX_DR
+    assert_eq!(radio.register(STATUS)[0] & (1 << 6), 0);
+}
+
+#[test]
+fn flush_empties_fifos() {
+    let radio = SimRadio::new();
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    standby.set_pipes_rx_lengths(&[Some(4); 6]).unwrap();
+    let mut rx = standby.rx().unwrap();
+    radio.advance(2000);
+    radio.deliver(&Frame::new(2, &P0, &[1; 4]));
+    radio.deliver(&Frame::new(2, &P0, &[2; 4]));
+    assert_eq!(radio.rx_fifo_len(), 2);
+    rx.flush_rx().unwrap();
+    assert_eq!(radio.rx_fifo_len(), 0);
+    assert!(rx.is_empty().unwrap());
+
+    let mut tx = rx.standby().unwrap().tx().unwrap();
+    tx.send(&[1; 4]).unwrap();
+    tx.send(&[2; 4]).unwrap();
+    assert_eq!(radio.tx_fifo_len(), 2);
+    tx.flush_tx().unwrap();
+    assert_eq!(radio.tx_fifo_len(), 0);
+    assert_eq!(radio.register(FIFO_STATUS)[0] & 0b1_0000, 0b1_0000);
+}
+
+#[test]
+fn rx_payload_width() {
+    let radio = SimRadio::new();
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    standby.set_pipes_rx_lengths(&[None; 6]).unwrap();
+    let mut rx = standby.rx().unwrap();
+    radio.advance(2000);
+
+    radio.deliver(&frame(&P0, b"seven!!"));
+    assert_eq!(command(&radio, &[0b0110_0000, 0xFF])[1], 7);
+    assert_eq!(rx.read().unwrap().as_ref(), b"seven!!");
+
+    // R_RX_PL_WID requires EN_DPL
+    let mut standby = rx.standby().unwrap();
+    standby.set_pipes_rx_lengths(&[Some(4); 6]).unwrap();
+    command(&radio, &[0x20 | FEATURE, 0]);
+    let _rx = standby.rx().unwrap();
+    radio.advance(200);
+    radio.deliver(&Frame::new(2, &P0, &[1; 4]));
+    assert_eq!(radio.rx_fifo_len(), 1);
+    assert_eq!(command(&radio, &[0b0110_0000, 0xFF])[1], 0);
+}
+
+#[test]
+fn activate_unlocks_feature_on_nrf24l01() {
+    let radio = SimRadio::with_variant(ChipVariant::Nrf24l01);
+    // Locked after reset
+    command(&radio, &[0x20 | FEATURE, 0b111]);
+    assert_eq!(radio.register(FEATURE), [0]);
+
+    // The driver activates the features
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    assert_eq!(standby.get_variant(), ChipVariant::Nrf24l01);
+    standby.set_pipes_rx_lengths(&[None; 6]).unwrap();
+    assert_eq!(radio.register(FEATURE)[0] & 0b100, 0b100);
+    assert!(standby.get_pipes_rx_lengths().unwrap().iter().all(Option::is_none));
+
+    // Another ACTIVATE locks and clears them again
+    command(&radio, &[0b0101_0000, 0x73]);
+    assert_eq!(radio.register(FEATURE), [0]);
+    assert_eq!(radio.register(DYNPD), [0]);
+}
+
+#[test]
+fn activate_is_ignored_on_nrf24l01_plus() {
+    let radio = SimRadio::new();
+    command(&radio, &[0x20 | FEATURE, 0b100]);
+    assert_eq!(radio.register(FEATURE), [0b100]);
+    command(&radio, &[0b0101_0000, 0x73]);
+    assert_eq!(radio.register(FEATURE), [0b100]);
+}
@@ -0,0 +1,127 @@
+//! The driver on several simulated radios sharing one ether
+#![cfg(feature = "sim")]
+
+use embedded_hal::spi::{Operation, SpiDevice};
+use embedded_nrf24l01::sim::{Ether, SimRadio};
+use embedded_nrf24l01::{Configuration, Pipe, NRF24L01};
+
+const STATUS: u8 = 0x07;
+const OBSERVE_TX: u8 = 0x08;
+
+const MAX_RT: u8 = 1 << 4;
+
+fn max_rt(radio: &SimRadio) -> bool {
+    radio.register(STATUS)[0] & MAX_RT != 0
+}
+
+#[test]
+fn tx_reaches_rx() {
+    let ether = Ether::new();
+    let (a, b) = (ether.radio(), ether.radio());
+    let mut standby = NRF24L01::new(a.ce(), a.spi()).unwrap();
+    standby.set_pipes_rx_lengths(&[None; 6]).unwrap();
+    let mut rx = standby.rx().unwrap();
+    let mut standby = NRF24L01::new(b.ce(), b.spi()).unwrap();
+    standby.set_pipes_rx_lengths(&[None; 6]).unwrap();
+    let mut tx = standby.tx().unwrap();
+    ether.advance(2000);
+
+    tx.send(b"hello").unwrap();
+    ether.advance(1000);
+    assert!(nb::block!(tx.poll_send()).is_ok());
+    assert_eq!(rx.can_read().unwrap(), Some(0));
+    assert_eq!(rx.read().unwrap().as_ref(), b"hello");
+
+    // Auto-ack makes the first transmission suffice
+    let frames = b.take_transmitted();
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0].payload, b"hello");
+    assert_eq!(b.register(OBSERVE_TX)[0] & 0x0F, 0);
+}
+
+#[test]
+fn ptx_gets_ack_payload_from_prx() {
+    let ether = Ether::new();
+    let (a, b) = (ether.radio(), ether.radio());
+    let mut prx = NRF24L01::new(a.ce(), a.spi()).unwrap().prx().unwrap();
+    let pipe = Pipe::new(0).unwrap();
+    assert!(prx.queue_ack_payload(pipe, b"reply").unwrap());
+    let mut ptx = NRF24L01::new(b.ce(), b.spi()).unwrap().ptx(2, 3).unwrap();
+    ether.advance(2000);
+
+    assert!(ptx.send_receive(Some(b"request")).unwrap().sent);
+    ether.advance(1000);
+    let result = ptx.send_receive(None).unwrap();
+    assert!(!result.dropped);
+    let received = result.received.expect("ACK payload");
+    assert_eq!(received.pipe, 0);
+    assert_eq!(received.payload.as_ref(), b"reply");
+
+    let received = prx.read().unwrap().expect("packet");
+    assert_eq!(received.pipe, 0);
+    assert_eq!(received.payload.as_ref(), b"request");
+    assert_eq!(prx.take_delivered(), [1, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn retransmits_until_receiver_listens() {
+    let ether = Ether::new();
+    let (a, b) = (ether.radio(), ether.radio());
+    let mut ptx = NRF24L01::new(b.ce(), b.spi()).unwrap().ptx(3, 15).unwrap();
+    ether.advance(2000);
+
+    assert!(ptx.send_receive(Some(b"late")).unwrap().sent);
+    // ARD of 1000 µs, so that a few retransmissions go unanswered
+    ether.advance(3500);
+    let mut prx = NRF24L01::new(a.ce(), a.spi()).unwrap().prx().unwrap();
+    ether.advance(5000);
+
+    assert!(!max_rt(&b));
+    assert_eq!(b.tx_fifo_len(), 0);
+    let frames = b.take_transmitted();
+    assert!(frames.len() > 2, "{} transmissions", frames.len());
+    assert!(frames.iter().all(|frame| frame.pid == frames[0].pid));
+    let arc_cnt = b.register(OBSERVE_TX)[0] & 0x0F;
+    assert_eq!(usize::from(arc_cnt), frames.len() - 1);
+
+    assert_eq!(prx.read().unwrap().expect("packet").payload.as_ref(), b"late");
+    assert!(prx.read().unwrap().is_none());
+}
+
+#[test]
+fn max_rt_without_receiver() {
+    let ether = Ether::new();
+    let (_a, b) = (ether.radio(), ether.radio());
+    let mut ptx = NRF24L01::new(b.ce(), b.spi()).unwrap().ptx(0, 3).unwrap();
+    ether.advance(2000);
+
+    assert!(ptx.send_receive(Some(b"lost")).unwrap().sent);
+    ether.advance(5000);
+    assert!(max_rt(&b));
+    assert!(b.irq_is_asserted());
+    // The packet and 3 retransmissions, then the packet stays queued
+    assert_eq!(b.take_transmitted().len(), 4);
+    assert_eq!(b.register(OBSERVE_TX)[0] & 0x0F, 3);
+    assert_eq!(b.tx_fifo_len(), 1);
+    ether.advance(5000);
+    assert!(b.take_transmitted().is_empty());
+
+    let result = ptx.send_receive(None).unwrap();
+    assert!(result.dropped);
+    assert_eq!(result.discarded, 1);
+    assert!(!max_rt(&b));
+    assert_eq!(b.tx_fifo_len(), 0);
+}
+
+#[test]
+fn spi_delay_advances_clock() {
+    let radio = SimRadio::new();
+    let before = radio.now();
+    let mut buf = [0xFF];
+    radio
+        .spi()
+        .transaction(&mut [Operation::DelayNs(500_000), Operation::TransferInPlace(&mut buf)])
+        .unwrap();
+    assert!(radio.now() >= before + 500);
+    assert!(radio.now() < before + 510);
+}
@@ -0,0 +1,55 @@
+//! `RegisterSnapshot` and `StandbyMode::restore()` on a simulated chip
+#![cfg(feature = "sim")]
+
+use embedded_nrf24l01::sim::SimRadio;
+use embedded_nrf24l01::{Address, Channel, Configuration, Pipe, NRF24L01};
+
+const CONFIG: u8 = 0x00;
+const SETUP_AW: u8 = 0x03;
+const SETUP_RETR: u8 = 0x04;
+const RF_CH: u8 = 0x05;
+const RX_ADDR_P1: u8 = 0x0B;
+const TX_ADDR: u8 = 0x10;
+const DYNPD: u8 = 0x1C;
+const FEATURE: u8 = 0x1D;
+
+#[test]
+fn registers_by_address() {
+    let radio = SimRadio::new();
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    let snapshot = standby.snapshot().unwrap();
+    for addr in [CONFIG, SETUP_AW, RF_CH, RX_ADDR_P1, TX_ADDR, DYNPD, FEATURE] {
+        assert_eq!(snapshot.register(addr), Some(radio.register(addr).as_slice()), "0x{:02X}", addr);
+    }
+    assert_eq!(snapshot.register(RX_ADDR_P1).unwrap().len(), 5);
+    // Not part of a snapshot
+    assert_eq!(snapshot.register(0x18), None);
+    assert_eq!(snapshot.diff(&standby.snapshot().unwrap()).count(), 0);
+}
+
+#[test]
+fn restore_undoes_changes() {
+    let radio = SimRadio::new();
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    standby.set_pipes_rx_lengths(&[None; 6]).unwrap();
+    let before = standby.snapshot().unwrap();
+
+    standby.set_frequency(Channel::new(100).unwrap()).unwrap();
+    standby.set_auto_retransmit(7, 9).unwrap();
+    standby.set_address_width(3).unwrap();
+    standby.set_rx_addr(Pipe::new(1).unwrap(), &Address::new(&[1, 2, 3]).unwrap()).unwrap();
+    standby.set_tx_addr(&Address::new(&[4, 5, 6]).unwrap()).unwrap();
+    standby.set_pipes_rx_lengths(&[Some(8); 6]).unwrap();
+    let after = standby.snapshot().unwrap();
+    let changed = before.diff(&after).map(|change| change.addr).collect::<Vec<_>>();
+    for addr in [SETUP_AW, SETUP_RETR, RF_CH, RX_ADDR_P1, TX_ADDR, DYNPD] {
+        assert!(changed.contains(&addr), "0x{:02X} in {:?}", addr, changed);
+    }
+
+    standby.restore(&before).unwrap();
+    let restored = standby.snapshot().unwrap();
+    assert_eq!(before.diff(&restored).collect::<Vec<_>>(), []);
+    assert_eq!(radio.register(RX_ADDR_P1), before.register(RX_ADDR_P1).unwrap());
+    assert_eq!(radio.register(TX_ADDR), before.register(TX_ADDR).unwrap());
+    assert_eq!(radio.register(RF_CH), [2]);
+}
@@ -0,0 +1,221 @@
+//! `TxMode` and its `MaxRtPolicy` on simulated radios
+#![cfg(feature = "sim")]
+
+use embedded_nrf24l01::sim::{Ether, SimRadio};
+use embedded_nrf24l01::{ConfigError, Configuration, Error, MaxRtPolicy, NRF24L01};
+
+/// One software retry, right away
+const RETRY: MaxRtPolicy = MaxRtPolicy::Retry { retries: 1, backoff_us: 0 };
+
+fn transmitted(radio: &SimRadio, payload: &[u8]) -> usize {
+    radio.take_transmitted().iter().filter(|frame| frame.payload == payload).count()
+}
+
+#[test]
+fn retries_reset_after_delivery() {
+    let ether = Ether::new();
+    let (a, b) = (ether.radio(), ether.radio());
+    let mut receiver = NRF24L01::new(a.ce(), a.spi()).unwrap();
+    receiver.set_pipes_rx_lengths(&[None; 6]).unwrap();
+    let mut standby = NRF24L01::new(b.ce(), b.spi()).unwrap();
+    standby.set_pipes_rx_lengths(&[None; 6]).unwrap();
+    let mut tx = standby.tx().unwrap();
+    tx.set_max_rt_policy(RETRY);
+    ether.advance(2000);
+
+    // The packet and 3 retransmissions, then one retry in software
+    tx.send(b"first").unwrap();
+    ether.advance(5000);
+    assert!(matches!(tx.poll_send(), Err(nb::Error::WouldBlock)));
+    assert_eq!(transmitted(&b, b"first"), 4);
+
+    // Delivered on the retry
+    let mut rx = receiver.rx().unwrap();
+    ether.advance(5000);
+    assert_eq!(tx.poll_send().unwrap(), 0);
+    assert_eq!(rx.read().unwrap().as_ref(), b"first");
+    let _receiver = rx.standby().unwrap();
+
+    // The next packet gets its own retry, one more transmission as
+    // ARC_CNT stays at the maximum
+    tx.send(b"second").unwrap();
+    let discarded = loop {
+        match tx.poll_send() {
+            Ok(discarded) => break discarded,
+            Err(nb::Error::WouldBlock) => ether.advance(500),
+            Err(nb::Error::Other(e)) => panic!("{:?}", e),
+        }
+    };
+    assert_eq!(discarded, 1);
+    assert_eq!(transmitted(&b, b"second"), 5);
+}
+
+#[test]
+fn flush_all_counts_queued_packets() {
+    for count in 1..=3 {
+        let radio = SimRadio::new();
+        let mut tx = NRF24L01::new(radio.ce(), radio.spi()).unwrap().tx().unwrap();
+        radio.advance(2000);
+        for i in 0..count {
+            tx.send(&[b'a' + i]).unwrap();
+        }
+        // Nobody acknowledges the first packet
+        radio.advance(5000);
+        assert_eq!(tx.wait_empty().unwrap(), usize::from(count));
+        assert_eq!(radio.tx_fifo_len(), 0);
+        // Neither the probe nor the packets behind the first are sent
+        let frames = radio.take_transmitted();
+        assert!(frames.iter().all(|frame| frame.payload == b"a"), "{}", count);
+        assert_eq!(frames.len(), 4);
+    }
+}
+
+#[test]
+fn drop_head_keeps_order() {
+    let ether = Ether::new();
+    let (a, b) = (ether.radio(), ether.radio());
+    let mut receiver = NRF24L01::new(a.ce(), a.spi()).unwrap();
+    receiver.set_pipes_rx_lengths(&[None; 6]).unwrap();
+    let mut standby = NRF24L01::new(b.ce(), b.spi()).unwrap();
+    standby.set_pipes_rx_lengths(&[None; 6]).unwrap();
+    let mut tx = standby.tx().unwrap();
+    tx.set_max_rt_policy(MaxRtPolicy::DropHead);
+    ether.advance(2000);
+
+    tx.send(b"first").unwrap();
+    tx.send(b"second").unwrap();
+    tx.send_no_ack(b"third").unwrap();
+    ether.advance(5000);
+    let mut rx = receiver.rx().unwrap();
+    ether.advance(200);
+    assert_eq!(tx.wait_empty().unwrap(), 1);
+
+    assert_eq!(rx.read().unwrap().as_ref(), b"second");
+    assert_eq!(rx.read().unwrap().as_ref(), b"third");
+    let frames = b.take_transmitted();
+    let payloads: Vec<&[u8]> = frames.iter().map(|frame| &frame.payload[..]).collect();
+    assert_eq!(payloads, [&b"first"[..], b"first", b"first", b"first", b"second", b"third"]);
+    assert!(frames[5].no_ack);
+}
+
+#[test]
+fn rejects_long_payload() {
+    let radio = SimRadio::new();
+    let mut tx = NRF24L01::new(radio.ce(), radio.spi()).unwrap().tx().unwrap();
+    match tx.send(&[0; 33]) {
+        Err(Error::Config(ConfigError::PayloadLength(33))) => {}
+        result => panic!("{:?}", result),
+    }
+    match tx.send_no_ack(&[0; 40]) {
+        Err(Error::Config(ConfigError::PayloadLength(40))) => {}
+        result => panic!("{:?}", result),
+    }
+    assert_eq!(radio.tx_fifo_len(), 0);
+    tx.send(&[0; 32]).unwrap();
+    assert_eq!(radio.tx_fifo_len(), 1);
+}
+
+#[cfg(feature = "async")]
+mod asynch {
+    use super::*;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use embedded_nrf24l01::asynch::{self, Configuration as _};
+
+    /// The simulated chip never lets a future return `Pending`
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut context = Context::from_waker(Waker::noop());
+        match pin!(future).poll(&mut context) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("pending"),
+        }
+    }
+
+    #[test]
+    fn retries_reset_after_delivery() {
+        let ether = Ether::new();
+        let (a, b) = (ether.radio(), ether.radio());
+        let mut receiver = NRF24L01::new(a.ce(), a.spi()).unwrap();
+        receiver.set_pipes_rx_lengths(&[None; 6]).unwrap();
+        let mut standby = block_on(asynch::NRF24L01::new(b.ce(), b.irq(), b.spi())).unwrap();
+        block_on(standby.set_pipes_rx_lengths(&[None; 6])).unwrap();
+        let mut tx = block_on(standby.tx()).unwrap();
+        tx.set_max_rt_policy(RETRY);
+        ether.advance(2000);
+
+        block_on(tx.send(b"first")).unwrap();
+        ether.advance(5000);
+        assert_eq!(transmitted(&b, b"first"), 4);
+        let mut rx = receiver.rx().unwrap();
+        assert_eq!(block_on(tx.wait_empty()).unwrap(), 0);
+        assert_eq!(rx.read().unwrap().as_ref(), b"first");
+        let _receiver = rx.standby().unwrap();
+
+        block_on(tx.send(b"second")).unwrap();
+        assert_eq!(block_on(tx.wait_empty()).unwrap(), 1);
+        assert_eq!(transmitted(&b, b"second"), 5);
+    }
+
+    #[test]
+    fn flush_all_counts_queued_packets() {
+        for count in 1..=3 {
+            let radio = SimRadio::new();
+            let standby = block_on(asynch::NRF24L01::new(radio.ce(), radio.irq(), radio.spi())).unwrap();
+            let mut tx = block_on(standby.tx()).unwrap();
+            radio.advance(2000);
+            for i in 0..count {
+                block_on(tx.send(&[b'a' + i])).unwrap();
+            }
+            assert_eq!(block_on(tx.wait_empty()).unwrap(), usize::from(count));
+            assert_eq!(radio.tx_fifo_len(), 0);
+            let frames = radio.take_transmitted();
+            assert!(frames.iter().all(|frame| frame.payload == b"a"), "{}", count);
+            assert_eq!(frames.len(), 4);
+        }
+    }
+
+    #[test]
+    fn drop_head_keeps_order() {
+        let ether = Ether::new();
+        let (a, b) = (ether.radio(), ether.radio());
+        let mut receiver = NRF24L01::new(a.ce(), a.spi()).unwrap();
+        receiver.set_pipes_rx_lengths(&[None; 6]).unwrap();
+        let mut standby = block_on(asynch::NRF24L01::new(b.ce(), b.irq(), b.spi())).unwrap();
+        block_on(standby.set_pipes_rx_lengths(&[None; 6])).unwrap();
+        let mut tx = block_on(standby.tx()).unwrap();
+        tx.set_max_rt_policy(MaxRtPolicy::DropHead);
+        ether.advance(2000);
+
+        block_on(tx.send(b"first")).unwrap();
+        block_on(tx.send(b"second")).unwrap();
+        block_on(tx.send_no_ack(b"third")).unwrap();
+        ether.advance(5000);
+        let mut rx = receiver.rx().unwrap();
+        ether.advance(200);
+        assert_eq!(block_on(tx.wait_empty()).unwrap(), 1);
+
+        assert_eq!(rx.read().unwrap().as_ref(), b"second");
+        assert_eq!(rx.read().unwrap().as_ref(), b"third");
+        let frames = b.take_transmitted();
+        let payloads: Vec<&[u8]> = frames.iter().map(|frame| &frame.payload[..]).collect();
+        assert_eq!(payloads, [&b"first"[..], b"first", b"first", b"first", b"second", b"third"]);
+        assert!(frames[5].no_ack);
+    }
+
+    #[test]
+    fn rejects_long_payload() {
+        let radio = SimRadio::new();
+        let standby = block_on(asynch::NRF24L01::new(radio.ce(), radio.irq(), radio.spi())).unwrap();
+        let mut tx = block_on(standby.tx()).unwrap();
+        match block_on(tx.send(&[0; 33])) {
+            Err(Error::Config(ConfigError::PayloadLength(33))) => {}
+            result => panic!("{:?}", result),
+        }
+        match block_on(tx.send_no_ack(&[0; 40])) {
+            Err(Error::Config(ConfigError::PayloadLength(40))) => {}
+            result => panic!("{:?}", result),
+        }
+        assert_eq!(radio.tx_fifo_len(), 0);
+    }
+}
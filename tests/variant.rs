@@ -0,0 +1,182 @@
+//! Chip variant detection and initialisation, blocking and async
+#![cfg(feature = "sim")]
+
+use embedded_nrf24l01::sim::SimRadio;
+use embedded_nrf24l01::{ChipVariant, ConfigError, Configuration, DataRate, Error, PowerLevel, NRF24L01};
+
+const RF_SETUP: u8 = 0x06;
+const FEATURE: u8 = 0x1D;
+
+const VARIANTS: [ChipVariant; 5] = [
+    ChipVariant::Nrf24l01,
+    ChipVariant::Nrf24l01Plus,
+    ChipVariant::Si24r1,
+    ChipVariant::Bk2421,
+    ChipVariant::Bk2423,
+];
+
+/// Bank 1 of Beken parts after init, as recommended by Beken
+const BANK1: [(u8, &[u8]); 4] = [
+    (0x00, &[0x40, 0x4B, 0x01, 0xE2]),
+    (0x04, &[0xF9, 0x9E, 0x86, 0x0B]),
+    (0x0D, &[0x36, 0xB4, 0x80, 0x00]),
+    (0x0E, &[0x41, 0x20, 0x08, 0x04, 0x81, 0x20, 0xCF, 0xF7, 0xFE, 0xFF, 0xFF]),
+];
+
+/// The Si24R1 cannot be told apart from the nRF24L01+
+fn detected(variant: ChipVariant) -> ChipVariant {
+    match variant {
+        ChipVariant::Si24r1 => ChipVariant::Nrf24l01Plus,
+        _ => variant,
+    }
+}
+
+fn check(variant: ChipVariant, radio: &SimRadio) {
+    if variant.needs_activate() {
+        assert_eq!(radio.register(FEATURE), [0]);
+    }
+    if variant.has_bank1() {
+        for (addr, value) in BANK1.iter() {
+            assert_eq!(radio.bank1_register(*addr), *value, "{:?} 0x{:02X}", variant, addr);
+        }
+    }
+}
+
+#[test]
+fn detects_every_variant() {
+    for variant in VARIANTS {
+        let radio = SimRadio::with_variant(variant);
+        let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+        assert_eq!(standby.get_variant(), detected(variant));
+        check(variant, &radio);
+        // Probing leaves RF_SETUP as it was
+        assert_eq!(radio.register(RF_SETUP), SimRadio::with_variant(variant).register(RF_SETUP));
+        // Features are unlocked
+        standby.set_pipes_rx_lengths(&[None; 6]).unwrap();
+        assert_eq!(radio.register(FEATURE)[0] & 0b100, 0b100, "{:?}", variant);
+    }
+}
+
+#[test]
+fn set_variant_overrides_detection() {
+    let radio = SimRadio::with_variant(ChipVariant::Si24r1);
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    assert_eq!(standby.set_power_dbm(7).unwrap(), 0);
+    standby.set_variant(ChipVariant::Si24r1).unwrap();
+    assert_eq!(standby.get_variant(), ChipVariant::Si24r1);
+    assert_eq!(standby.set_power_dbm(7).unwrap(), 7);
+    assert_eq!(radio.register(RF_SETUP)[0] & 0b111, 0b111);
+
+    let radio = SimRadio::with_variant(ChipVariant::Bk2423);
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    standby.set_rf(&DataRate::R250Kbps, PowerLevel::MAX).unwrap();
+    standby.write_bank1_register(0x00, &[0; 4]).unwrap();
+    standby.set_variant(ChipVariant::Bk2421).unwrap();
+    match standby.set_rf(&DataRate::R250Kbps, PowerLevel::MAX) {
+        Err(Error::Unsupported) => {}
+        result => panic!("{:?}", result),
+    }
+    // Bank 1 is initialised again
+    check(ChipVariant::Bk2421, &radio);
+}
+
+#[test]
+fn nrf24l01plus_keeping_rf_setup_bit0() {
+    let radio = SimRadio::with_variant(ChipVariant::Nrf24l01Plus);
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    // Sets bit 0 of RF_SETUP, which many nRF24L01+ read back
+    standby.set_variant(ChipVariant::Si24r1).unwrap();
+    standby.set_power_dbm(7).unwrap();
+    assert_eq!(radio.register(RF_SETUP)[0] & 1, 1);
+    drop(standby);
+
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    assert_eq!(standby.get_variant(), ChipVariant::Nrf24l01Plus);
+}
+
+#[test]
+fn bank1_register_bounds() {
+    let radio = SimRadio::with_variant(ChipVariant::Bk2421);
+    let mut standby = NRF24L01::new(radio.ce(), radio.spi()).unwrap();
+    let mut ramp = [0; 11];
+    standby.read_bank1_register(0x0E, &mut ramp).unwrap();
+    assert_eq!(ramp[..], *BANK1[3].1);
+
+    match standby.read_bank1_register(0x20, &mut [0; 4]) {
+        Err(Error::Config(ConfigError::Register(0x20))) => {}
+        result => panic!("{:?}", result),
+    }
+    match standby.read_bank1_register(0x00, &mut [0; 33]) {
+        Err(Error::Config(ConfigError::RegisterLength(33))) => {}
+        result => panic!("{:?}", result),
+    }
+    match standby.write_bank1_register(0xFF, &[0; 4]) {
+        Err(Error::Config(ConfigError::Register(0xFF))) => {}
+        result => panic!("{:?}", result),
+    }
+    match standby.write_bank1_register(0x00, &[0; 40]) {
+        Err(Error::Config(ConfigError::RegisterLength(40))) => {}
+        result => panic!("{:?}", result),
+    }
+    // Nothing was written, and bank 0 is selected again
+    check(ChipVariant::Bk2421, &radio);
+    assert_eq!(standby.get_frequency().unwrap(), 2);
+}
+
+#[cfg(feature = "async")]
+mod asynch {
+    use super::*;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use embedded_nrf24l01::asynch::{self, Configuration as _};
+
+    /// The simulated chip never lets a future return `Pending`
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut context = Context::from_waker(Waker::noop());
+        match pin!(future).poll(&mut context) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("pending"),
+        }
+    }
+
+    /// Registers the init sequence leaves behind
+    fn state(radio: &SimRadio) -> Vec<Vec<u8>> {
+        let bank0 = (0..=0x1D).map(|addr| radio.register(addr));
+        bank0.chain((0..=0x0E).map(|addr| radio.bank1_register(addr))).collect()
+    }
+
+    #[test]
+    fn detects_every_variant() {
+        for variant in VARIANTS {
+            let radio = SimRadio::with_variant(variant);
+            let mut standby = block_on(asynch::NRF24L01::new(radio.ce(), radio.irq(), radio.spi())).unwrap();
+            assert_eq!(standby.get_variant(), detected(variant));
+            check(variant, &radio);
+
+            // Same registers as the blocking driver
+            let blocking = SimRadio::with_variant(variant);
+            NRF24L01::new(blocking.ce(), blocking.spi()).unwrap();
+            assert_eq!(state(&radio), state(&blocking), "{:?}", variant);
+        }
+    }
+    #[test]
+    fn set_variant_and_bank1_register_bounds() {
+        let radio = SimRadio::with_variant(ChipVariant::Bk2423);
+        let mut standby = block_on(asynch::NRF24L01::new(radio.ce(), radio.irq(), radio.spi())).unwrap();
+        block_on(standby.write_bank1_register(0x00, &[0; 4])).unwrap();
+        block_on(standby.set_variant(ChipVariant::Bk2421)).unwrap();
+        assert_eq!(standby.get_variant(), ChipVariant::Bk2421);
+        check(ChipVariant::Bk2421, &radio);
+
+        match block_on(standby.read_bank1_register(0x20, &mut [0; 4])) {
+            Err(Error::Config(ConfigError::Register(0x20))) => {}
+            result => panic!("{:?}", result),
+        }
+        match block_on(standby.write_bank1_register(0x00, &[0; 33])) {
+            Err(Error::Config(ConfigError::RegisterLength(33))) => {}
+            result => panic!("{:?}", result),
+        }
+        check(ChipVariant::Bk2421, &radio);
+    }
+}